@@ -171,6 +171,14 @@ static inline int page_map(size_t viraddr, size_t phyaddr, size_t npages, size_t
  */
 int page_unmap(size_t viraddr, size_t npages);
 
+/** @brief Check if the current task is able to write to a range
+ *
+ * @param start Range's virtual start address
+ * @param end Range's virtual end address
+ * @return 1 if the range is writable, otherwise 0
+ */
+int page_writable(size_t start, size_t end);
+
 /** @brief Change the page permission in the page tables of the current task
  *
  * Applies given flags noted in the 'flags' parameter to
@@ -186,6 +194,9 @@ int page_unmap(size_t viraddr, size_t npages);
  */
 int page_set_flags(size_t viraddr, uint32_t npages, int flags);
 
+/** @brief Unmap a range and return the mapped frames to the page allocator */
+int page_unmap_put(size_t viraddr, size_t npages);
+
 /** @brief Handler to map on demand pages for the heap
  *
  * * @param viraddr Virtual address, which triggers the page fault
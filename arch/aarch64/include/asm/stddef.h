@@ -129,13 +129,17 @@ struct state {
 	uint64_t x30;
 } __attribute__((aligned(16)));
 
-typedef struct {
+typedef struct sigaltstack {
 	void	*ss_sp;		/* Stack base or pointer.  */
+	int	ss_flags;	/* Flags.  */
+	size_t	ss_size;	/* Stack size.  */
 } stack_t;
 
 const int32_t is_uhyve(void);
 static inline const int32_t is_single_kernel(void) { return 1; }
 static inline const char* get_cmdline(void) { return 0; }
+static inline const void* get_initrd(size_t* size) { return 0; }
+static inline uint32_t get_uhyve_features(void) { return 0; }
 static inline int init_rcce(void) { return 0; }
 void print_cpu_status(int isle);
 
@@ -59,6 +59,8 @@ extern handle_fpu_state fpu_init;*/
 static inline void save_fpu_state(union fpu_state* state){}
 static inline void restore_fpu_state(union fpu_state* state){}
 static inline void fpu_init(union fpu_state* state){}
+static inline void flush_fpu_state(union fpu_state* state){}
+static inline void load_fpu_state(union fpu_state* state){}
 
 #ifdef __cplusplus
 }
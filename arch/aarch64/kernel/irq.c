@@ -329,8 +329,8 @@ size_t** do_fiq(void *regs)
 	if ((vector == INT_PPI_NSPHYS_TIMER) || (vector == RESCHED_INT)) {
 		// a timer interrupt may have caused unblocking of tasks
 		ret = scheduler();
-	} else if (get_highest_priority() > per_core(current_task)->prio) {
-		// there's a ready task with higher priority
+	} else if (need_reschedule()) {
+		// there's a ready task with higher priority or an earlier deadline
 		ret = scheduler();
 	}
 
@@ -350,8 +350,8 @@ size_t** do_irq(void *regs)
 	// Check if timers have expired that would unblock tasks
 	check_workqueues_in_irqhandler(vector);
 
-	if (get_highest_priority() > per_core(current_task)->prio) {
-		// there's a ready task with higher priority
+	if (need_reschedule()) {
+		// there's a ready task with higher priority or an earlier deadline
 		ret = scheduler();
 	}
 
@@ -26,17 +26,8 @@
  */
 #include <hermit/signal.h>
 
-int hermit_signal(signal_handler_t handler)
+void signal_notify(struct task* task)
 {
-	//task_t* curr_task = per_core(current_task);
-	//curr_task->signal_handler = handler;
-
-	return 0;
-}
-
-int hermit_kill(tid_t dest, int signum)
-{
-	return 0;
 }
 
 void signal_init(void)
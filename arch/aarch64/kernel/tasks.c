@@ -103,12 +103,9 @@ static int thread_entry(void* arg, size_t ep)
 	//vma_dump();
 
 	entry_point_t call_ep = (entry_point_t) ep;
-	call_ep(arg);
-	/* After finishing the task, we will return here and call the
-	   cleanup function, which calls the scheduler */
-	leave_kernel_task();
-
-	return 0;
+	/* After finishing the task, we call the cleanup function, which
+	   calls the scheduler. The return value is the exit code. */
+	do_exit(call_ep(arg));
 }
 
 size_t* get_current_stack(void)
@@ -211,6 +208,11 @@ void wakeup_core(uint32_t core_id)
 	// Currently not required...
 }
 
+void preempt_core(uint32_t core_id)
+{
+	// Currently not required...
+}
+
 void shutdown_system(void)
 {
 	LOG_INFO("Try to shutdown system\n");
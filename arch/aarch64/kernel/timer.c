@@ -33,7 +33,11 @@
 #include <hermit/errno.h>
 #include <hermit/spinlock.h>
 #include <hermit/logging.h>
+#include <hermit/vma.h>
+#include <hermit/time_page.h>
 #include <asm/irq.h>
+#include <asm/page.h>
+#include <asm/uhyve.h>
 
 /*
  * This will keep track of how many ticks the system
@@ -48,9 +52,10 @@ extern int32_t boot_processor;
 
 #define MHZ 1000000
 
+static uint64_t boot_tsc __attribute__ ((section(".data"))) = 0;
+
 #ifdef DYNAMIC_TICKS
 DEFINE_PER_CORE(uint64_t, last_tsc, 0);
-static uint64_t boot_tsc __attribute__ ((section(".data"))) = 0;
 
 void check_ticks(void)
 {
@@ -105,6 +110,19 @@ int timer_deadline(uint32_t ticks)
 	return 0;
 }
 
+int timer_deadline_cycles(uint64_t cycles)
+{
+	if (cycles > 0xFFFFFFFFULL)
+		cycles = 0xFFFFFFFFULL;
+	else if (!cycles)
+		cycles = 1;
+
+	set_cntp_tval((uint32_t) cycles);
+	set_cntp_ctl(1);
+
+	return 0;
+}
+
 void timer_disable(void)
 {
 	/* stop timer */
@@ -193,14 +211,96 @@ int timer_wait(unsigned int ticks)
 
 int clock_init(void)
 {
-#ifdef DYNAMIC_TICKS
 	if (!boot_tsc)
 		boot_tsc = get_cntpct();
-#endif
 
 	return 0;
 }
 
+uint64_t get_boot_tsc(void)
+{
+	return boot_tsc;
+}
+
+uint64_t get_tsc_frequency(void)
+{
+	return freq_hz;
+}
+
+/// PL031 real time clock of QEMU's virt machine
+#define PL031_BASE		0x09010000ULL
+/// data register, seconds since the epoch
+#define PL031_DR		0x000
+/// peripheral and PrimeCell identification registers
+#define PL031_PERIPH_ID0	0xFE0
+#define PL031_PERIPH_ID1	0xFE4
+#define PL031_CELL_ID0		0xFF0
+
+static volatile uint8_t* rtc = NULL;
+
+static inline uint32_t rtc_read(uint32_t reg)
+{
+	return *((volatile uint32_t*) (rtc + reg));
+}
+
+static int rtc_init(void)
+{
+	uint32_t cell_id = 0;
+	int ret;
+
+	ret = vma_add(PL031_BASE, PL031_BASE+PAGE_SIZE, VMA_READ|VMA_WRITE);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	ret = page_map(PL031_BASE, PL031_BASE, 1, PG_GLOBAL|PG_RW|PG_DEVICE);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	rtc = (volatile uint8_t*) PL031_BASE;
+
+	// PrimeCell id 0xB105F00D and part number 0x031
+	for(uint32_t i=0; i<4; i++)
+		cell_id |= (rtc_read(PL031_CELL_ID0 + i*4) & 0xFF) << (i*8);
+	if ((cell_id != 0xB105F00D) || ((rtc_read(PL031_PERIPH_ID0) & 0xFF) != 0x31)
+	    || ((rtc_read(PL031_PERIPH_ID1) & 0x0F) != 0x0)) {
+		LOG_WARNING("Unable to find the PL031 real time clock at 0x%llx\n", PL031_BASE);
+		rtc = NULL;
+		return -ENODEV;
+	}
+
+	LOG_INFO("Map PL031 real time clock at 0x%llx\n", PL031_BASE);
+
+	return 0;
+}
+
+uint64_t get_wallclock(void)
+{
+	static int rtc_probed = 0;
+
+	if (is_uhyve()) {
+		if (get_uhyve_features() & UHYVE_FEATURE_TIME) {
+			uhyve_time_t uhyve_args = {0, 0};
+
+			uhyve_send(UHYVE_PORT_TIME, (unsigned)virt_to_phys((size_t)&uhyve_args));
+
+			return uhyve_args.sec * 1000000000ULL + uhyve_args.nsec;
+		}
+
+		// uhyve doesn't emulate the PL031
+		return 0;
+	}
+
+	if (!rtc_probed) {
+		rtc_probed = 1;
+		rtc_init();
+	}
+
+	if (!rtc)
+		return 0;
+
+	return rtc_read(PL031_DR) * 1000000000ULL;
+}
+
 int timer_init(void)
 {
 #ifdef DYNAMIC_TICKS
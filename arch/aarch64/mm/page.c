@@ -41,6 +41,7 @@
 #include <hermit/spinlock.h>
 #include <hermit/tasks.h>
 #include <hermit/logging.h>
+#include <hermit/vma.h>
 
 #include <asm/irq.h>
 #include <asm/page.h>
@@ -95,6 +96,33 @@ int page_set_flags(size_t viraddr, uint32_t npages, int flags)
 	return -EINVAL;
 }
 
+//TODO: code is missing
+int page_unmap_put(size_t viraddr, size_t npages)
+{
+	return -EINVAL;
+}
+
+int page_writable(size_t start, size_t end)
+{
+	task_t* task = per_core(current_task);
+	size_t viraddr;
+	uint32_t flags;
+
+	for(viraddr=start & PAGE_MASK; viraddr<end; viraddr+=PAGE_SIZE) {
+		if ((viraddr >= (size_t) &kernel_start) && (viraddr < (size_t) &kernel_start + image_size))
+			continue;
+		// the heap is mapped on demand
+		if ((task->heap) && (viraddr >= task->heap->start) && (viraddr < task->heap->end))
+			continue;
+
+		// TODO: check the access permissions of the page tables
+		if (vma_get_flags(viraddr, &flags) || !(flags & VMA_WRITE) || (flags & VMA_NO_ACCESS))
+			return 0;
+	}
+
+	return 1;
+}
+
 int __page_map(size_t viraddr, size_t phyaddr, size_t npages, size_t bits)
 {
 	int lvl, ret = -ENOMEM;
@@ -146,5 +146,10 @@ typedef struct multiboot_mod_list multiboot_module_t;
 extern const multiboot_info_t* const mb_info;
 extern char* cmdline;
 extern size_t cmdsize;
+/// Physical address and size of the initrd, which is moved by the loader
+extern size_t initrd_start;
+extern size_t initrd_size;
+/// Features of uhyve, which are set by uhyve in the boot header
+extern uint32_t uhyve_features;
 
 #endif
@@ -201,4 +201,27 @@ int page_unmap(size_t viraddr, size_t npages);
  */
 int page_set_flags(size_t viraddr, uint32_t npages, int flags);
 
+/** @brief Unmap a range and return the mapped frames to the page allocator
+ *
+ * Only pages of 4 KB are released, missing entries are ignored.
+ *
+ * @param viraddr Range's virtual start address
+ * @param npages The range's size in pages
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) on failure.
+ */
+int page_unmap_put(size_t viraddr, size_t npages);
+
+/** @brief Check if the current task is able to write to a range
+ *
+ * Pages, which are mapped on demand by the page fault handler, are
+ * writable as well.
+ *
+ * @param start Range's virtual start address
+ * @param end Range's virtual end address
+ * @return 1 if the range is writable, otherwise 0
+ */
+int page_writable(size_t start, size_t end);
+
 #endif
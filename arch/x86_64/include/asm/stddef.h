@@ -200,7 +200,7 @@ struct state {
 	uint64_t ss;
 };
 
-typedef struct {
+typedef struct sigaltstack {
 	void	*ss_sp;		/* Stack base or pointer.  */
 	int	ss_flags;	/* Flags.  */
 	size_t	ss_size;	/* Stack size.  */
@@ -210,6 +210,10 @@ const int32_t is_uhyve(void);
 const int32_t is_single_kernel(void);
 
 const char* get_cmdline(void);
+/** @brief Returns the initrd, which is passed by the loader, or NULL */
+const void* get_initrd(size_t* size);
+/** @brief Returns the features, which are announced by uhyve */
+uint32_t get_uhyve_features(void);
 int init_rcce(void);
 void print_cpu_status(int isle);
 
@@ -62,6 +62,33 @@ void switch_context(size_t** stack);
  */
 int create_default_frame(task_t* task, entry_point_t ep, void* arg, uint32_t core_id);
 
+/** @brief Inject the handler of the next deliverable signal
+ *
+ * @param task The receiver of the signal
+ * @param s The state of the interrupted task or NULL, if the handler
+ *          is injected into the saved state of the task
+ * @return
+ *  - 0 on success or if no signal is deliverable
+ *  - -EFAULT (-14) if the stack of the handler isn't writable, the
+ *    signal is discarded
+ */
+struct state;
+int signal_deliver(task_t* task, struct state* s);
+
+/** @brief Deliver the signal of a fault to the current task
+ *
+ * @param s The state of the faulting task
+ * @param signum The signal (SIGSEGV, SIGBUS, SIGFPE, SIGILL or SIGTRAP)
+ * @param code Value of si_code
+ * @param addr Faulting address
+ * @return
+ *  - 0 on success, the handler runs after returning from the exception
+ *  - -EINVAL (-22) if the task hasn't installed a handler
+ *  - -EPERM (-1) if the fault occurred within the kernel image
+ *  - -EFAULT (-14) if the stack of the handler isn't writable
+ */
+int signal_fault(struct state* s, int signum, int code, void* addr);
+
 /** @brief Jump to user code
  *
  * This function runs the user code after stopping it just as if
@@ -39,6 +39,7 @@
 
 #include <hermit/stddef.h>
 #include <asm/processor.h>
+#include <sys/signal.h>
 
 #ifdef __cplusplus
 extern "C" {
@@ -152,6 +153,15 @@ typedef struct ucontext {
 	fenv_t		uc_fenv;
 	struct ucontext	*uc_link;
 	stack_t		uc_stack;
+	/// signal mask, which is restored after a signal handler
+	sigset_t	uc_sigmask;
+	/// registers of an interrupted context, which aren't part of uc_mregs
+	uint64_t	uc_rax;
+	uint64_t	uc_r10;
+	uint64_t	uc_r11;
+	uint64_t	uc_rflags;
+	/// FPU and SSE registers of an interrupted context
+	union fpu_state	uc_fpregs;
 } ucontext_t;
 
 typedef void (*handle_fpu_state)(union fpu_state* state);
@@ -160,6 +170,32 @@ extern handle_fpu_state save_fpu_state;
 extern handle_fpu_state restore_fpu_state;
 extern handle_fpu_state fpu_init;
 
+/** @brief Save the FPU registers of this core, even if the TS flag is set
+ *
+ * Required to migrate the owner of the FPU to another core.
+ */
+static inline void flush_fpu_state(union fpu_state* state)
+{
+	const size_t cr0 = read_cr0();
+
+	clts();
+	save_fpu_state(state);
+	write_cr0(cr0);
+}
+
+/** @brief Load the FPU registers of this core, even if the TS flag is set
+ *
+ * Required to replace the state of the owner of the FPU.
+ */
+static inline void load_fpu_state(union fpu_state* state)
+{
+	const size_t cr0 = read_cr0();
+
+	clts();
+	restore_fpu_state(state);
+	write_cr0(cr0);
+}
+
 #ifdef __cplusplus
 }
 #endif
@@ -334,6 +334,30 @@ int apic_timer_deadline(uint32_t ticks)
 	return -EINVAL;
 }
 
+int apic_timer_deadline_cycles(uint64_t cycles)
+{
+	const uint64_t freq = 1000000ULL * (uint64_t) get_cpu_frequency();
+
+	if (BUILTIN_EXPECT(apic_is_enabled() && icr && freq, 1)) {
+		// APIC timer increments per second
+		const uint64_t rate = (uint64_t) icr * TIMER_FREQ;
+		uint64_t counter = (cycles / freq) * rate + ((cycles % freq) * rate) / freq;
+
+		if (counter > 0xFFFFFFFFULL)
+			counter = 0xFFFFFFFFULL;
+		else if (!counter)
+			counter = 1;
+
+		LOG_DEBUG("timer oneshot in %zd cycles at core %d\n", cycles, CORE_ID);
+		lapic_timer_oneshot();
+		lapic_timer_set_counter((uint32_t) counter);
+
+		return 0;
+	}
+
+	return -EINVAL;
+}
+
 int apic_disable_timer(void)
 {
 	if (BUILTIN_EXPECT(!apic_is_enabled(), 0))
@@ -76,6 +76,9 @@ align 4
     global hcgateway
     global hcmask
     global host_logical_addr
+    global initrd_start
+    global initrd_size
+    global uhyve_features
     base dq 0
     limit dq 0
     cpu_freq dd 0
@@ -107,6 +110,9 @@ align 4
     hcgateway db 10,0,5,1
     hcmask db 255,255,255,0
     host_logical_addr dq 0
+    initrd_start dq 0
+    initrd_size dq 0
+    uhyve_features dd 0
 
 ; Bootstrap page tables are used during the initialization.
 align 4096
@@ -703,6 +709,17 @@ is_single_kernel:
     ret
 
 
+extern sighandler_restore
+
+global sighandler_return
+sighandler_return:
+    ; rsp points to the signal frame after returning from the signal handler
+	mov rdi, rsp
+	call sighandler_restore
+	; sighandler_restore() restores the signal mask and returns the saved state
+	mov rsp, rax
+	jmp sighandler_epilog
+
 global sighandler_epilog
 sighandler_epilog:
     ; restore only those registers that might have changed between returning
@@ -724,11 +741,10 @@ sighandler_epilog:
 	pop rdx
 	pop rcx
 	pop rax
-	add rsp, 4 * 8		; ignore int_no, error, rip, cs
-	popfq
-	add rsp, 2 * 8		; ignore userrsp, ss
+	add rsp, 2 * 8		; ignore int_no, error
 
-    jmp [rsp - 5 * 8]	; jump to rip from saved state
+	; the signal handler could have changed rip and rsp of the saved state
+	iretq
 
 
 global replace_boot_stack
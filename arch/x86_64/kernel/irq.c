@@ -317,8 +317,8 @@ size_t** irq_handler(struct state *s)
 	if ((s->int_no == 32) || (s->int_no == 123)) {
 		// a timer interrupt may have caused unblocking of tasks
 		ret = scheduler();
-	} else if ((s->int_no >= 32) && (get_highest_priority() > per_core(current_task)->prio)) {
-		// there's a ready task with higher priority
+	} else if ((s->int_no >= 32) && need_reschedule()) {
+		// there's a ready task with higher priority or an earlier deadline
 		ret = scheduler();
 	}
 
@@ -43,6 +43,7 @@
 #include <asm/irq.h>
 #include <asm/idt.h>
 #include <asm/apic.h>
+#include <asm/tasks.h>
 
 /*
  * These are function prototypes for all of the exception
@@ -209,6 +210,51 @@ static void arch_fpu_handler(struct state *s)
 	fpu_handler();
 }
 
+/* signal and si_code of an exception, the exceptions without signal terminate the task */
+static const struct {
+	int signum;
+	int code;
+} exception_signals[32] = {
+	[0] = {SIGFPE, FPE_INTDIV}, [1] = {SIGTRAP, TRAP_TRACE},
+	[3] = {SIGTRAP, TRAP_BRKPT}, [4] = {SIGFPE, FPE_INTOVF},
+	[5] = {SIGSEGV, SI_KERNEL}, [6] = {SIGILL, ILL_ILLOPN},
+	[12] = {SIGBUS, BUS_ADRERR}, [13] = {SIGSEGV, SI_KERNEL},
+	[16] = {SIGFPE, FPE_FLTINV}, [17] = {SIGBUS, BUS_ADRALN},
+	[19] = {SIGFPE, FPE_FLTINV}
+};
+
+/* decode si_code of a floating-point exception from the unmasked exception flags */
+static int fpu_exception_code(uint64_t int_no)
+{
+	uint32_t flags;
+
+	if (int_no == 19) {
+		uint32_t mxcsr;
+
+		// the mask bits of MXCSR are located 7 bits above the flags
+		asm volatile ("stmxcsr %0" : "=m"(mxcsr));
+		flags = mxcsr & ~(mxcsr >> 7) & 0x3F;
+	} else {
+		uint16_t cw, sw;
+
+		asm volatile ("fnstcw %0; fnstsw %1" : "=m"(cw), "=m"(sw));
+		flags = sw & ~cw & 0x3F;
+	}
+
+	if (flags & 0x01)	// invalid operation
+		return FPE_FLTINV;
+	if (flags & 0x04)	// divide by zero
+		return FPE_FLTDIV;
+	if (flags & 0x08)	// overflow
+		return FPE_FLTOVF;
+	if (flags & 0x12)	// underflow or denormal operand
+		return FPE_FLTUND;
+	if (flags & 0x20)	// precision
+		return FPE_FLTRES;
+
+	return FPE_FLTINV;
+}
+
 /*
  * All of our Exception handling Interrupt Service Routines will
  * point to this function. This will tell us what exception has
@@ -219,6 +265,16 @@ static void arch_fpu_handler(struct state *s)
  */
 static void arch_fault_handler(struct state *s)
 {
+	// does the task handle the fault itself?
+	if ((s->int_no < 32) && exception_signals[s->int_no].signum) {
+		int code = exception_signals[s->int_no].code;
+
+		if ((s->int_no == 16) || (s->int_no == 19))
+			code = fpu_exception_code(s->int_no);
+
+		if (!signal_fault(s, exception_signals[s->int_no].signum, code, (void*) s->rip))
+			return;
+	}
 
 	if (s->int_no < 32)
 		LOG_INFO("%s", exception_messages[s->int_no]);
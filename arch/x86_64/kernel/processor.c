@@ -52,6 +52,7 @@ extern atomic_int32_t current_boot_id;
 
 islelock_t* rcce_lock = NULL;
 rcce_mpb_t* rcce_mpb = NULL;
+shm_segment_t* rcce_shm = NULL;
 
 extern void isrsyscall(void);
 
@@ -63,6 +64,40 @@ const char* get_cmdline(void)
 	return NULL;
 }
 
+const void* get_initrd(size_t* size)
+{
+	static size_t addr = 0;
+	size_t npages, flags = PG_GLOBAL;
+
+	if (!initrd_size)
+		return NULL;
+
+	if (!addr) {
+		npages = PAGE_CEIL(initrd_size) >> PAGE_BITS;
+		addr = vma_alloc(npages << PAGE_BITS, VMA_READ|VMA_CACHEABLE);
+		if (BUILTIN_EXPECT(!addr, 0))
+			return NULL;
+		if (has_nx())
+			flags |= PG_XD;
+		if (page_map(addr, initrd_start, npages, flags)) {
+			vma_free(addr, addr + (npages << PAGE_BITS));
+			addr = 0;
+			return NULL;
+		}
+
+		LOG_INFO("Map initrd at %p (size 0x%zx)\n", addr, initrd_size);
+	}
+
+	*size = initrd_size;
+
+	return (const void*) addr;
+}
+
+uint32_t get_uhyve_features(void)
+{
+	return is_uhyve() ? uhyve_features : 0;
+}
+
 int init_rcce(void)
 {
 	size_t addr, flags = PG_GLOBAL|PG_RW;
@@ -79,8 +114,9 @@ int init_rcce(void)
 
 	rcce_lock = (islelock_t*) addr;
 	rcce_mpb = (rcce_mpb_t*) (addr + CACHE_LINE*(RCCE_MAXNP+1));
+	rcce_shm = (shm_segment_t*) (addr + PAGE_SIZE - MAX_SHM_SEGMENTS*sizeof(shm_segment_t));
 
-	LOG_INFO("Map rcce_lock at %p and rcce_mpb at %p\n", rcce_lock, rcce_mpb);
+	LOG_INFO("Map rcce_lock at %p, rcce_mpb at %p and rcce_shm at %p\n", rcce_lock, rcce_mpb, rcce_shm);
 
 	return 0;
 }
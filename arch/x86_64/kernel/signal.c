@@ -29,196 +29,314 @@
 #include <hermit/spinlock.h>
 #include <hermit/stdio.h>
 #include <hermit/tasks.h>
-#include <hermit/dequeue.h>
+#include <hermit/string.h>
+#include <hermit/errno.h>
 #include <hermit/logging.h>
 #include <asm/apic.h>
 #include <asm/irq.h>
-#include <asm/atomic.h>
+#include <asm/tasks.h>
+#include <asm/page.h>
 
 #define SIGNAL_IRQ (32 + 82)
-#define SIGNAL_BUFFER_SIZE (16)
 
-// Per-core signal queue and buffer
-static dequeue_t signal_queue[MAX_CORES];
-static sig_t signal_buffer[MAX_CORES][SIGNAL_BUFFER_SIZE];
+/* Note that linker symbols are not variables, they have no memory
+ * allocated for maintaining a value, rather their address is their value. */
+extern const void kernel_start;
 
-static void _signal_irq_handler(struct state* s)
-{
-	LOG_DEBUG("Enter _signal_irq_handler() on core %d\n", CORE_ID);
+/// arithmetic flags and direction flag, which a handler is allowed to change
+#define RFLAGS_USER_MASK	0xCD5UL
 
-	sig_t signal;
-	task_t* dest_task;
-	task_t* curr_task = per_core(current_task);
+/// upper bound of the stack, which is required to inject a handler
+#define SIGNAL_STACK_SIZE	(2*sizeof(struct state) + sizeof(ucontext_t) + sizeof(signal_frame_t) + 0x80)
 
-	while(dequeue_pop(&signal_queue[CORE_ID], &signal) == 0) {
-		LOG_DEBUG("  Deliver signal %d\n", signal.signum);
-
-		if(get_task(signal.dest, &dest_task) == 0) {
-			LOG_DEBUG("  Found valid task with ID %d\n", dest_task->id);
-
-			// only service signals for tasks on this core
-			if(dest_task->last_core != CORE_ID) {
-				LOG_DEBUG("  Signal dispatched to wrong CPU! Dropping it ...\n");
-				continue;
-			}
-
-			if(dest_task->signal_handler) {
-				LOG_DEBUG("  Has signal handler (%p)\n", dest_task->signal_handler);
-
-				/* We will inject the signal handler into the control flow when
-				 * the task will continue it's exection the next time. There are
-				 * 3 cases how the task was interrupted:
-				 *
-				 *   1. call to reschedule() by own intend
-				 *   2. a timer interrupt lead to rescheduling to another task
-				 *   3. this IRQ interrupted the task
-				 *
-				 * Depending on those cases, the state of the task can either be
-				 * saved to it's own stack (1.), it's interrupt stack (IST, 2.)
-				 * or the stack of this interrupt handler (3.).
-				 *
-				 * When the signal handler finishes it's execution, we need to
-				 * restore the task state, so we make the signal handler return
-				 * first to sighandler_epilog() which then restores the original
-				 * state.
-				 *
-				 * For cases 2+3, when task was interrupted by an IRQ, we modify
-				 * the existing state on the interrupt stack to execute the
-				 * signal handler, wherease in case 1, we craft a new state and
-				 * place it on top of the task stack.
-				 *
-				 * The task stack will have the following layout:
-				 *
-				 * |         ...          | <- task's rsp before interruption
-				 * |----------------------|
-				 * |     saved state      |
-				 * |----------------------|
-				 * | &sighandler_epilog() | <- rsp after IRQ
-				 * |----------------------|
-				 * |----------------------| Only for case 1:
-				 * | signal handler state | Craft signal handler state, so it
-				 * |----------------------| executes before task is continued
-				 */
-
-				size_t* task_stackptr;
-				struct state *task_state, *sighandler_state;
-
-				const int task_is_running = dest_task == curr_task;
-				LOG_DEBUG("  Task is%s running\n", task_is_running ? "" : " not");
-
-				// location of task state depends of type of interruption
-				task_state = (!task_is_running) ?
-				/* case 1+2: */	(struct state*) dest_task->last_stack_pointer :
-				/* case 3:   */ s;
-
-				// pseudo state pushed by reschedule() has INT no. 0
-				const int state_on_task_stack = task_state->int_no == 0;
-
-				if(state_on_task_stack) {
-					LOG_DEBUG("  State is already on task stack\n");
-					// stack pointer was saved by switch_context() after saving
-					// task state to task stack
-					task_stackptr = dest_task->last_stack_pointer;
-				} else {
-					// stack pointer is last rsp, since task state is saved to
-					// interrupt stack
-					task_stackptr = (size_t*) task_state->rsp;
-
-					LOG_DEBUG("  Copy state to task stack\n");
-					task_stackptr -= sizeof(struct state) / sizeof(size_t);
-					memcpy(task_stackptr, task_state, sizeof(struct state));
-				}
-
-				// signal handler will return to this function to restore
-				// register state
-				extern void sighandler_epilog();
-				*(--task_stackptr) = (uint64_t) &sighandler_epilog;
-				size_t* sighandler_rsp = task_stackptr;
-
-				if(state_on_task_stack) {
-					LOG_DEBUG("  Craft state for signal handler on task stack\n");
-
-					// we actually only care for ss, rflags, cs, fs and gs
-					task_stackptr -= sizeof(struct state) / sizeof(size_t);
-					sighandler_state = (struct state*) task_stackptr;
-					memcpy(sighandler_state, task_state, sizeof(struct state));
-
-					// advance stack pointer so signal handler state will be
-					// restored first
-					dest_task->last_stack_pointer = (size_t*) sighandler_state;
-				} else {
-					LOG_DEBUG("  Reuse state on IST for signal handler\n");
-					sighandler_state = task_state;
-				}
-
-				// update rsp so that sighandler_epilog() will be executed
-				// after signal handler
-				sighandler_state->rsp = (uint64_t) sighandler_rsp;
-				sighandler_state->userrsp = sighandler_state->rsp;
-
-				// call signal handler instead of continuing task's execution
-				sighandler_state->rdi = (uint64_t) signal.signum;
-				sighandler_state->rip = (uint64_t) dest_task->signal_handler;
-			} else {
-				LOG_DEBUG("  No signal handler installed\n");
-			}
-		} else {
-			LOG_DEBUG("  Task %d has already died\n", signal.dest);
-		}
-	}
-	LOG_DEBUG("Leave _signal_irq_handler() on core %d\n", CORE_ID);
+/* describe the interrupted state for SA_SIGINFO handlers */
+static void context_save(ucontext_t* uc, const struct state* s, sigset_t mask)
+{
+	memset(uc, 0x00, sizeof(ucontext_t));
+
+	uc->uc_mregs.r15 = s->r15;
+	uc->uc_mregs.r14 = s->r14;
+	uc->uc_mregs.r13 = s->r13;
+	uc->uc_mregs.r12 = s->r12;
+	uc->uc_mregs.r9 = s->r9;
+	uc->uc_mregs.r8 = s->r8;
+	uc->uc_mregs.rdi = s->rdi;
+	uc->uc_mregs.rsi = s->rsi;
+	uc->uc_mregs.rbp = s->rbp;
+	uc->uc_mregs.rbx = s->rbx;
+	uc->uc_mregs.rdx = s->rdx;
+	uc->uc_mregs.rcx = s->rcx;
+	uc->uc_mregs.rsp = s->userrsp;
+	uc->uc_mregs.rip = s->rip;
+	uc->uc_rax = s->rax;
+	uc->uc_r10 = s->r10;
+	uc->uc_r11 = s->r11;
+	uc->uc_rflags = s->rflags;
+	uc->uc_sigmask = mask;
 }
 
-int hermit_signal(signal_handler_t handler)
+/* the handler is able to modify the interrupted context, e.g. to skip a faulting instruction */
+static void context_restore(struct state* s, const ucontext_t* uc)
 {
-	task_t* curr_task = per_core(current_task);
-	curr_task->signal_handler = handler;
+	s->r15 = uc->uc_mregs.r15;
+	s->r14 = uc->uc_mregs.r14;
+	s->r13 = uc->uc_mregs.r13;
+	s->r12 = uc->uc_mregs.r12;
+	s->r9 = uc->uc_mregs.r9;
+	s->r8 = uc->uc_mregs.r8;
+	s->rdi = uc->uc_mregs.rdi;
+	s->rsi = uc->uc_mregs.rsi;
+	s->rbp = uc->uc_mregs.rbp;
+	s->rbx = uc->uc_mregs.rbx;
+	s->rdx = uc->uc_mregs.rdx;
+	s->rcx = uc->uc_mregs.rcx;
+	s->rsp = s->userrsp = uc->uc_mregs.rsp;
+	s->rip = uc->uc_mregs.rip;
+	s->rax = uc->uc_rax;
+	s->r10 = uc->uc_r10;
+	s->r11 = uc->uc_r11;
+	s->rflags = (s->rflags & ~RFLAGS_USER_MASK) | (uc->uc_rflags & RFLAGS_USER_MASK);
+}
 
-	return 0;
+/* reserved bits, which are modified by the handler, raise a #GP while restoring the FPU state */
+static void fpu_sanitize(union fpu_state* fpu)
+{
+	const uint32_t mxcsr_mask = fpu->fxsave.mxcsr_mask ? fpu->fxsave.mxcsr_mask : 0xFFBF;
+
+	fpu->fxsave.mxcsr &= mxcsr_mask;
+	fpu->xsave.hdr.xcomp_bv = 0;
+	memset(fpu->xsave.hdr.reserved, 0x00, sizeof(fpu->xsave.hdr.reserved));
 }
 
-int hermit_kill(tid_t dest, int signum)
+void* sighandler_restore(signal_frame_t* frame)
 {
-	task_t* task;
-	if(BUILTIN_EXPECT(get_task(dest, &task), 0)) {
-		LOG_ERROR("Trying to send signal %d to invalid task %d\n", signum, dest);
-		return -ENOENT;
-	}
+	ucontext_t* uc = (ucontext_t*) frame->context;
 
-	const tid_t dest_core = task->last_core;
+	context_restore((struct state*) frame->state, uc);
+	fpu_sanitize(&uc->uc_fpregs);
+	task_fpu_restore(per_core(current_task), &uc->uc_fpregs);
+	frame->mask = uc->uc_sigmask;
 
-	LOG_DEBUG("Send signal %d from task %d (core %d) to task %d (core %d)\n",
-	        signum, per_core(current_task)->id, CORE_ID, dest, dest_core);
+	return signal_return(frame);
+}
 
-	if(task == per_core(current_task)) {
-		LOG_DEBUG("  Deliver signal to itself, call handler immediately\n");
+int signal_deliver(task_t* dest_task, struct state* s)
+{
+	signal_frame_t frame;
+	struct sigaction act;
+	const int signum = signal_dequeue(dest_task, &frame, &act);
 
-		if(task->signal_handler) {
-			task->signal_handler(signum);
-		}
+	if (!signum) {
+		LOG_DEBUG("  No deliverable signal\n");
 		return 0;
 	}
 
-	sig_t signal = {dest, signum};
-	if(dequeue_push(&signal_queue[dest_core], &signal)) {
-		LOG_ERROR("  Cannot push signal to task's signal queue, dropping it\n");
-		return -ENOMEM;
+	LOG_DEBUG("  Deliver signal %d to handler %p\n", signum, act.sa_handler);
+
+	/* We will inject the signal handler into the control flow when
+	 * the task will continue it's exection the next time. There are
+	 * 3 cases how the task was interrupted:
+	 *
+	 *   1. call to reschedule() by own intend
+	 *   2. a timer interrupt lead to rescheduling to another task
+	 *   3. this IRQ interrupted the task
+	 *
+	 * In the cases 1+2, the handler is injected when the task is switched
+	 * in again (s == NULL), otherwise the IRQ passes its state s.
+	 *
+	 * Depending on those cases, the state of the task can either be
+	 * saved to it's own stack (1.), it's interrupt stack (IST, 2.)
+	 * or the stack of this interrupt handler (3.).
+	 *
+	 * When the signal handler finishes it's execution, we need to
+	 * restore the signal mask and the task state, so we make the signal
+	 * handler return first to sighandler_return(), which passes the
+	 * signal frame to sighandler_restore() and jumps to sighandler_epilog()
+	 * to restore the (possibly modified) original state.
+	 *
+	 * For cases 2+3, when task was interrupted by an IRQ, we modify
+	 * the existing state on the interrupt stack to execute the
+	 * signal handler, wherease in case 1, we craft a new state and
+	 * place it on top of the task stack.
+	 *
+	 * With SA_ONSTACK, the saved state is moved to the alternate signal
+	 * stack and the handler runs on it.
+	 *
+	 * The FPU registers are part of the ucontext, because the handler
+	 * is free to use the FPU.
+	 *
+	 * The task stack will have the following layout:
+	 *
+	 * |         ...          | <- task's rsp before interruption
+	 * |----------------------|
+	 * |     saved state      |
+	 * |----------------------|
+	 * |       ucontext       | <- 64 byte aligned
+	 * |----------------------|
+	 * |     signal frame     | <- 16 byte aligned
+	 * |----------------------|
+	 * | &sighandler_return() | <- rsp after IRQ
+	 * |----------------------|
+	 * |----------------------| Only for case 1:
+	 * | signal handler state | Craft signal handler state, so it
+	 * |----------------------| executes before task is continued
+	 */
+
+	size_t* task_stackptr;
+	signal_frame_t* sigframe;
+	ucontext_t* uc;
+	struct state *task_state, *sighandler_state;
+
+	const int task_is_running = s != NULL;
+	LOG_DEBUG("  Task is%s running\n", task_is_running ? "" : " not");
+
+	// location of task state depends of type of interruption
+	task_state = (!task_is_running) ?
+	/* case 1+2: */	(struct state*) dest_task->last_stack_pointer :
+	/* case 3:   */ s;
+
+	// pseudo state pushed by reschedule() has INT no. 0
+	const int state_on_task_stack = task_state->int_no == 0;
+
+	// stack pointer was saved by switch_context() after saving task
+	// state to task stack, otherwise it's the last rsp
+	const size_t sp = state_on_task_stack ? (size_t) dest_task->last_stack_pointer : task_state->rsp;
+	const size_t altstack = signal_stack(dest_task, &act, sp);
+	const size_t stack_top = altstack ? (altstack & ~0xFUL) : sp;
+
+	// e.g. a stack overflow => the handler can't run on this stack
+	if (BUILTIN_EXPECT(!page_writable(stack_top - SIGNAL_STACK_SIZE, stack_top), 0)) {
+		LOG_ERROR("Unable to deliver signal %d to task %d, stack at %#zx isn't writable\n",
+		          signum, dest_task->id, stack_top);
+		signal_return(&frame);
+		return -EFAULT;
+	}
+
+	if(state_on_task_stack && !altstack) {
+		LOG_DEBUG("  State is already on task stack\n");
+		task_stackptr = dest_task->last_stack_pointer;
+	} else {
+		task_stackptr = (size_t*) stack_top;
+
+		LOG_DEBUG("  Copy state to %s stack\n", altstack ? "alternate" : "task");
+		task_stackptr -= sizeof(struct state) / sizeof(size_t);
+		memcpy(task_stackptr, task_state, sizeof(struct state));
+	}
+
+	// context of the interrupted task is written back by sighandler_restore()
+	// xsave requires a 64 byte aligned area
+	uc = (ucontext_t*) (((size_t) task_stackptr - sizeof(ucontext_t)) & ~0x3FUL);
+	context_save(uc, (struct state*) task_stackptr, frame.mask);
+	task_fpu_save(dest_task, &uc->uc_fpregs);
+
+	// signal frame is restored by signal_return()
+	frame.state = task_stackptr;
+	frame.context = uc;
+	sigframe = (signal_frame_t*) (((size_t) uc - sizeof(signal_frame_t)) & ~0xFUL);
+	memcpy(sigframe, &frame, sizeof(signal_frame_t));
+	task_stackptr = (size_t*) sigframe;
+
+	// signal handler will return to this function to restore
+	// signal mask and register state
+	extern void sighandler_return();
+	*(--task_stackptr) = (uint64_t) &sighandler_return;
+	size_t* sighandler_rsp = task_stackptr;
+
+	if(state_on_task_stack) {
+		LOG_DEBUG("  Craft state for signal handler on task stack\n");
+
+		// we actually only care for ss, rflags, cs, fs and gs
+		task_stackptr -= sizeof(struct state) / sizeof(size_t);
+		sighandler_state = (struct state*) task_stackptr;
+		memcpy(sighandler_state, task_state, sizeof(struct state));
+
+		// advance stack pointer so signal handler state will be
+		// restored first
+		dest_task->last_stack_pointer = (size_t*) sighandler_state;
+
+		// reschedule() disables the interrupts, but the handler
+		// runs like a normal function of the task
+		sighandler_state->rflags |= (1 << 9);
+	} else {
+		LOG_DEBUG("  Reuse state on IST for signal handler\n");
+		sighandler_state = task_state;
 	}
 
-	// send IPI to destination core
-	LOG_DEBUG("  Send signal IPI (%d) to core %d\n", SIGNAL_IRQ, dest_core);
-	apic_send_ipi(dest_core, SIGNAL_IRQ);
+	// update rsp so that sighandler_return() will be executed
+	// after signal handler
+	sighandler_state->rsp = (uint64_t) sighandler_rsp;
+	sighandler_state->userrsp = sighandler_state->rsp;
+
+	// call signal handler instead of continuing task's execution
+	sighandler_state->rdi = (uint64_t) signum;
+	sighandler_state->rsi = (uint64_t) &sigframe->info;
+	sighandler_state->rdx = (uint64_t) uc;
+	if (act.sa_flags & SA_SIGINFO)
+		sighandler_state->rip = (uint64_t) act.sa_sigaction;
+	else
+		sighandler_state->rip = (uint64_t) act.sa_handler;
+
+	// interrupt a sleeping task => the handler runs immediately
+	if ((dest_task->status == TASK_BLOCKED) && (dest_task->flags & TASK_INTERRUPTIBLE))
+		wakeup_task(dest_task->id);
 
 	return 0;
 }
 
-void signal_init(void)
+int signal_fault(struct state* s, int signum, int code, void* addr)
 {
-	// initialize per-core signal queue
-	for(int i = 0; i < MAX_CORES; i++) {
-		dequeue_init(&signal_queue[i], signal_buffer[i],
-		             SIGNAL_BUFFER_SIZE, sizeof(sig_t));
-	}
+	task_t* curr_task = per_core(current_task);
+	siginfo_t info;
+	int ret;
+
+	if (BUILTIN_EXPECT(curr_task->status == TASK_IDLE, 0))
+		return -EINVAL;
+
+	// a fault of the kernel itself isn't recoverable by a handler
+	if ((s->rip >= (size_t) &kernel_start) && (s->rip < (size_t) &kernel_start + image_size))
+		return -EPERM;
+
+	memset(&info, 0x00, sizeof(info));
+	info.si_signo = signum;
+	info.si_code = code;
+	info.si_pid = curr_task->id;
+	info.si_addr = addr;
+
+	ret = signal_force(&info);
+	if (ret)
+		return ret;
+
+	LOG_DEBUG("Deliver signal %d of a fault at %p to task %d\n", signum, addr, curr_task->id);
+
+	// the task is terminated, if the handler can't be injected
+	return signal_deliver(curr_task, s);
+}
+
+static void _signal_irq_handler(struct state* s)
+{
+	task_t* curr_task = per_core(current_task);
 
+	LOG_DEBUG("Enter _signal_irq_handler() on core %d\n", CORE_ID);
+
+	// the signals of the other tasks are delivered when they are switched in
+	if ((curr_task->status != TASK_IDLE) && signal_deliverable(curr_task))
+		signal_deliver(curr_task, s);
+
+	LOG_DEBUG("Leave _signal_irq_handler() on core %d\n", CORE_ID);
+}
+
+void signal_notify(task_t* task)
+{
+	// interrupt a sleeping task => the handler runs, when it's switched in
+	if ((task->status == TASK_BLOCKED) && (task->flags & TASK_INTERRUPTIBLE))
+		wakeup_task(task->id);
+
+	// the task could run on its last core => interrupt it there
+	LOG_DEBUG("  Send signal IPI (%d) to core %d\n", SIGNAL_IRQ, task->last_core);
+	apic_send_ipi(task->last_core, SIGNAL_IRQ);
+}
+
+void signal_init(void)
+{
 	irq_install_handler(SIGNAL_IRQ, _signal_irq_handler);
 }
@@ -35,6 +35,7 @@
 #include <hermit/vma.h>
 #include <hermit/rcce.h>
 #include <hermit/logging.h>
+#include <hermit/signal.h>
 #include <asm/tss.h>
 #include <asm/page.h>
 #include <asm/multiboot.h>
@@ -109,9 +110,9 @@ static int thread_entry(void* arg, size_t ep)
 	//vma_dump();
 
 	entry_point_t call_ep = (entry_point_t) ep;
-	call_ep(arg);
 
-	return 0;
+	// the return value of the entry point is the exit code of the task
+	do_exit(call_ep(arg));
 }
 
 int is_proxy(void)
@@ -142,6 +143,10 @@ size_t* get_current_stack(void)
 
 	set_tss(stptr, (size_t) curr_task->ist_addr + KERNEL_STACK_SIZE - 0x10);
 
+	// pending signals are delivered before the task continues
+	if ((curr_task->status != TASK_IDLE) && signal_deliverable(curr_task))
+		signal_deliver(curr_task, NULL);
+
 	return curr_task->last_stack_pointer;
 }
 
@@ -255,6 +260,16 @@ void wakeup_core(uint32_t core_id)
 	apic_send_ipi(core_id, 121);
 }
 
+void preempt_core(uint32_t core_id)
+{
+	// the current core checks its ready queue by itself
+	if (core_id == CORE_ID)
+		return;
+
+	// the wakeup interrupt checks, if a reschedule is required
+	apic_send_ipi(core_id, 121);
+}
+
 void reschedule(void)
 {
 	size_t** stack;
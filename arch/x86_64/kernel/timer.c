@@ -33,9 +33,12 @@
 #include <hermit/errno.h>
 #include <hermit/spinlock.h>
 #include <hermit/logging.h>
+#include <hermit/time_page.h>
 #include <asm/irq.h>
 #include <asm/irqflags.h>
 #include <asm/io.h>
+#include <asm/page.h>
+#include <asm/uhyve.h>
 
 /*
  * This will keep track of how many ticks the system
@@ -44,10 +47,10 @@
 DEFINE_PER_CORE(uint64_t, timer_ticks, 0);
 extern uint32_t cpu_freq;
 extern int32_t boot_processor;
+uint64_t boot_tsc __attribute__ ((section(".data"))) = 0;
 
 #ifdef DYNAMIC_TICKS
 DEFINE_PER_CORE(uint64_t, last_rdtsc, 0);
-uint64_t boot_tsc __attribute__ ((section(".data"))) = 0;
 
 void check_ticks(void)
 {
@@ -183,14 +186,135 @@ static int pit_init(void)
 
 int clock_init(void)
 {
-#ifdef DYNAMIC_TICKS
 	if (!boot_tsc)
 		boot_tsc = rdtsc();
-#endif
 
 	return 0;
 }
 
+uint64_t get_boot_tsc(void)
+{
+	return boot_tsc;
+}
+
+uint64_t get_tsc_frequency(void)
+{
+	return 1000000ULL * (uint64_t) get_cpu_frequency();
+}
+
+#define CMOS_SECONDS	0x00
+#define CMOS_MINUTES	0x02
+#define CMOS_HOURS	0x04
+#define CMOS_DAY	0x07
+#define CMOS_MONTH	0x08
+#define CMOS_YEAR	0x09
+#define CMOS_STATUS_A	0x0A
+#define CMOS_STATUS_B	0x0B
+#define CMOS_CENTURY	0x32
+
+#define CMOS_UIP	(1 << 7)	/* update in progress */
+#define CMOS_24H	(1 << 1)
+#define CMOS_BINARY	(1 << 2)
+
+typedef struct {
+	uint8_t sec, min, hour, day, mon, year, century;
+} rtc_time_t;
+
+static void rtc_read(rtc_time_t* t)
+{
+	while (cmos_read(CMOS_STATUS_A) & CMOS_UIP)
+		PAUSE;
+
+	t->sec = cmos_read(CMOS_SECONDS);
+	t->min = cmos_read(CMOS_MINUTES);
+	t->hour = cmos_read(CMOS_HOURS);
+	t->day = cmos_read(CMOS_DAY);
+	t->mon = cmos_read(CMOS_MONTH);
+	t->year = cmos_read(CMOS_YEAR);
+	t->century = cmos_read(CMOS_CENTURY);
+}
+
+static inline uint8_t bcd2bin(uint8_t val)
+{
+	return (val & 0x0F) + (val >> 4) * 10;
+}
+
+/* days since 1970-01-01 of a date in the proleptic Gregorian calendar */
+static uint64_t days_from_civil(uint64_t y, uint64_t m, uint64_t d)
+{
+	y -= m <= 2;
+
+	const uint64_t era = y / 400;
+	const uint64_t yoe = y - era * 400;
+	const uint64_t doy = (153 * (m > 2 ? m - 3 : m + 9) + 2) / 5 + d - 1;
+	const uint64_t doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+	return era * 146097 + doe - 719468;
+}
+
+/* seconds since the epoch, read from the CMOS real time clock */
+static uint64_t rtc_get_time(void)
+{
+	rtc_time_t t, last;
+	uint64_t year;
+	uint8_t status;
+	int pm;
+
+	// read the clock until we get the same values twice
+	rtc_read(&t);
+	do {
+		last = t;
+		rtc_read(&t);
+	} while(memcmp(&t, &last, sizeof(rtc_time_t)));
+
+	status = cmos_read(CMOS_STATUS_B);
+	pm = t.hour & 0x80;
+	t.hour &= 0x7F;
+
+	if (!(status & CMOS_BINARY)) {
+		t.sec = bcd2bin(t.sec);
+		t.min = bcd2bin(t.min);
+		t.hour = bcd2bin(t.hour);
+		t.day = bcd2bin(t.day);
+		t.mon = bcd2bin(t.mon);
+		t.year = bcd2bin(t.year);
+		t.century = bcd2bin(t.century);
+	}
+
+	if (!(status & CMOS_24H))
+		t.hour = (t.hour % 12) + (pm ? 12 : 0);
+
+	// the century register is optional => assume 20xx, if it's invalid
+	if ((t.century >= 19) && (t.century <= 21))
+		year = t.century * 100 + t.year;
+	else
+		year = 2000 + t.year;
+
+	if (BUILTIN_EXPECT(!t.day || !t.mon || (t.mon > 12), 0))
+		return 0;
+
+	return days_from_civil(year, t.mon, t.day) * 86400ULL
+		+ t.hour * 3600ULL + t.min * 60ULL + t.sec;
+}
+
+uint64_t get_wallclock(void)
+{
+	if (is_uhyve()) {
+		if (get_uhyve_features() & UHYVE_FEATURE_TIME) {
+			uhyve_time_t uhyve_args = {0, 0};
+
+			uhyve_send(UHYVE_PORT_TIME, (unsigned)virt_to_phys((size_t)&uhyve_args));
+
+			return uhyve_args.sec * 1000000000ULL + uhyve_args.nsec;
+		}
+
+		// uhyve doesn't emulate the CMOS
+		return 0;
+	}
+
+	return rtc_get_time() * 1000000000ULL;
+}
+
 /*
  * Sets up the system clock by installing the timer handler
  * into IRQ0
@@ -34,6 +34,8 @@
 
 #define HALT	asm volatile ("hlt")
 
+#define ALIGN_2M(addr)	(((addr) + 0x1FFFFFULL) & ~0x1FFFFFULL)
+
 /*
  * Note that linker symbols are not variables, they have no memory allocated for
  * maintaining a value, rather their address is their value.
@@ -44,7 +46,7 @@ extern const void bss_start;
 extern const void bss_end;
 extern size_t uartport;
 
-static int load_code(size_t viraddr, size_t phyaddr, size_t limit, uint32_t file_size, size_t mem_size, size_t cmdline, size_t cmdsize)
+static int load_code(size_t viraddr, size_t phyaddr, size_t limit, uint32_t file_size, size_t mem_size, size_t cmdline, size_t cmdsize, size_t initrd, size_t initrd_size)
 {
 	const size_t displacement = 0x200000ULL - (phyaddr & 0x1FFFFFULL);
 
@@ -69,6 +71,8 @@ static int load_code(size_t viraddr, size_t phyaddr, size_t limit, uint32_t file
 	*((uint64_t*) (viraddr + 0x98)) = uartport;
 	*((uint64_t*) (viraddr + 0xA0)) = cmdline;
 	*((uint64_t*) (viraddr + 0xA8)) = cmdsize;
+	*((uint64_t*) (viraddr + 0xC4)) = initrd;
+	*((uint64_t*) (viraddr + 0xCC)) = initrd_size;
 
 	// move file to a 2 MB boundary
 	for(size_t va = viraddr+(npages << PAGE_BITS)+displacement-sizeof(uint8_t); va >= viraddr+displacement; va-=sizeof(uint8_t))
@@ -82,6 +86,37 @@ static int load_code(size_t viraddr, size_t phyaddr, size_t limit, uint32_t file
 	return 0;
 }
 
+/*
+ * The initrd is copied behind the kernel, the page pool of the loader
+ * and the original modules. Afterwards, the kernel is able to protect
+ * it simply by moving the begin of its free list.
+ */
+static size_t move_initrd(size_t src, size_t size, size_t kernel_end, size_t mod_end, size_t limit)
+{
+	size_t dest = kernel_end;
+	size_t npages = PAGE_CEIL(size) >> PAGE_BITS;
+
+	// 4 MB gap for the page pool of the loader
+	if (dest < mod_end + 0x400000)
+		dest = mod_end + 0x400000;
+	dest = ALIGN_2M(dest);
+
+	if (dest + size > limit) {
+		kprintf("Initrd (size 0x%zx) doesn't fit into the memory\n", size);
+		return 0;
+	}
+
+	if (page_map(src & PAGE_MASK, src & PAGE_MASK, (PAGE_CEIL(src + size) - (src & PAGE_MASK)) >> PAGE_BITS, PG_GLOBAL))
+		return 0;
+	if (page_map(dest, dest, npages, PG_GLOBAL|PG_RW))
+		return 0;
+
+	kprintf("Move initrd from 0x%zx to 0x%zx (size 0x%zx)\n", src, dest, size);
+	memcpy((void*) dest, (void*) src, size);
+
+	return dest;
+}
+
 void main(void)
 {
 	size_t limit = 0;
@@ -92,6 +127,9 @@ void main(void)
 	size_t mem_size = 0;
 	size_t cmdline_size = 0;
 	size_t cmdline = 0;
+	size_t initrd = 0;
+	size_t initrd_size = 0;
+	size_t mod_end = 0;
 
 	// initialize .bss section
 	memset((void*)&bss_start, 0x00, ((size_t) &bss_end - (size_t) &bss_start));
@@ -143,6 +181,18 @@ void main(void)
 			multiboot_module_t* mmodule = (multiboot_module_t*) ((size_t) mb_info->mods_addr);
 			header = (elf_header_t*) ((size_t) mmodule[0].mod_start);
 			kprintf("ELF file is located at %p\n", header);
+
+			for(int i=0; i<mb_info->mods_count; i++) {
+				if (mod_end < mmodule[i].mod_end)
+					mod_end = mmodule[i].mod_end;
+			}
+
+			// the second module is an optional initrd (newc cpio archive)
+			if (mb_info->mods_count > 1) {
+				initrd = mmodule[1].mod_start;
+				initrd_size = mmodule[1].mod_end - mmodule[1].mod_start;
+				kprintf("Initrd is located at 0x%zx (size 0x%zx)\n", initrd, initrd_size);
+			}
 		}
 	} else {
 		goto failed;
@@ -196,7 +246,19 @@ void main(void)
 		}
 	}
 
-	if (BUILTIN_EXPECT(load_code(viraddr, phyaddr, limit, file_size, mem_size, cmdline, cmdline_size), 0))
+	if (initrd_size) {
+		// physical end of the kernel after its migration to a 2 MB boundary
+		size_t kernel_end = (phyaddr & ~0x1FFFFFULL) + 0x200000ULL + mem_size;
+
+		if (kernel_end < viraddr + mem_size)
+			kernel_end = viraddr + mem_size;
+
+		initrd = move_initrd(initrd, initrd_size, kernel_end, mod_end, limit);
+		if (!initrd)
+			initrd_size = 0;
+	}
+
+	if (BUILTIN_EXPECT(load_code(viraddr, phyaddr, limit, file_size, mem_size, cmdline, cmdline_size, initrd, initrd_size), 0))
 		goto failed;
 
 	kprintf("Entry point: 0x%zx\n", header->entry);
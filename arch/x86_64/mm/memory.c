@@ -375,6 +375,12 @@ int memory_init(void)
 		}
 	}
 
+	// the loader places the initrd behind the kernel => exclude it from the free list
+	if (initrd_size && (initrd_start + initrd_size > init_list.start) && (initrd_start < init_list.end)) {
+		init_list.start = PAGE_CEIL(initrd_start + initrd_size);
+		LOG_INFO("Reserve initrd at 0x%zx - 0x%zx\n", initrd_start, initrd_start + initrd_size);
+	}
+
 	// determine allocated memory, we use 2MB pages to map the kernel
 	atomic_int64_add(&total_allocated_pages, PAGE_2M_CEIL(image_size) >> PAGE_BITS);
 	atomic_int64_sub(&total_available_pages, PAGE_2M_CEIL(image_size) >> PAGE_BITS);
@@ -41,6 +41,7 @@
 #include <hermit/spinlock.h>
 #include <hermit/tasks.h>
 #include <hermit/logging.h>
+#include <hermit/vma.h>
 
 #include <asm/multiboot.h>
 #include <asm/irq.h>
@@ -102,10 +103,139 @@ int getpagesize(void)
 	return PAGE_SIZE;
 }
 
-//TODO: code is missing
+/*
+ * Returns the entry of the last level page table or NULL, if the
+ * tables don't exist or the address is part of a huge page.
+ */
+static size_t* page_entry(size_t viraddr)
+{
+	ssize_t vpn = viraddr >> PAGE_BITS;
+
+	for (int lvl=PAGE_LEVELS-1; lvl>0; lvl--) {
+		size_t entry = self[lvl][vpn >> (lvl * PAGE_MAP_BITS)];
+
+		if (!(entry & PG_PRESENT) || (entry & PG_PSE))
+			return NULL;
+	}
+
+	return &self[0][vpn];
+}
+
+int page_writable(size_t start, size_t end)
+{
+	task_t* task = per_core(current_task);
+	size_t viraddr;
+	size_t* entry;
+	uint32_t flags;
+
+	for(viraddr=start & PAGE_MASK; viraddr<end; viraddr+=PAGE_SIZE) {
+		// the kernel image and the heap are mapped by huge pages
+		if ((viraddr >= (size_t) &kernel_start) && (viraddr < (size_t) &kernel_start + image_size))
+			continue;
+		if ((task->heap) && (viraddr >= task->heap->start) && (viraddr < task->heap->end))
+			continue;
+
+		entry = page_entry(viraddr);
+		if (entry && (*entry & PG_PRESENT)) {
+			if (!(*entry & PG_RW))
+				return 0;
+			continue;
+		}
+
+		// anonymous memory is mapped on demand
+		if (vma_get_flags(viraddr, &flags) || !(flags & VMA_ANON)
+		   || !(flags & VMA_WRITE) || (flags & VMA_NO_ACCESS))
+			return 0;
+	}
+
+	return 1;
+}
+
+/* Convert VMA flags into page table bits of an anonymous mapping */
+static size_t page_bits(int flags)
+{
+	size_t bits = PG_USER;
+
+	if (flags & VMA_WRITE)
+		bits |= PG_RW;
+	if (!(flags & VMA_EXECUTE) && has_nx())
+		bits |= PG_XD;
+	if (!(flags & VMA_CACHEABLE))
+		bits |= PG_PCD;
+
+	return bits;
+}
+
+/*
+ * The flags are VMA flags. Without VMA_READ, VMA_WRITE and VMA_EXECUTE,
+ * or with VMA_NO_ACCESS, the pages are marked as non-present. The frames
+ * remain in the page tables and are restored by the next call.
+ */
 int page_set_flags(size_t viraddr, uint32_t npages, int flags)
 {
-	return -EINVAL;
+	int access = (flags & (VMA_READ|VMA_WRITE|VMA_EXECUTE)) && !(flags & VMA_NO_ACCESS);
+	size_t bits = page_bits(flags);
+	int8_t send_ipi = 0;
+
+	if (BUILTIN_EXPECT(viraddr & ~PAGE_MASK, 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&page_lock);
+
+	for (size_t i=0; i<npages; i++) {
+		size_t addr = viraddr + i*PAGE_SIZE;
+		size_t* entry = page_entry(addr);
+		size_t phyaddr;
+
+		if (!entry || !(*entry & PAGE_MASK))
+			continue; // not populated yet
+
+		phyaddr = *entry & PAGE_MASK;
+		if (access)
+			*entry = phyaddr | bits | PG_PRESENT | PG_ACCESSED | PG_DIRTY;
+		else
+			*entry = phyaddr | bits;
+
+		tlb_flush_one_page(addr, 0);
+		send_ipi = 1;
+	}
+
+	if (send_ipi)
+		ipi_tlb_flush();
+
+	spinlock_irqsave_unlock(&page_lock);
+
+	return 0;
+}
+
+int page_unmap_put(size_t viraddr, size_t npages)
+{
+	int8_t send_ipi = 0;
+
+	if (BUILTIN_EXPECT(viraddr & ~PAGE_MASK, 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&page_lock);
+
+	for (size_t i=0; i<npages; i++) {
+		size_t addr = viraddr + i*PAGE_SIZE;
+		size_t* entry = page_entry(addr);
+
+		if (!entry || !(*entry & PAGE_MASK))
+			continue;
+
+		put_page(*entry & PAGE_MASK);
+		*entry = 0;
+		tlb_flush_one_page(addr, 0);
+		send_ipi = 1;
+	}
+
+	if (send_ipi)
+		ipi_tlb_flush();
+
+	spinlock_irqsave_unlock(&page_lock);
+
+	return 0;
 }
 
 int __page_map(size_t viraddr, size_t phyaddr, size_t npages, size_t bits, uint8_t do_ipi)
@@ -223,6 +353,7 @@ int page_unmap(size_t viraddr, size_t npages)
 void page_fault_handler(struct state *s)
 {
 	size_t viraddr = read_cr2();
+	const size_t fault_addr = viraddr;
 	task_t* task = per_core(current_task);
 
 	int check_pagetables(size_t vaddr)
@@ -247,6 +378,12 @@ void page_fault_handler(struct state *s)
 		return 1;
 	}
 
+	uint32_t vma_flags = 0;
+
+	// query the VMA before the page tables are locked
+	if (vma_get_flags(viraddr, &vma_flags))
+		vma_flags = 0;
+
 	spinlock_irqsave_lock(&page_lock);
 
 	if (((task->heap) && (viraddr >= task->heap->start) && (viraddr < task->heap->end))
@@ -292,9 +429,68 @@ void page_fault_handler(struct state *s)
 		return;
 	}
 
+	if (vma_flags & (VMA_ANON|VMA_FILE)) {
+		size_t* entry = page_entry(viraddr);
+		size_t phyaddr;
+
+		// another core has already resolved the fault
+		if (!(s->error & 0x1) && entry && (*entry & PG_PRESENT)) {
+			spinlock_irqsave_unlock(&page_lock);
+			return;
+		}
+
+		// protection violation or access to a non-accessible region
+		if ((s->error & 0x1) || (vma_flags & VMA_NO_ACCESS)
+		   || !(vma_flags & (VMA_READ|VMA_WRITE|VMA_EXECUTE))
+		   || (entry && (*entry & PAGE_MASK)))
+			goto default_handler;
+		if ((s->error & 0x2) && !(vma_flags & VMA_WRITE))
+			goto default_handler;
+
+		// on demand mapping of anonymous memory and files
+		viraddr &= PAGE_MASK;
+
+		phyaddr = get_page();
+		if (BUILTIN_EXPECT(!phyaddr, 0)) {
+			LOG_ERROR("out of memory: task = %u\n", task->id);
+			goto default_handler;
+		}
+
+		// mmap guarantees zeroed pages => map it writable to clear it
+		if (BUILTIN_EXPECT(__page_map(viraddr, phyaddr, 1, PG_RW|(has_nx() ? PG_XD : 0), 0), 0)) {
+			LOG_ERROR("map_region: could not map %#lx to %#lx, task = %u\n", phyaddr, viraddr, task->id);
+			put_page(phyaddr);
+
+			goto default_handler;
+		}
+
+		memset((void*) viraddr, 0x00, PAGE_SIZE);
+
+		// a read beyond the end of file leaves the remaining bytes zeroed
+		if ((vma_flags & VMA_FILE) && (mmap_file_read(viraddr, phyaddr) < 0)) {
+			LOG_ERROR("unable to read file mapping at %#lx, task = %u\n", viraddr, task->id);
+			page_unmap_put(viraddr, 1);
+
+			goto default_handler;
+		}
+
+		__page_map(viraddr, phyaddr, 1, page_bits(vma_flags), 0);
+
+		spinlock_irqsave_unlock(&page_lock);
+
+		// clear cr2 to signalize that the pagefault is solved by the pagefault handler
+		write_cr2(0);
+
+		return;
+	}
+
 default_handler:
 	spinlock_irqsave_unlock(&page_lock);
 
+	// does the task handle the fault itself (e.g. guard pages of a runtime)?
+	if (!signal_fault(s, SIGSEGV, (s->error & 0x1) ? SEGV_ACCERR : SEGV_MAPERR, (void*) fault_addr))
+		return;
+
 	LOG_ERROR("Page Fault Exception (%d) on core %d at cs:ip = %#x:%#lx, fs = %#lx, gs = %#lx, rflags 0x%lx, task = %u, addr = %#lx, error = %#x [ %s %s %s %s %s ]\n",
 		s->int_no, CORE_ID, s->cs, s->rip, s->fs, s->gs, s->rflags, task->id, viraddr, s->error,
 		(s->error & 0x4) ? "user" : "supervisor",
@@ -13,6 +13,9 @@ set(MAX_ISLE "8" CACHE STRING
 set(MAX_FNAME "128" CACHE STRING
 	"Define the maximum length of a file name")
 
+set(TMPFS_PATH "/tmp" CACHE STRING
+	"Mount point of the RAM-backed file system")
+
 set(KERNEL_STACK_SIZE 8192 CACHE STRING
 	"Kernel stack size in bytes")
 
@@ -23,6 +26,9 @@ set(MAX_ARGC_ENVC 128 CACHE STRING
 	"Maximum number of command line parameters and enviroment variables
 	forwarded to uhyve")
 
+set(SCHED_RR_QUANTUM 10000 CACHE STRING
+	"Time slice of round-robin scheduled tasks in microseconds")
+
 option(DYNAMIC_TICKS
 	"Don't use a periodic timer event to keep track of time" ON)
 
@@ -43,6 +43,7 @@
 #include <hermit/signal.h>
 #include <hermit/mailbox.h>
 #include <hermit/logging.h>
+#include <hermit/uhyve_ring.h>
 #include <asm/io.h>
 #include <asm/irq.h>
 #include <sys/poll.h>
@@ -70,7 +71,7 @@ static int uhyve_net_write_sync(uint8_t *data, int n)
 	uhyve_netwrite.len = n;
 	uhyve_netwrite.ret = 0;
 
-	outportl(UHYVE_PORT_NETWRITE, (unsigned)virt_to_phys((size_t)&uhyve_netwrite));
+	uhyve_call(UHYVE_PORT_NETWRITE, (unsigned)virt_to_phys((size_t)&uhyve_netwrite));
 
 	return uhyve_netwrite.ret;
 }
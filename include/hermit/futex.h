@@ -0,0 +1,81 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file include/hermit/futex.h
+ * @brief Operations and encoding of the futex system call
+ *
+ * The constants are compatible to Linux. This header doesn't depend on
+ * kernel headers and could be included by the user space.
+ */
+
+#ifndef __FUTEX_H__
+#define __FUTEX_H__
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define FUTEX_WAIT		0
+#define FUTEX_WAKE		1
+#define FUTEX_REQUEUE		3
+#define FUTEX_CMP_REQUEUE	4
+#define FUTEX_WAKE_OP		5
+
+/// HermitCore has a single address space => all futexes are private
+#define FUTEX_PRIVATE_FLAG	128
+#define FUTEX_CMD_MASK		(~FUTEX_PRIVATE_FLAG)
+
+/* Operations of FUTEX_WAKE_OP */
+#define FUTEX_OP_SET		0	/* *uaddr2 = oparg */
+#define FUTEX_OP_ADD		1	/* *uaddr2 += oparg */
+#define FUTEX_OP_OR		2	/* *uaddr2 |= oparg */
+#define FUTEX_OP_ANDN		3	/* *uaddr2 &= ~oparg */
+#define FUTEX_OP_XOR		4	/* *uaddr2 ^= oparg */
+
+/// Use (1 << oparg) as operand
+#define FUTEX_OP_OPARG_SHIFT	8
+
+/* Comparisons of FUTEX_WAKE_OP */
+#define FUTEX_OP_CMP_EQ		0
+#define FUTEX_OP_CMP_NE		1
+#define FUTEX_OP_CMP_LT		2
+#define FUTEX_OP_CMP_LE		3
+#define FUTEX_OP_CMP_GT		4
+#define FUTEX_OP_CMP_GE		5
+
+/// Encode the argument val3 of FUTEX_WAKE_OP
+#define FUTEX_OP(op, oparg, cmp, cmparg) \
+	((((op) & 0xf) << 28) | (((cmp) & 0xf) << 24) \
+	 | (((oparg) & 0xfff) << 12) | ((cmparg) & 0xfff))
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,173 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file include/hermit/proxy.h
+ * @brief Communication with the proxy on the host
+ *
+ * A system call, which is forwarded to the proxy, consists of one request
+ * and at most one reply. The legacy protocol (HERMIT_MAGIC) handles one
+ * call at a time. The multiplexed protocol (HERMIT_MAGIC_MUX) prefixes
+ * each message with a proxy_header_t. Consequently, several tasks are able
+ * to wait for their replies at the same time. A kernel task receives all
+ * replies and wakes up the blocked tasks. The multiplexed protocol requires
+ * a proxy, which announces HERMIT_MAGIC_MUX. Otherwise, the kernel falls
+ * back to the legacy protocol.
+ *
+ * The kernel doesn't rely on LWIP_NETCONN_FULLDUPLEX. The demultiplexer
+ * waits for a reply by lwip_select() and the reads and writes of the
+ * socket never overlap.
+ */
+
+#ifndef __PROXY_H__
+#define __PROXY_H__
+
+#include <hermit/stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/// Port of the proxy connection
+#define HERMIT_PORT		0x494E
+/// Magic number of the legacy protocol
+#define HERMIT_MAGIC		0x7E317
+/// Magic number of the multiplexed protocol, followed by the version
+#define HERMIT_MAGIC_MUX	0x7E318
+/// Version of the multiplexed protocol, which is supported by the kernel
+#define PROXY_VERSION		2
+
+/** @brief Header of a message in the multiplexed protocol
+ *
+ * The proxy replies to each request with a nonzero id and uses the id of
+ * the request in the header of the reply. Requests without a reply
+ * (e.g. exit) use the id 0.
+ */
+typedef struct proxy_header {
+	/// Identifier of the call
+	uint64_t id;
+	/// Number of bytes following the header
+	uint64_t len;
+} __attribute__((packed)) proxy_header_t;
+
+/** @brief State of a forwarded system call */
+typedef struct proxy_call {
+	/// Identifier of the call, 0 if the proxy doesn't reply
+	uint64_t id;
+	/// Number of bytes of the request, which aren't sent yet
+	size_t req_len;
+	/// Received reply (multiplexed protocol)
+	char* reply;
+	/// Size of the reply
+	size_t reply_len;
+	/// Number of consumed bytes of the reply
+	size_t reply_pos;
+	/// The request is sent to the proxy
+	uint8_t sent;
+	/// The call holds the connection to send its request
+	uint8_t sending;
+	/// Reply is available or the connection is closed
+	volatile uint8_t done;
+	/// Error number, if the connection is closed
+	int err;
+	/// Task, which waits for the reply
+	tid_t waiter;
+	/// Next call in the list of pending calls
+	struct proxy_call* next;
+} proxy_call_t;
+
+/** @brief Negotiate the protocol with a new proxy
+ *
+ * @param sd Socket of the proxy
+ * @param magic Magic number, which is received from the proxy
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) if the magic number is unknown
+ */
+int proxy_handshake(int sd, int magic);
+
+/** @brief Use a connected proxy for the forwarding of system calls
+ *
+ * In case of the multiplexed protocol, the demultiplexer task is started.
+ */
+int proxy_start(int sd);
+
+/** @brief Close the connection to the proxy */
+void proxy_shutdown(void);
+
+/** @brief Begin a forwarded system call
+ *
+ * In case of the multiplexed protocol, the header of the request is sent
+ * and the connection is held until all bytes of the request are sent.
+ * On success, the function returns with the irqsave spinlock of the
+ * connection held (send_lock or, in case of the legacy protocol,
+ * proxy_lock). The caller streams the request by proxy_send(), which
+ * blocks in lwip_write(), and releases the lock by proxy_end() or, in
+ * case of the multiplexed protocol, by sending the last byte of the
+ * request. Hence, the caller must not block on anything else in between.
+ *
+ * @param call State of the call
+ * @param len Size of the request in bytes
+ * @param reply The proxy replies to the request
+ * @return
+ * - 0 on success
+ * - -ENOSYS (-38) if no proxy is connected
+ * - -EIO (-5) if the connection is closed
+ */
+int proxy_begin(proxy_call_t* call, size_t len, int reply);
+
+/** @brief Send a part of the request
+ *
+ * @return
+ * - number of sent bytes on success
+ * - -EINVAL (-22) if the request exceeds the size passed to proxy_begin()
+ * - -EIO (-5) if the connection is closed
+ */
+int proxy_send(proxy_call_t* call, const void* buf, size_t len);
+
+/** @brief Receive a part of the reply
+ *
+ * The first call waits for the reply.
+ */
+int proxy_recv(proxy_call_t* call, void* buf, size_t len);
+
+/** @brief Discard a part of the reply */
+int proxy_skip(proxy_call_t* call, size_t len);
+
+/** @brief Finish a system call
+ *
+ * Missing bytes of the request are padded with zeros to keep the
+ * connection in sync. A pending reply is dropped.
+ */
+void proxy_end(proxy_call_t* call);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
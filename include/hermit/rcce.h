@@ -49,10 +49,45 @@ typedef struct rcce_mpb {
 	volatile size_t mpb[MAX_ISLE];
 } rcce_mpb_t;
 
-#define MAX_RCCE_SESSIONS 	((PAGE_SIZE - CACHE_LINE*(RCCE_MAXNP+1)) / sizeof(rcce_mpb_t))
+/// Maximum number of SysV shared memory segments
+#define MAX_SHM_SEGMENTS	16
+
+/// Slot is in use
+#define SHM_SEG_USED		(1 << 0)
+/// Segment is marked for destruction (IPC_RMID)
+#define SHM_SEG_REMOVED		(1 << 1)
+/// Segment is allocated from the high bandwidth memory
+#define SHM_SEG_HBMEM		(1 << 2)
+/// Segment is destroyed, but its memory is still owned by its isle
+#define SHM_SEG_ORPHAN		(1 << 3)
+
+/** @brief SysV shared memory segment
+ *
+ * In multi-kernel mode, the table of segments is part of the RCCE
+ * internals and shared between all isles.
+ */
+typedef struct shm_segment {
+	/// Key of the segment, IPC_PRIVATE for private segments
+	int64_t key;
+	/// Size in bytes
+	uint64_t size;
+	/// Physical address of the segment
+	uint64_t paddr;
+	/// Number of attaches in all isles
+	int32_t nattch;
+	/// Sequence number, which is part of the identifier
+	int32_t seq;
+	/// State of the slot
+	uint32_t flags;
+	/// Isle, which allocated the memory and is responsible to free it
+	int32_t isle;
+} shm_segment_t;
+
+#define MAX_RCCE_SESSIONS 	((PAGE_SIZE - CACHE_LINE*(RCCE_MAXNP+1) - MAX_SHM_SEGMENTS*sizeof(shm_segment_t)) / sizeof(rcce_mpb_t))
 
 extern islelock_t* rcce_lock;
 extern rcce_mpb_t* rcce_mpb;
+extern shm_segment_t* rcce_shm;
 extern uint64_t phy_rcce_internals;
 
 #endif
@@ -40,12 +40,31 @@ extern "C" {
 
 #include <hermit/stddef.h>
 #include <hermit/semaphore_types.h>
+#include <sys/signal.h>
 
-#define MAX_SIGNALS 32
+#define MAX_SIGNALS NSIG
 
 typedef void (*signal_handler_t)(int);
 
-// This is used in deqeue.h (HACK)
+/** @brief Frame on the task stack between the interrupted state and the signal handler
+ *
+ * The handler returns to sighandler_return(), which restores the signal
+ * mask by signal_return() and continues the interrupted task.
+ */
+typedef struct signal_frame {
+	/// the interrupted state, which is restored by sighandler_epilog()
+	void* state;
+	/// signal mask before the delivery
+	sigset_t mask;
+	/// argument of SA_SIGINFO handlers
+	siginfo_t info;
+	/// architecture specific context of the interrupted task (ucontext_t)
+	void* context;
+} signal_frame_t;
+
+struct task;
+
+/// Signal to a task, e.g. of an expired timer
 typedef struct _sig {
 	tid_t dest;
 	int signum;
@@ -61,6 +80,63 @@ typedef struct _sig {
  */
 int hermit_kill(tid_t dest, int signum);
 
+/** @brief Send a signal with additional information to a task
+ *
+ * The signal becomes pending and is delivered, if it isn't blocked by the
+ * task. Ignored signals are discarded.
+ *
+ * @return
+ *  - 0 on success
+ *  - -ENOENT (-2) if task not found
+ *  - -EINVAL (-22) on an invalid signal number
+ */
+int signal_send(tid_t dest, const siginfo_t* info);
+
+/** @brief Dequeue the next deliverable signal of a task
+ *
+ * The signal mask of the task is updated for the execution of the handler.
+ * Ignored signals are skipped.
+ *
+ * @param task Receiver of the signal
+ * @param frame Receives the previous mask and the siginfo
+ * @param act Receives the action, SIG_DFL is replaced by the default action
+ * @return Signal number or 0, if no signal is deliverable
+ */
+int signal_dequeue(struct task* task, signal_frame_t* frame, struct sigaction* act);
+
+/** @brief Restore the signal mask after a handler returned
+ *
+ * @return The interrupted state
+ */
+void* signal_return(signal_frame_t* frame);
+
+/** @brief Raise a synchronous signal of a fault in the current task
+ *
+ * The signal is only raised, if the task has installed a handler and
+ * doesn't block the signal. Otherwise, the task has to be terminated.
+ *
+ * @return
+ *  - 0 on success, the architecture has to deliver the signal immediately
+ *  - -EINVAL (-22) if the fault isn't handled by the task
+ */
+int signal_force(const siginfo_t* info);
+
+/** @brief Choose the stack of a signal handler
+ *
+ * @param task Receiver of the signal
+ * @param act Action of the signal
+ * @param sp Stack pointer of the interrupted context
+ * @return Top of the alternate signal stack or 0, if the handler runs
+ *         on the interrupted stack
+ */
+size_t signal_stack(struct task* task, const struct sigaction* act, size_t sp);
+
+/** @brief Returns true, if a pending signal isn't blocked */
+int signal_deliverable(struct task* task);
+
+/** @brief Architecture specific: trigger the delivery of pending signals */
+void signal_notify(struct task* task);
+
 /** @brief Register signal handler
  *
  * @param handler	Signal handler
@@ -81,6 +81,33 @@ extern size_t image_size;
 #define UHYVE_PORT_CMDSIZE		0x740
 #define UHYVE_PORT_CMDVAL		0x780
 
+/* Ports for vectored I/O, the host receives an array of (phys, len) pairs */
+#define UHYVE_PORT_READV		0x7C0
+#define UHYVE_PORT_WRITEV		0x800
+/// Feature bit of the boot header: the host supports UHYVE_PORT_READV and UHYVE_PORT_WRITEV
+#define UHYVE_FEATURE_IOV		(1 << 2)
+
+#define UHYVE_PORT_STAT			0x840
+
+/* Ports for directories and the namespace of the host */
+#define UHYVE_PORT_MKDIR		0x880
+#define UHYVE_PORT_RMDIR		0x8C0
+#define UHYVE_PORT_UNLINK		0x900
+#define UHYVE_PORT_RENAME		0x940
+#define UHYVE_PORT_OPENDIR		0x980
+#define UHYVE_PORT_READDIR		0x9C0
+#define UHYVE_PORT_CLOSEDIR		0xA00
+
+/* Reads a part of a host file into a guest physical buffer (file-backed mmap) */
+#define UHYVE_PORT_PREAD		0xA40
+
+/* Registration and notification of the hypercall ring (hermit/uhyve_ring.h) */
+#define UHYVE_PORT_RING			0xA80
+#define UHYVE_PORT_RING_NOTIFY		0xAC0
+
+/* Wall-clock time of the host (hermit/time_page.h) */
+#define UHYVE_PORT_TIME			0xB00
+
 
 #define BUILTIN_EXPECT(exp, b)		__builtin_expect((exp), (b))
 //#define BUILTIN_EXPECT(exp, b)	(exp)
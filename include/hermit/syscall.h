@@ -73,7 +73,32 @@ int sys_setprio(tid_t* id, int prio);
 void NORETURN sys_exit(int arg);
 ssize_t sys_read(int fd, char* buf, size_t len);
 ssize_t sys_write(int fd, const char* buf, size_t len);
+struct iovec;
+ssize_t sys_readv(int fd, const struct iovec *iov, int iovcnt);
+ssize_t sys_writev(int fd, const struct iovec *iov, int iovcnt);
 ssize_t sys_sbrk(ssize_t incr);
+
+/** @brief Map anonymous memory
+ *
+ * Only private and anonymous mappings are supported. The pages are
+ * mapped on demand and filled with zeros.
+ *
+ * @return The start address or a negative error number
+ */
+void* sys_mmap(void* addr, size_t length, int prot, int flags, int fd, off_t offset);
+int sys_munmap(void* addr, size_t length);
+int sys_mprotect(void* addr, size_t length, int prot);
+
+/** @brief SysV shared memory
+ *
+ * In multi-kernel mode, the segments are shared between all isles.
+ * The functions return a negative error number on failure.
+ */
+int sys_shmget(long key, size_t size, int shmflg);
+void* sys_shmat(int shmid, const void* shmaddr, int shmflg);
+int sys_shmdt(const void* shmaddr);
+struct shmid_ds;
+int sys_shmctl(int shmid, int cmd, struct shmid_ds* buf);
 int sys_open(const char* name, int flags, int mode);
 int sys_close(int fd);
 void sys_msleep(unsigned int ms);
@@ -84,15 +109,122 @@ int sys_sem_post(sem_t* sem);
 int sys_sem_timedwait(sem_t *sem, unsigned int ms);
 int sys_sem_cancelablewait(sem_t* sem, unsigned int ms);
 int sys_clone(tid_t* id, void* ep, void* argv);
+
+/** @brief Clone the current task and pin the new task to the core core_id */
+int sys_clone_on_core(tid_t* id, void* ep, void* argv, unsigned int core_id);
+
+/// the cloned task has to be joined, otherwise it's reaped on its termination
+#define CLONE_JOINABLE		(1 << 0)
+
+/** @brief Clone the current task with CLONE_* flags */
+int sys_clone_flags(tid_t* id, void* ep, void* argv, unsigned int flags);
+
+/** @brief Wait for the termination of a joinable task and receive its exit code */
+int sys_join(tid_t id, int* status);
+
+/** @brief Reap a cloned task (0 = current task) on its termination without joining it */
+int sys_detach(tid_t id);
+
+/** @brief Cores, on which a task (0 = current task) is allowed to run (see sys/sched.h) */
+int sys_sched_setaffinity(tid_t id, size_t cpusetsize, const void* mask);
+int sys_sched_getaffinity(tid_t id, size_t cpusetsize, void* mask);
+
+/** @brief Scheduling policy and priority of a task (0 = current task, see sys/sched.h) */
+struct sched_param;
+int sys_sched_setscheduler(tid_t id, int policy, const struct sched_param* param);
+int sys_sched_getscheduler(tid_t id);
+int sys_sched_getparam(tid_t id, struct sched_param* param);
+struct sched_attr;
+int sys_sched_setattr(tid_t id, const struct sched_attr* attr, unsigned int flags);
+int sys_sched_getattr(tid_t id, struct sched_attr* attr, unsigned int size, unsigned int flags);
+struct timespec;
+int sys_sched_rr_get_interval(tid_t id, struct timespec* interval);
 off_t sys_lseek(int fd, off_t offset, int whence);
 size_t sys_get_ticks(void);
 int sys_rcce_init(int session_id);
 size_t sys_rcce_malloc(int session_id, int ue);
 int sys_rcce_fini(int session_id);
+struct stat;
+struct dirent;
+struct pollfd;
+struct timeval;
+int sys_stat(const char* file, struct stat* st);
+int sys_lstat(const char* file, struct stat* st);
+int sys_fstat(int fd, struct stat* st);
+int sys_ioctl(int fd, unsigned long cmd, void* arg);
+int sys_mkdir(const char* path, int mode);
+int sys_rmdir(const char* path);
+int sys_unlink(const char* path);
+int sys_rename(const char* oldpath, const char* newpath);
+int sys_opendir(const char* path);
+int sys_readdir(int fd, struct dirent* entry);
+int sys_poll(struct pollfd* fds, unsigned long nfds, int timeout);
+
+/** @brief Synchronous I/O multiplexing
+ *
+ * Bit i of a set refers to the file descriptor i. The FD_SET macros of
+ * LwIP strip LWIP_FD_BIT from a socket. Hence, bit i refers to the socket
+ * i | LWIP_FD_BIT, if the file descriptor i isn't open.
+ */
+int sys_select(int nfds, unsigned long* readfds, unsigned long* writefds, unsigned long* exceptfds, struct timeval* timeout);
+int sys_truncate(const char* path, off_t length);
+int sys_ftruncate(int fd, off_t length);
 void sys_yield(void);
+
+/** @brief Clocks based on the cycle counter
+ *
+ * CLOCK_REALTIME, CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID and
+ * CLOCK_THREAD_CPUTIME_ID are supported. The functions return a
+ * negative error number on failure.
+ */
+struct timespec;
+struct timezone;
+int sys_clock_getres(int clock_id, struct timespec* res);
+int sys_clock_gettime(int clock_id, struct timespec* tp);
+int sys_clock_settime(int clock_id, const struct timespec* tp);
+int sys_gettimeofday(struct timeval* tv, struct timezone* tz);
+
+/** @brief High-resolution sleeps
+ *
+ * The sleep is interrupted by signal handlers. In this case, the functions
+ * return -EINTR and store the remaining time of relative sleeps in rem.
+ */
+int sys_nanosleep(const struct timespec* req, struct timespec* rem);
+int sys_clock_nanosleep(int clock_id, int flags, const struct timespec* req, struct timespec* rem);
+
+/** @brief Fast user-space locking (see hermit/futex.h)
+ *
+ * FUTEX_WAIT, FUTEX_WAKE, FUTEX_REQUEUE, FUTEX_CMP_REQUEUE and FUTEX_WAKE_OP
+ * are supported. FUTEX_WAIT takes a relative timeout. The other operations
+ * return the number of woken (and requeued) tasks.
+ *
+ * @return A negative error number on failure
+ */
+int sys_futex(int* uaddr, int op, int val, const struct timespec* timeout, int* uaddr2, int val3);
+
+/** @brief Interval timers (ITIMER_REAL, ITIMER_VIRTUAL, ITIMER_PROF) of the current task */
+struct itimerval;
+int sys_getitimer(int which, struct itimerval* value);
+int sys_setitimer(int which, const struct itimerval* value, struct itimerval* ovalue);
+
+/** @brief Returns the read-only time page (hermit/time_page.h) or NULL */
+struct time_page;
+const struct time_page* sys_get_time_page(void);
 int sys_kill(tid_t dest, int signum);
 int sys_signal(signal_handler_t handler);
 
+/** @brief POSIX signal handling, the actions are shared by all tasks */
+struct sigaction;
+int sys_sigaction(int signum, const struct sigaction* act, struct sigaction* oldact);
+int sys_sigprocmask(int how, const unsigned long* set, unsigned long* oldset);
+int sys_pthread_sigmask(int how, const unsigned long* set, unsigned long* oldset);
+int sys_sigpending(unsigned long* set);
+int sys_sigsuspend(const unsigned long* mask);
+
+/** @brief Alternate stack of the SA_ONSTACK handlers of the current task */
+struct sigaltstack;
+int sys_sigaltstack(const struct sigaltstack* ss, struct sigaltstack* old_ss);
+
 struct ucontext;
 typedef struct ucontext ucontext_t;
 
@@ -131,6 +263,13 @@ int setcontext(ucontext_t *ucp);
 #define __NR_clone		27
 #define __NR_sem_cancelablewait	28
 #define __NR_get_ticks		29
+#define __NR_lstat		30
+#define __NR_mkdir		31
+#define __NR_rmdir		32
+#define __NR_rename		33
+#define __NR_opendir		34
+#define __NR_readdir		35
+#define __NR_closedir		36
 
 #ifndef __KERNEL__
 inline static long
@@ -44,6 +44,11 @@
 extern "C" {
 #endif
 
+#ifndef SCHED_RR_QUANTUM
+/// time slice of round-robin scheduled tasks in microseconds
+#define SCHED_RR_QUANTUM	10000
+#endif
+
 /** @brief System call to terminate a user level process */
 void NORETURN sys_exit(int);
 
@@ -87,6 +92,107 @@ int multitasking_init(void);
  */
 int clone_task(tid_t* id, entry_point_t ep, void* arg, uint8_t prio);
 
+/** @brief Clone current task and pin the new task to a core
+ *
+ * @param id Pointer to a tid_t struct were the id shall be set
+ * @param ep Pointer to the function the task shall start with
+ * @param arg Arguments list
+ * @param prio Desired priority of the new task
+ * @param core_id The new task runs only on this core. With MAX_CORES, the
+ *        core is chosen by the affinity of the current task.
+ * @param flags CLONE_JOINABLE keeps the task after its termination until
+ *        it's joined, otherwise the task is detached
+ *
+ * @return
+ * - 0 on success
+ * - -ENOMEM (-12) or -EINVAL (-22) on failure
+ */
+int clone_task_on_core(tid_t* id, entry_point_t ep, void* arg, uint8_t prio, uint32_t core_id, uint32_t flags);
+
+/** @brief Set the cores, on which a task is allowed to run
+ *
+ * A task on a core outside of the new set is migrated, when it's
+ * scheduled the next time.
+ *
+ * @param id Id of the task, 0 for the current task
+ * @param affinity Allowed cores
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) if the set doesn't contain an available core
+ * - -EBUSY (-16) if the task is a deadline task
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int set_task_affinity(tid_t id, const cpu_set_t* affinity);
+
+/** @brief Get the cores, on which a task is allowed to run
+ *
+ * @param id Id of the task, 0 for the current task
+ * @param affinity Receives the allowed cores
+ * @return
+ * - 0 on success
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int get_task_affinity(tid_t id, cpu_set_t* affinity);
+
+/** @brief Set the scheduling policy and the priority of a task
+ *
+ * Tasks with SCHED_OTHER or SCHED_RR share a priority level by
+ * time slices of SCHED_RR_QUANTUM microseconds. A task with
+ * SCHED_FIFO keeps the core, until it blocks, yields or a task
+ * with a higher priority is ready. A deadline task
+ * releases its utilization of the core.
+ *
+ * @param id Id of the task, 0 for the current task
+ * @param policy SCHED_OTHER, SCHED_FIFO or SCHED_RR
+ * @param prio New priority, 0 keeps the current priority
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) on an invalid policy or priority
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int set_task_scheduler(tid_t id, int policy, uint8_t prio);
+
+/** @brief Get the scheduling policy and the priority of a task
+ *
+ * @param id Id of the task, 0 for the current task
+ * @param policy Receives the policy, may be NULL
+ * @param prio Receives the priority, may be NULL
+ * @return
+ * - 0 on success
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int get_task_scheduler(tid_t id, int* policy, uint8_t* prio);
+
+/** @brief Schedule a task by earliest deadline first (SCHED_DEADLINE)
+ *
+ * In each period, the task gets its runtime until its relative
+ * deadline. Ready deadline tasks precede all priorities. A task,
+ * which exceeds its runtime, is throttled until its next period.
+ * The task is only admitted, if the utilization of all deadline
+ * tasks of its core stays below 95%. Afterwards, the task is bound
+ * to this core.
+ *
+ * @param id Id of the task, 0 for the current task
+ * @param runtime Runtime per period in nanoseconds
+ * @param deadline Relative deadline in nanoseconds
+ * @param period Period in nanoseconds, 0 for the deadline
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) if runtime <= deadline <= period doesn't hold
+ * - -EBUSY (-16) if the utilization of the core doesn't permit the task
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int set_task_deadline(tid_t id, uint64_t runtime, uint64_t deadline, uint64_t period);
+
+/** @brief Get the runtime, deadline and period of a deadline task in nanoseconds
+ *
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) if the task isn't a deadline task
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int get_task_deadline(tid_t id, uint64_t* runtime, uint64_t* deadline, uint64_t* period);
+
 
 /** @brief Create a task with a specific entry point
  *
@@ -148,6 +254,16 @@ void finish_task_switch(void);
  */
 uint32_t get_highest_priority(void);
 
+/** @brief Check, if a ready task preempts the current task
+ *
+ * A ready task preempts by a higher priority. A ready deadline
+ * task preempts all other tasks or a deadline task with a later
+ * deadline.
+ *
+ * @return 1 if the scheduler has to switch the task, otherwise 0
+ */
+int need_reschedule(void);
+
 
 /** @brief Call to rescheduling
  *
@@ -155,6 +271,13 @@ uint32_t get_highest_priority(void);
  */
 void reschedule(void);
 
+/** @brief Release the core in favor of the ready tasks with the same priority
+ *
+ * The current task is moved to the tail of its ready queue,
+ * independent of its scheduling policy and its time slice.
+ */
+void yield_task(void);
+
 
 /** @brief Wake up a blocked task
  *
@@ -175,6 +298,15 @@ int wakeup_task(tid_t);
  */
 void wakeup_core(uint32_t core_id);
 
+/** @brief Interrupt core_id to reschedule
+ *
+ * A busy core checks, if a new ready task
+ * preempts its current task.
+ *
+ * @param core_id Specifies the core
+ */
+void preempt_core(uint32_t core_id);
+
 /** @brief Block current task
  *
  * The current task's status will be changed to TASK_BLOCKED
@@ -219,13 +351,62 @@ int get_task(tid_t id, task_t** task);
  */
 int set_timer(uint64_t deadline);
 
+/** @brief Block current task until timer expires
+ *
+ * In contrast to set_timer(), the deadline isn't rounded to clock ticks.
+ *
+ * @param deadline Value of the cycle counter (get_rdtsc()), when the timer expires
+ * @return
+ *  - 0 on success
+ *  - -EINVAL (-22) on failure
+ */
+int set_timer_cycles(uint64_t deadline);
+
 /** @brief check is a timer is expired */
 void check_timers(void);
 
+/** @brief Periodically move waiting tasks to less loaded cores */
+void check_balance(void);
+
+
+/** @brief Terminate the current task
+ *
+ * A joining task receives arg as exit code.
+ *
+ * @param arg Exit code of the task
+ */
+void NORETURN do_exit(int arg);
 
 /** @brief Abort current task */
 void NORETURN do_abort(void);
 
+/** @brief Wait for the termination of a task
+ *
+ * A task cloned with CLONE_JOINABLE stays a zombie after its termination,
+ * until another task joins it. Afterwards, its id may be reused.
+ *
+ * @param id Id of the task
+ * @param exit_code Receives the argument of do_exit(), may be NULL
+ * @return
+ * - 0 on success
+ * - -EDEADLK (-35) if the current task joins itself
+ * - -EINVAL (-22) if the task is detached or another task joins it
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int join_task(tid_t id, int* exit_code);
+
+/** @brief Reap a task on its termination without joining it
+ *
+ * A task, which is already finished, is reaped immediately.
+ *
+ * @param id Id of the task, 0 for the current task
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) if the task is already detached or another task joins it
+ * - -ESRCH (-3) if the task doesn't exist
+ */
+int detach_task(tid_t id);
+
 
 /** @brief This function shall be called by leaving kernel-level tasks */
 void NORETURN leave_kernel_task(void);
@@ -239,6 +420,42 @@ void check_scheduling(void);
 /** @brief return true if a task is available and ready. */
 int is_task_available(void);
 
+/** @brief Copy the FPU state of a task, which runs on this core
+ *
+ * The state is taken from the registers, if the task still owns the FPU.
+ */
+void task_fpu_save(task_t* task, union fpu_state* state);
+
+/** @brief Replace the FPU state of a task, which runs on this core */
+void task_fpu_restore(task_t* task, union fpu_state* state);
+
+/** @brief Consumed CPU cycles of the current task */
+uint64_t get_task_cycles(void);
+
+/** @brief Consumed CPU cycles of all tasks, including the terminated tasks */
+uint64_t get_process_cycles(void);
+
+/** @brief Arm or disarm an interval timer of the current task
+ *
+ * ITIMER_REAL is measured in cycles of the cycle counter, ITIMER_VIRTUAL
+ * and ITIMER_PROF in consumed CPU cycles of the task. On expiry, the task receives
+ * SIGALRM, SIGVTALRM or SIGPROF and the timer is reloaded with interval.
+ *
+ * @param which ITIMER_REAL, ITIMER_VIRTUAL or ITIMER_PROF
+ * @param value Time until the first expiration, 0 to disarm the timer
+ * @param interval Reload value, 0 for a one-shot timer
+ * @param old_value Previous remaining time (may be NULL)
+ * @param old_interval Previous reload value (may be NULL)
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) on an invalid timer
+ */
+int set_itimer(uint32_t which, uint64_t value, uint64_t interval,
+               uint64_t* old_value, uint64_t* old_interval);
+
+/** @brief Remaining time and reload value of an interval timer */
+int get_itimer(uint32_t which, uint64_t* value, uint64_t* interval);
+
 /** @brief This function shutdowns the (ip) network */
 int network_shutdown(void);
 
@@ -263,6 +480,7 @@ static inline void check_workqueues_in_irqhandler(int irq)
 #endif
 
 	check_timers();
+	check_balance();
 
 	if (go_down)
 		shutdown_system();
@@ -41,6 +41,7 @@
 #include <hermit/spinlock_types.h>
 #include <hermit/vma.h>
 #include <hermit/signal.h>
+#include <sys/sched.h>
 #include <asm/tasks_types.h>
 #include <asm/atomic.h>
 
@@ -54,11 +55,15 @@ extern "C" {
 #define TASK_BLOCKED	3
 #define TASK_FINISHED	4
 #define TASK_IDLE	5
+#define TASK_ZOMBIE	6
 
 #define TASK_DEFAULT_FLAGS	0
 #define TASK_FPU_INIT		(1 << 0)
 #define TASK_FPU_USED		(1 << 1)
 #define TASK_TIMER		(1 << 2)
+#define TASK_INTERRUPTIBLE	(1 << 3)
+#define TASK_DL_THROTTLED	(1 << 4)
+#define TASK_YIELD		(1 << 5)
 
 #define MAX_PRIO	31
 #define REALTIME_PRIO	31
@@ -69,6 +74,27 @@ extern "C" {
 
 typedef int (*entry_point_t)(void*);
 
+/// Number of interval timers per task (ITIMER_REAL, ITIMER_VIRTUAL, ITIMER_PROF)
+#define MAX_ITIMERS	3
+
+/** @brief Interval timer of a task */
+typedef struct itimer {
+	/// expiration as value of the cycle counter (ITIMER_REAL) or consumed CPU cycles, 0 if disarmed
+	uint64_t		expires;
+	/// reload value in cycles, 0 for a one-shot timer
+	uint64_t		interval;
+	/// task, which receives the signal
+	tid_t			owner;
+	/// core, whose queue contains the timer (ITIMER_REAL only)
+	uint32_t		core;
+	/// next timer in the queue
+	struct itimer*		next;
+	/// previous timer in the queue
+	struct itimer*		prev;
+} itimer_t;
+
+struct fd_table;
+
 /** @brief Represents a the process control block */
 typedef struct task {
 	/// Task id = position in the task table
@@ -87,14 +113,18 @@ typedef struct task {
 	uint8_t			flags;
 	/// Task priority
 	uint8_t			prio;
-	/// timeout for a blocked task
+	/// timeout for a blocked task (value of the cycle counter)
 	uint64_t		timeout;
 	/// starting time/tick of the task
 	uint64_t		start_tick;
 	/// last TSC, when the task got the CPU
 	uint64_t		last_tsc;
+	/// consumed CPU cycles (without the current time slice)
+	uint64_t		cpu_cycles;
 	/// the userspace heap
 	vma_t*			heap;
+	/// table of file descriptors (shared with the parent)
+	struct fd_table*	fd_table;
 	/// parent thread
 	tid_t			parent;
 	/// next task in the queue
@@ -107,10 +137,46 @@ typedef struct task {
 	size_t		tls_size;
 	/// LwIP error code
 	int		lwip_err;
-	/// Handler for (POSIX) Signals
-	signal_handler_t signal_handler;
+	/// blocked signals
+	sigset_t	sig_mask;
+	/// pending signals
+	volatile sigset_t sig_pending;
 	/// FPU state
 	union fpu_state	fpu;
+	/// interval timers (see setitimer)
+	itimer_t	itimers[MAX_ITIMERS];
+	/// number of executed signal handlers, interrupts sleeps
+	volatile uint32_t signal_count;
+	/// number of executed signal handlers without SA_RESTART
+	volatile uint32_t signal_intr_count;
+	/// information about the pending signals
+	siginfo_t	sig_info[NSIG];
+	/// alternate stack of the SA_ONSTACK handlers (see sigaltstack)
+	stack_t		sig_altstack;
+	/// cores, on which the task is allowed to run
+	cpu_set_t	affinity;
+	/// scheduling policy (SCHED_OTHER, SCHED_FIFO, SCHED_RR or SCHED_DEADLINE)
+	uint32_t	policy;
+	/// end of the time slice (get_rdtsc()) for round-robin scheduling
+	uint64_t	slice_end;
+	/// runtime of a deadline task per period in cycles
+	uint64_t	dl_runtime;
+	/// relative deadline of a deadline task in cycles
+	uint64_t	dl_deadline;
+	/// period of a deadline task in cycles
+	uint64_t	dl_period;
+	/// absolute deadline (get_rdtsc()) of the current job
+	uint64_t	dl_abs_deadline;
+	/// remaining runtime of the current job in cycles
+	int64_t		dl_budget;
+	/// time stamp (get_rdtsc()), since which the budget is charged
+	uint64_t	dl_tsc;
+	/// exit code of a finished task (see do_exit)
+	int		exit_code;
+	/// task, which waits for the termination of this task (0 = none)
+	tid_t		joiner;
+	/// a detached task is reaped on its termination, otherwise it has to be joined
+	uint8_t		detached;
 } task_t;
 
 typedef struct {
@@ -134,6 +200,14 @@ typedef struct {
 	task_list_t	queue[MAX_PRIO];
 	/// a queue for timers
 	task_list_t     timers;
+	/// a queue for the interval timers ITIMER_REAL, sorted by the expiration
+	itimer_t*	itimers;
+	/// time stamp (get_rdtsc()) of the last load balancing
+	uint64_t	balance_tsc;
+	/// ready deadline tasks, sorted by their absolute deadlines
+	task_list_t	dl_queue;
+	/// utilization of the admitted deadline tasks (fixed point, see DL_BW_SHIFT)
+	uint64_t	dl_bw;
 	/// lock for this runqueue
 	spinlock_irqsave_t lock;
 } readyqueues_t;
@@ -87,6 +87,52 @@ static inline void sleep(unsigned int sec) { timer_wait(sec*TIMER_FREQ); }
 /** @brief Get milliseconds since system boot */
 uint64_t get_uptime(void);
 
+#define NSEC_PER_SEC	1000000000ULL
+#define NSEC_PER_USEC	1000ULL
+
+/** @brief Value of the cycle counter at boot time */
+uint64_t get_boot_tsc(void);
+
+/** @brief Frequency of the cycle counter in Hz (0, if it isn't known yet) */
+uint64_t get_tsc_frequency(void);
+
+/** @brief Wall-clock time in nanoseconds since the epoch
+ *
+ * The time is read from the real time clock or from the host.
+ *
+ * @return The current time or 0, if it isn't available
+ */
+uint64_t get_wallclock(void);
+
+/** @brief Initialize the time page
+ *
+ * The time page describes CLOCK_MONOTONIC and CLOCK_REALTIME. It is
+ * mapped read-only into the user space (see sys_get_time_page()).
+ */
+int time_init(void);
+
+/** @brief Get nanoseconds since system boot */
+uint64_t get_uptime_ns(void);
+
+/** @brief Convert cycles of the cycle counter into nanoseconds */
+uint64_t cycles_to_ns(uint64_t cycles);
+
+/** @brief Convert nanoseconds into cycles of the cycle counter (rounded up) */
+uint64_t ns_to_cycles(uint64_t ns);
+
+/** @brief Sleep until the cycle counter reaches a deadline
+ *
+ * In contrast to timer_wait(), the deadline isn't rounded to clock ticks.
+ * Early wakeups are ignored.
+ *
+ * @param deadline Value of the cycle counter (get_rdtsc())
+ * @param interruptible Stop sleeping, if a signal handler has been executed
+ * @return
+ * - 0 on success
+ * - -EINTR (-4) if the sleep was interrupted by a signal
+ */
+int timer_wait_cycles(uint64_t deadline, int interruptible);
+
 #ifdef __cplusplus
 }
 #endif
@@ -0,0 +1,138 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file include/hermit/time_page.h
+ * @brief Read-only page, which describes the clocks of the kernel
+ *
+ * The page contains the parameters to convert the cycle counter into
+ * nanoseconds. Therefore, user-level code is able to read the time without
+ * entering the kernel. The kernel changes the parameters only within a
+ * sequence lock: the sequence number is odd while an update is in progress.
+ *
+ * This header doesn't depend on kernel headers outside of __KERNEL__.
+ */
+
+#ifndef __TIME_PAGE_H__
+#define __TIME_PAGE_H__
+
+#ifdef __KERNEL__
+#include <hermit/stddef.h>
+#include <asm/processor.h>
+#else
+#include <stdint.h>
+#endif
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/// Feature bit of the boot header: the host provides the wall-clock time
+#define UHYVE_FEATURE_TIME	(1 << 1)
+
+/** @brief Argument of UHYVE_PORT_TIME, filled by the host */
+typedef struct uhyve_time {
+	/// seconds since the epoch
+	uint64_t sec;
+	/// nanoseconds within the current second
+	uint64_t nsec;
+} __attribute__((packed)) uhyve_time_t;
+
+/** @brief Layout of the time page */
+typedef struct time_page {
+	/// Sequence number, odd while the kernel updates the page
+	volatile uint32_t seq;
+	/// ns = (cycles * mult) >> shift
+	uint32_t shift;
+	uint64_t mult;
+	/// Value of the cycle counter at boot time
+	uint64_t tsc_base;
+	/// Nanoseconds since the epoch at boot time
+	uint64_t realtime_base;
+	/// Frequency of the cycle counter in Hz
+	uint64_t freq;
+} time_page_t;
+
+/** @brief Read the cycle counter, which is described by the time page */
+static inline uint64_t time_page_counter(void)
+{
+#ifdef __KERNEL__
+	return get_rdtsc();
+#elif defined(__x86_64__)
+	uint32_t lo, hi;
+
+	asm volatile ("lfence; rdtsc" : "=a"(lo), "=d"(hi) :: "memory");
+
+	return ((uint64_t) hi << 32) | lo;
+#elif defined(__aarch64__)
+	uint64_t val;
+
+	asm volatile ("isb; mrs %0, cntvct_el0" : "=r"(val) :: "memory");
+
+	return val;
+#else
+#error "time_page_counter() isn't supported on this architecture"
+#endif
+}
+
+/** @brief Convert cycles into nanoseconds */
+static inline uint64_t time_page_cycles_to_ns(const time_page_t* tp, uint64_t cycles)
+{
+	return (uint64_t) (((unsigned __int128) cycles * tp->mult) >> tp->shift);
+}
+
+/** @brief Read the time in nanoseconds
+ *
+ * @param tp Pointer to the time page
+ * @param realtime Nonzero for the time since the epoch, 0 for the time since boot
+ */
+static inline uint64_t time_page_read(const time_page_t* tp, int realtime)
+{
+	uint32_t seq;
+	uint64_t ns;
+
+	do {
+		while ((seq = tp->seq) & 1)
+			;
+		__atomic_thread_fence(__ATOMIC_ACQUIRE);
+
+		ns = time_page_cycles_to_ns(tp, time_page_counter() - tp->tsc_base);
+		if (realtime)
+			ns += tp->realtime_base;
+
+		__atomic_thread_fence(__ATOMIC_ACQUIRE);
+	} while (tp->seq != seq);
+
+	return ns;
+}
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,208 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file include/hermit/uhyve_ring.h
+ * @brief Shared-memory hypercall ring of uhyve
+ *
+ * Instead of one VM exit per hypercall, the kernel writes the hypercalls
+ * into a submission queue in guest memory. The host processes the entries
+ * asynchronously, writes the results into the argument structures (as for
+ * port I/O) and signals completions by the completion queue and an
+ * interrupt. The host announces the support by UHYVE_FEATURE_RING in the
+ * boot header, the kernel registers the ring by UHYVE_PORT_RING.
+ *
+ * The layout of the ring and its producer and consumer functions are
+ * shared with the host. Therefore, this header doesn't depend on kernel
+ * headers outside of __KERNEL__.
+ */
+
+#ifndef __UHYVE_RING_H__
+#define __UHYVE_RING_H__
+
+#ifdef __KERNEL__
+#include <hermit/stddef.h>
+#include <hermit/errno.h>
+#else
+#include <stdint.h>
+#include <errno.h>
+#endif
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/// Feature bit of the boot header: the host supports the hypercall ring
+#define UHYVE_FEATURE_RING	(1 << 0)
+
+/// Interrupt, which signals new completions
+#define UHYVE_IRQ_RING		12
+
+/// Number of entries of the submission and the completion queue
+#define UHYVE_RING_ENTRIES	64
+
+/// Set by the host, if it waits for a notification (UHYVE_PORT_RING_NOTIFY)
+#define UHYVE_RING_NEED_NOTIFY	(1 << 0)
+
+/** @brief Submitted hypercall */
+typedef struct uhyve_sqe {
+	/// Cookie of the request, returned by the completion
+	uint64_t id;
+	/// Port of the hypercall
+	uint32_t port;
+	uint32_t reserved;
+	/// Guest physical address of the argument structure
+	uint64_t data;
+} __attribute__((packed)) uhyve_sqe_t;
+
+/** @brief Completed hypercall */
+typedef struct uhyve_cqe {
+	/// Cookie of the request
+	uint64_t id;
+} __attribute__((packed)) uhyve_cqe_t;
+
+/** @brief Layout of the ring in guest memory
+ *
+ * The heads are written by the consumer, the tails by the producer.
+ * All indices are free running and taken modulo UHYVE_RING_ENTRIES.
+ */
+typedef struct uhyve_ring {
+	/// Next submission, which is consumed by the host
+	volatile uint32_t sq_head;
+	/// Next free submission slot, written by the kernel
+	volatile uint32_t sq_tail;
+	/// Next completion, which is consumed by the kernel
+	volatile uint32_t cq_head;
+	/// Next free completion slot, written by the host
+	volatile uint32_t cq_tail;
+	/// Flags of the host, e.g. UHYVE_RING_NEED_NOTIFY
+	volatile uint32_t flags;
+	uint32_t reserved[3];
+	uhyve_sqe_t sq[UHYVE_RING_ENTRIES];
+	uhyve_cqe_t cq[UHYVE_RING_ENTRIES];
+} __attribute__((packed)) uhyve_ring_t;
+
+/** @brief Argument of UHYVE_PORT_RING */
+typedef struct uhyve_ring_setup {
+	/// Guest physical address of the ring
+	uint64_t ring;
+	/// Number of entries per queue
+	uint32_t entries;
+	/// Interrupt of completions
+	uint32_t irq;
+	/// 0, if the host uses the ring
+	int32_t ret;
+} __attribute__((packed)) uhyve_ring_setup_t;
+
+/** @brief Append a hypercall to the submission queue
+ *
+ * The producers of the ring have to be serialized by the caller.
+ *
+ * @param r The ring
+ * @param id Cookie of the request
+ * @param port Port of the hypercall
+ * @param data Guest physical address of the argument structure
+ * @return
+ * - 0 on success
+ * - 1 on success, if the host waits for a notification
+ * - -EBUSY (-16) if the submission queue is full
+ */
+static inline int uhyve_ring_submit(uhyve_ring_t* r, uint64_t id, uint32_t port, uint64_t data)
+{
+	const uint32_t tail = r->sq_tail;
+	uhyve_sqe_t* sqe = r->sq + (tail % UHYVE_RING_ENTRIES);
+
+	if (tail - r->sq_head >= UHYVE_RING_ENTRIES)
+		return -EBUSY;
+
+	sqe->id = id;
+	sqe->port = port;
+	sqe->reserved = 0;
+	sqe->data = data;
+
+	// publish the entry before the new tail and read the flags afterwards
+	__sync_synchronize();
+	r->sq_tail = tail + 1;
+	__sync_synchronize();
+
+	return (r->flags & UHYVE_RING_NEED_NOTIFY) ? 1 : 0;
+}
+
+/** @brief Take the next entry of the completion queue
+ *
+ * The consumers of the completions have to be serialized by the caller.
+ *
+ * @param r The ring
+ * @param id Cookie of the completed request
+ * @return
+ * - 1 if a completion is taken
+ * - 0 if the completion queue is empty
+ */
+static inline int uhyve_ring_complete(uhyve_ring_t* r, uint64_t* id)
+{
+	const uint32_t head = r->cq_head;
+
+	if (head == r->cq_tail)
+		return 0;
+
+	// read the entry after the tail, which publishes it
+	__sync_synchronize();
+	*id = r->cq[head % UHYVE_RING_ENTRIES].id;
+	r->cq_head = head + 1;
+
+	return 1;
+}
+
+#ifdef __KERNEL__
+
+/** @brief Register the hypercall ring, if the host supports it
+ *
+ * @return
+ * - 0 on success
+ * - -ENOSYS (-38) if the host doesn't support the ring
+ * - -ENOMEM (-12) on a lack of memory
+ */
+int uhyve_ring_init(void);
+
+/** @brief Hypercall, which blocks the current task until its completion
+ *
+ * The hypercall is submitted to the ring, if it's available and the
+ * current task is able to block. Otherwise, it falls back to uhyve_send().
+ *
+ * @param port Port of the hypercall
+ * @param data Guest physical address of the argument structure
+ */
+void uhyve_call(unsigned short port, unsigned int data);
+
+#endif
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,280 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @author Stefan Lankes
+ * @file include/hermit/vfs.h
+ * @brief Virtual file system and file descriptor table
+ *
+ * Every file descriptor refers to a file object, which provides a table
+ * of file operations. Host files, sockets and in-kernel file systems
+ * implement these operations.
+ */
+
+#ifndef __VFS_H__
+#define __VFS_H__
+
+#include <hermit/stddef.h>
+#include <hermit/spinlock_types.h>
+#include <asm/atomic.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/// Maximum number of open files per file descriptor table
+#define MAX_FILES	256
+
+/* open flags, the values are identical to newlib's definitions */
+#define O_RDONLY	0x0000
+#define O_WRONLY	0x0001
+#define O_RDWR		0x0002
+#define O_ACCMODE	0x0003
+#define O_APPEND	0x0008
+#define O_CREAT		0x0200
+#define O_TRUNC		0x0400
+#define O_EXCL		0x0800
+#define O_NONBLOCK	0x4000
+
+#define SEEK_SET	0
+#define SEEK_CUR	1
+#define SEEK_END	2
+
+struct file;
+struct iovec;
+struct stat;
+struct dirent;
+
+/** @brief Operations on an open file
+ *
+ * Only read, write and close are mandatory. If readv/writev are missing,
+ * the VFS splits the request into several calls of read/write.
+ */
+typedef struct file_ops {
+	ssize_t (*read)(struct file* file, char* buf, size_t len);
+	ssize_t (*write)(struct file* file, const char* buf, size_t len);
+	ssize_t (*readv)(struct file* file, const struct iovec* iov, int iovcnt);
+	ssize_t (*writev)(struct file* file, const struct iovec* iov, int iovcnt);
+	off_t (*lseek)(struct file* file, off_t offset, int whence);
+	int (*fstat)(struct file* file, struct stat* st);
+	/// returns the subset of the requested events, which are ready,
+	/// the result mustn't change, because poll doesn't wait for files
+	int (*poll)(struct file* file, int events);
+	int (*ioctl)(struct file* file, unsigned long cmd, void* arg);
+	int (*truncate)(struct file* file, off_t length);
+	/// returns 1 if an entry is read, 0 at the end of the directory
+	int (*readdir)(struct file* file, struct dirent* entry);
+	int (*close)(struct file* file);
+} file_ops_t;
+
+/** @brief Open file */
+typedef struct file {
+	/// operations of the backend
+	const file_ops_t* ops;
+	/// number of references (fd table entries and pending calls)
+	atomic_int32_t refs;
+	/// flags, which are used to open the file
+	int flags;
+	/// descriptor of the backend (e.g. the file descriptor of the host)
+	int handle;
+	/// current file position
+	off_t offset;
+	/// private data of the backend
+	void* priv;
+} file_t;
+
+/** @brief Operations of a mounted file system
+ *
+ * The path is relative to the mount point. Only open is mandatory,
+ * missing operations return -ENOSYS.
+ */
+typedef struct fs_ops {
+	int (*open)(void* fs, const char* path, int flags, int mode, file_t* file);
+	/// follow is zero, if a symbolic link itself should be examined
+	int (*stat)(void* fs, const char* path, struct stat* st, int follow);
+	int (*mkdir)(void* fs, const char* path, int mode);
+	int (*rmdir)(void* fs, const char* path);
+	int (*unlink)(void* fs, const char* path);
+	/// both paths belong to the same file system
+	int (*rename)(void* fs, const char* oldpath, const char* newpath);
+	int (*opendir)(void* fs, const char* path, file_t* file);
+} fs_ops_t;
+
+/** @brief Table of file descriptors
+ *
+ * The table is shared between all threads of an application.
+ */
+typedef struct fd_table {
+	/// number of tasks, which share the table
+	atomic_int32_t refs;
+	/// lock for the table
+	spinlock_irqsave_t lock;
+	/// open files
+	file_t* files[MAX_FILES];
+} fd_table_t;
+
+/** @brief Initialize the VFS and mount the host file system as root */
+int vfs_init(void);
+
+/** @brief Mount a file system
+ *
+ * @param path Mount point
+ * @param ops Operations of the file system
+ * @param fs Private data of the file system
+ * @return
+ * - 0 on success
+ * - -ENOMEM (-12) or -EINVAL (-22) on failure
+ */
+int vfs_mount(const char* path, const fs_ops_t* ops, void* fs);
+
+/** @brief Create a file descriptor table for the current task
+ *
+ * The file descriptors 0, 1 and 2 refer to the standard streams of the host.
+ */
+int fd_table_init(void);
+
+/** @brief Take a reference of a table, which is shared with a cloned task */
+void fd_table_get(fd_table_t* table);
+
+/** @brief Drop the reference of the current task, the last one closes all files */
+void fd_table_put(void);
+
+/** @brief Allocate a new file object with one reference */
+file_t* file_alloc(const file_ops_t* ops, int flags);
+
+/** @brief Install a file in the table of the current task
+ *
+ * @return
+ * - the new file descriptor on success
+ * - -EMFILE (-24) if the table is full
+ */
+int fd_install(file_t* file);
+
+/** @brief Get the file of a file descriptor and increase its reference counter
+ *
+ * @return
+ * - 0 on success
+ * - -EBADF (-9) if the descriptor is invalid
+ */
+int fd_get(int fd, file_t** file);
+
+/** @brief Release a reference, which is obtained by fd_get() */
+void fd_put(file_t* file);
+
+/** @brief Remove a file descriptor from the table */
+int fd_close(int fd);
+
+/** @brief Open a file by the mounted file systems
+ *
+ * @return
+ * - the new file descriptor on success
+ * - a negative error code on failure
+ */
+int vfs_open(const char* path, int flags, int mode);
+
+/** @brief Open a directory
+ *
+ * The entries are read by sys_readdir(), the descriptor is
+ * released by sys_close().
+ *
+ * @return
+ * - the new file descriptor on success
+ * - a negative error code on failure
+ */
+int vfs_opendir(const char* path);
+
+/** @brief Get the status of a file
+ *
+ * @param follow Zero, if a symbolic link itself should be examined
+ */
+int vfs_stat(const char* path, struct stat* st, int follow);
+
+/** @brief Create a directory */
+int vfs_mkdir(const char* path, int mode);
+
+/** @brief Remove an empty directory */
+int vfs_rmdir(const char* path);
+
+/** @brief Remove a file */
+int vfs_unlink(const char* path);
+
+/** @brief Rename a file
+ *
+ * @return
+ * - 0 on success
+ * - -EXDEV (-18) if both paths belong to different file systems
+ */
+int vfs_rename(const char* oldpath, const char* newpath);
+
+/** @brief Returns the file object of a LwIP socket
+ *
+ * The object is shared by all users of the socket and released by fd_put().
+ *
+ * @return
+ * - 0 on success
+ * - -EBADF (-9) if the descriptor doesn't refer to a socket
+ */
+int socket_get_file(int fd, file_t** file);
+
+/** @brief Close a LwIP socket
+ *
+ * @return
+ * - 0 on success
+ * - -EBADF (-9) if the descriptor doesn't refer to a socket
+ * - the negative error number of LwIP on failure
+ */
+int socket_close(int fd);
+
+/** @brief Create a file object for a file descriptor of the host */
+file_t* host_file_alloc(int host_fd, int flags);
+
+extern const fs_ops_t host_fs_ops;
+
+/** @brief Create an empty RAM-backed file system
+ *
+ * @return Private data, which has to be passed to vfs_mount()
+ * with tmpfs_ops
+ */
+void* tmpfs_create(void);
+
+extern const fs_ops_t tmpfs_ops;
+
+/** @brief Create a read-only file system from the initrd
+ *
+ * @return Private data, which has to be passed to vfs_mount()
+ * with initramfs_ops, or NULL if no initrd is available
+ */
+void* initramfs_create(void);
+
+extern const fs_ops_t initramfs_ops;
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -56,6 +56,10 @@ extern "C" {
 #define VMA_NO_ACCESS	(1 << 4)
 /// This VMA should be part of the userspace
 #define VMA_USER	(1 << 5)
+/// Anonymous memory (mmap), which is mapped on demand by the page fault handler
+#define VMA_ANON	(1 << 6)
+/// Read-only mapping of a host file, which is mapped on demand by the page fault handler
+#define VMA_FILE	(1 << 7)
 /// A collection of flags used for the kernel heap (kmalloc)
 #define VMA_HEAP	(VMA_READ|VMA_WRITE|VMA_CACHEABLE)
 
@@ -139,6 +143,43 @@ size_t vma_alloc(size_t size, uint32_t flags);
  */
 int vma_free(size_t start, size_t end);
 
+/** @brief Get the flags of the VMA, which contains an address
+ *
+ * @return
+ * - 0 on success
+ * - -EINVAL (-22) if the address isn't part of a VMA
+ */
+int vma_get_flags(size_t addr, uint32_t* flags);
+
+/** @brief Check if a range is completely covered by VMAs with the given flags
+ *
+ * @return 1 if all VMAs in the range contain the flags, otherwise 0
+ */
+int vma_check(size_t start, size_t end, uint32_t flags);
+
+/** @brief Change the flags of a range
+ *
+ * The range has to be completely covered by VMAs, which are split
+ * at the boundaries of the range. Afterwards, the range is described
+ * by a single VMA.
+ *
+ * @return
+ * - 0 on success
+ * - -ENOMEM (-12) if the range isn't covered or on a lack of memory
+ */
+int vma_set_flags(size_t start, size_t end, uint32_t flags);
+
+/** @brief Read the contents of a file mapping into a frame
+ *
+ * The page fault handler uses this function to resolve faults in
+ * VMAs with VMA_FILE.
+ *
+ * @param viraddr Faulting address
+ * @param phyaddr Physical address of the frame, which backs the page
+ * @return Number of read bytes or a negative error number
+ */
+ssize_t mmap_file_read(size_t viraddr, size_t phyaddr);
+
 /** @brief Dump information about this task's VMAs into the terminal. */
 void vma_dump(void);
 
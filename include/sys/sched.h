@@ -0,0 +1,96 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#ifndef __SYS_SCHED_H__
+#define __SYS_SCHED_H__
+
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* scheduling policies, compatible to newlib */
+/// time-sliced round robin within a priority (default)
+#define SCHED_OTHER	0
+/// runs until it blocks, yields or a task with a higher priority is ready
+#define SCHED_FIFO	1
+/// time-sliced round robin within a priority
+#define SCHED_RR	2
+/// earliest deadline first, precedes all priorities
+#define SCHED_DEADLINE	6
+
+struct sched_param {
+	int	sched_priority;
+};
+
+/** @brief Scheduling attributes, compatible to Linux
+ *
+ * The times of SCHED_DEADLINE are specified in nanoseconds.
+ */
+struct sched_attr {
+	uint32_t	size;
+	uint32_t	sched_policy;
+	uint64_t	sched_flags;
+	int32_t		sched_nice;
+	uint32_t	sched_priority;
+	uint64_t	sched_runtime;
+	uint64_t	sched_deadline;
+	uint64_t	sched_period;
+};
+
+/// maximal number of cores in a cpu_set_t
+#define CPU_SETSIZE	1024
+
+#define __CPU_BITS	(8 * sizeof(unsigned long))
+
+/** @brief Set of cores, on which a task is allowed to run */
+typedef struct {
+	unsigned long	__bits[CPU_SETSIZE / __CPU_BITS];
+} cpu_set_t;
+
+#define CPU_ZERO(set)		__builtin_memset((set), 0x00, sizeof(cpu_set_t))
+#define CPU_SET(cpu, set)	((set)->__bits[(cpu) / __CPU_BITS] |= (1UL << ((cpu) % __CPU_BITS)))
+#define CPU_CLR(cpu, set)	((set)->__bits[(cpu) / __CPU_BITS] &= ~(1UL << ((cpu) % __CPU_BITS)))
+#define CPU_ISSET(cpu, set)	(((set)->__bits[(cpu) / __CPU_BITS] & (1UL << ((cpu) % __CPU_BITS))) != 0)
+
+static inline int CPU_COUNT(const cpu_set_t* set)
+{
+	int i, count = 0;
+
+	for(i=0; i<CPU_SETSIZE / __CPU_BITS; i++)
+		count += __builtin_popcountl(set->__bits[i]);
+
+	return count;
+}
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -34,16 +34,22 @@
 extern "C" {
 #endif
 
+/* shmat flags */
+#define SHM_RDONLY	010000	/* read-only access */
+#define SHM_RND		020000	/* round attach address to SHMLBA boundary */
+
+#define SHMLBA		4096	/* attach address multiple */
+
 struct shmid_ds {
 	struct ipc_perm		shm_perm;	/* operation perms */
+	size_t			shm_segsz;	/* size of segment (bytes) */
+	unsigned short		shm_nattch;	/* no. of current attaches */
 #if 0
-        int                     shm_segsz;      /* size of segment (bytes) */
         __kernel_time_t         shm_atime;      /* last attach time */
         __kernel_time_t         shm_dtime;      /* last detach time */
         __kernel_time_t         shm_ctime;      /* last change time */
         __kernel_ipc_pid_t      shm_cpid;       /* pid of creator */
         __kernel_ipc_pid_t      shm_lpid;       /* pid of last operator */
-        unsigned short          shm_nattch;     /* no. of current attaches */
         unsigned short          shm_unused;     /* compatibility */
         void                    *shm_unused2;   /* ditto - used by DIPC */
         void                    *shm_unused3;   /* unused */
@@ -0,0 +1,163 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+
+#ifndef __SYS_SIGNAL_H__
+#define __SYS_SIGNAL_H__
+
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* the signal numbers are compatible to newlib */
+#define SIGHUP		1
+#define SIGINT		2
+#define SIGQUIT		3
+#define SIGILL		4
+#define SIGTRAP		5
+#define SIGABRT		6
+#define SIGEMT		7
+#define SIGFPE		8
+#define SIGKILL		9
+#define SIGBUS		10
+#define SIGSEGV		11
+#define SIGSYS		12
+#define SIGPIPE		13
+#define SIGALRM		14
+#define SIGTERM		15
+#define SIGURG		16
+#define SIGSTOP		17
+#define SIGTSTP		18
+#define SIGCONT		19
+#define SIGCHLD		20
+#define SIGTTIN		21
+#define SIGTTOU		22
+#define SIGIO		23
+#define SIGXCPU		24
+#define SIGXFSZ		25
+#define SIGVTALRM	26
+#define SIGPROF		27
+#define SIGWINCH	28
+#define SIGLOST		29
+#define SIGUSR1		30
+#define SIGUSR2		31
+
+#define NSIG		32
+
+typedef unsigned long sigset_t;
+
+/// bit of a signal within sigset_t
+#define sigmask(sig)	(1UL << (sig))
+
+#define SIG_SETMASK	0
+#define SIG_BLOCK	1
+#define SIG_UNBLOCK	2
+
+#define SA_NOCLDSTOP	0x00000001
+/// the handler takes a siginfo_t and the interrupted context
+#define SA_SIGINFO	0x00000002
+/// the handler runs on the alternate signal stack (see sigaltstack)
+#define SA_ONSTACK	0x08000000
+/// interrupted waits are restarted instead of returning EINTR
+#define SA_RESTART	0x10000000
+/// don't block the signal during the execution of its handler
+#define SA_NODEFER	0x40000000
+/// reset the handler to SIG_DFL on delivery
+#define SA_RESETHAND	0x80000000
+
+/* ss_flags of the alternate signal stack */
+#define SS_ONSTACK	1	/* the task executes on the stack */
+#define SS_DISABLE	2	/* the stack is disabled */
+
+#define MINSIGSTKSZ	2048
+#define SIGSTKSZ	8192
+
+/* values of si_code */
+#define SI_USER		1	/* sent by kill */
+#define SI_QUEUE	2	/* sent by sigqueue */
+#define SI_TIMER	3	/* expiration of a timer */
+#define SI_KERNEL	0x80	/* sent by the kernel */
+
+/* si_code of SIGILL */
+#define ILL_ILLOPC	1	/* illegal opcode */
+#define ILL_ILLOPN	2	/* illegal operand */
+/* si_code of SIGFPE */
+#define FPE_INTDIV	1	/* integer divide by zero */
+#define FPE_INTOVF	2	/* integer overflow */
+#define FPE_FLTDIV	3	/* floating-point divide by zero */
+#define FPE_FLTOVF	4	/* floating-point overflow */
+#define FPE_FLTUND	5	/* floating-point underflow */
+#define FPE_FLTRES	6	/* floating-point inexact result */
+#define FPE_FLTINV	7	/* invalid floating-point operation */
+/* si_code of SIGSEGV */
+#define SEGV_MAPERR	1	/* address not mapped */
+#define SEGV_ACCERR	2	/* invalid permissions */
+/* si_code of SIGBUS */
+#define BUS_ADRALN	1	/* invalid address alignment */
+#define BUS_ADRERR	2	/* nonexistent physical address */
+/* si_code of SIGTRAP */
+#define TRAP_BRKPT	1	/* breakpoint */
+#define TRAP_TRACE	2	/* trace trap */
+
+union sigval {
+	int	sival_int;
+	void*	sival_ptr;
+};
+
+typedef struct {
+	int		si_signo;	/* signal number */
+	int		si_errno;	/* errno value */
+	int		si_code;	/* signal code */
+	int		si_pid;		/* id of the sending task */
+	void*		si_addr;	/* faulting address */
+	int		si_status;	/* exit value */
+	union sigval	si_value;	/* signal value */
+} siginfo_t;
+
+typedef void (*_sig_func_ptr)(int);
+
+struct sigaction {
+	union {
+		_sig_func_ptr	sa_handler;
+		void		(*sa_sigaction)(int, siginfo_t*, void*);
+	};
+	sigset_t	sa_mask;	/* additionally blocked signals */
+	int		sa_flags;
+};
+
+#define SIG_DFL		((_sig_func_ptr) 0)	/* default action */
+#define SIG_IGN		((_sig_func_ptr) 1)	/* ignore the signal */
+#define SIG_ERR		((_sig_func_ptr) -1)
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
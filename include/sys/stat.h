@@ -0,0 +1,82 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/*
+ * Kernel-side copy of newlib's struct stat. The layout has to match the
+ * definition in newlib's <sys/stat.h>, which is used by the application.
+ */
+#ifndef __SYS_STAT_H__
+#define __SYS_STAT_H__
+
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define S_IFMT		0170000	/* type of file */
+#define S_IFDIR		0040000	/* directory */
+#define S_IFCHR		0020000	/* character special */
+#define S_IFBLK		0060000	/* block special */
+#define S_IFREG		0100000	/* regular */
+#define S_IFLNK		0120000	/* symbolic link */
+#define S_IFSOCK	0140000	/* socket */
+#define S_IFIFO		0010000	/* fifo */
+
+#define S_ISDIR(m)	(((m) & S_IFMT) == S_IFDIR)
+#define S_ISCHR(m)	(((m) & S_IFMT) == S_IFCHR)
+#define S_ISREG(m)	(((m) & S_IFMT) == S_IFREG)
+#define S_ISLNK(m)	(((m) & S_IFMT) == S_IFLNK)
+#define S_ISSOCK(m)	(((m) & S_IFMT) == S_IFSOCK)
+#define S_ISFIFO(m)	(((m) & S_IFMT) == S_IFIFO)
+
+struct stat {
+	int16_t		st_dev;
+	uint16_t	st_ino;
+	uint32_t	st_mode;
+	uint16_t	st_nlink;
+	uint16_t	st_uid;
+	uint16_t	st_gid;
+	int16_t		st_rdev;
+	int64_t		st_size;
+	int64_t		st_atime;
+	long		st_spare1;
+	int64_t		st_mtime;
+	long		st_spare2;
+	int64_t		st_ctime;
+	long		st_spare3;
+	long		st_blksize;
+	long		st_blocks;
+	long		st_spare4[2];
+};
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
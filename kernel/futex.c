@@ -0,0 +1,427 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/futex.c
+ * @brief Fast user-space locking
+ *
+ * The waiting tasks are queued in a hash table, which is indexed by the
+ * address of the futex. Uncontended locks don't enter the kernel, only
+ * waiters and wakers of contended locks use sys_futex().
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/errno.h>
+#include <hermit/tasks.h>
+#include <hermit/spinlock.h>
+#include <hermit/time.h>
+#include <hermit/syscall.h>
+#include <hermit/logging.h>
+#include <hermit/futex.h>
+#include <sys/time.h>
+
+#define FUTEX_HASH_BITS		8
+#define FUTEX_HASH_SIZE		(1 << FUTEX_HASH_BITS)
+
+/** @brief Task, which waits on a futex */
+typedef struct futex_waiter {
+	/// Address of the futex, changed by FUTEX_REQUEUE
+	int* volatile uaddr;
+	/// Waiting task
+	tid_t id;
+	/// Set by the waker
+	volatile int woken;
+	/// Next waiter in the bucket
+	struct futex_waiter* next;
+	/// Previous waiter in the bucket
+	struct futex_waiter* prev;
+} futex_waiter_t;
+
+/** @brief Wait queue for all futexes with the same hash value */
+typedef struct futex_bucket {
+	futex_waiter_t* first;
+	futex_waiter_t* last;
+	spinlock_irqsave_t lock;
+} futex_bucket_t;
+
+static futex_bucket_t futex_queues[FUTEX_HASH_SIZE] = {
+	[0 ... FUTEX_HASH_SIZE-1] = {NULL, NULL, SPINLOCK_IRQSAVE_INIT}};
+
+static inline futex_bucket_t* futex_bucket(int* uaddr)
+{
+	const uint64_t key = (size_t) uaddr >> 2;
+
+	return futex_queues + ((key * 0x9E3779B97F4A7C15ULL) >> (64 - FUTEX_HASH_BITS));
+}
+
+static void queue_push(futex_bucket_t* bucket, futex_waiter_t* waiter)
+{
+	waiter->next = NULL;
+	waiter->prev = bucket->last;
+
+	if (bucket->last)
+		bucket->last->next = waiter;
+	else
+		bucket->first = waiter;
+	bucket->last = waiter;
+}
+
+static void queue_remove(futex_bucket_t* bucket, futex_waiter_t* waiter)
+{
+	if (waiter->prev)
+		waiter->prev->next = waiter->next;
+	else
+		bucket->first = waiter->next;
+
+	if (waiter->next)
+		waiter->next->prev = waiter->prev;
+	else
+		bucket->last = waiter->prev;
+
+	waiter->next = waiter->prev = NULL;
+}
+
+/* lock the bucket of a waiter, which could be requeued concurrently */
+static futex_bucket_t* lock_waiter_bucket(futex_waiter_t* waiter)
+{
+	while(1) {
+		int* uaddr = waiter->uaddr;
+		futex_bucket_t* bucket = futex_bucket(uaddr);
+
+		spinlock_irqsave_lock(&bucket->lock);
+		if (waiter->uaddr == uaddr)
+			return bucket;
+		spinlock_irqsave_unlock(&bucket->lock);
+	}
+}
+
+/* lock two buckets in a fixed order to avoid deadlocks */
+static void lock_buckets(futex_bucket_t* b1, futex_bucket_t* b2)
+{
+	if (b1 == b2) {
+		spinlock_irqsave_lock(&b1->lock);
+	} else if (b1 < b2) {
+		spinlock_irqsave_lock(&b1->lock);
+		spinlock_irqsave_lock(&b2->lock);
+	} else {
+		spinlock_irqsave_lock(&b2->lock);
+		spinlock_irqsave_lock(&b1->lock);
+	}
+}
+
+static void unlock_buckets(futex_bucket_t* b1, futex_bucket_t* b2)
+{
+	if (b1 != b2)
+		spinlock_irqsave_unlock(&b2->lock);
+	spinlock_irqsave_unlock(&b1->lock);
+}
+
+/* wake up to nr waiters of uaddr, the caller has to hold the lock of the bucket */
+static int futex_wake_locked(futex_bucket_t* bucket, int* uaddr, int nr)
+{
+	futex_waiter_t* waiter = bucket->first;
+	futex_waiter_t* next;
+	int woken = 0;
+
+	while (waiter && (woken < nr)) {
+		next = waiter->next;
+
+		if (waiter->uaddr == uaddr) {
+			// the waiter is able to leave after setting woken
+			const tid_t id = waiter->id;
+
+			queue_remove(bucket, waiter);
+			waiter->woken = 1;
+			wakeup_task(id);
+			woken++;
+		}
+
+		waiter = next;
+	}
+
+	return woken;
+}
+
+static int futex_wait(int* uaddr, int val, const struct timespec* timeout)
+{
+	task_t* curr_task = per_core(current_task);
+	const uint32_t intr_count = curr_task->signal_intr_count;
+	futex_waiter_t waiter;
+	futex_bucket_t* bucket;
+	uint64_t deadline = 0;
+	int ret = 0;
+
+	if (timeout) {
+		if (BUILTIN_EXPECT((timeout->tv_sec < 0) || (timeout->tv_nsec < 0)
+		    || (timeout->tv_nsec >= (long) NSEC_PER_SEC), 0))
+			return -EINVAL;
+
+		deadline = get_rdtsc() + ns_to_cycles((uint64_t) timeout->tv_sec * NSEC_PER_SEC + timeout->tv_nsec);
+	}
+
+	waiter.uaddr = uaddr;
+	waiter.id = curr_task->id;
+	waiter.woken = 0;
+
+	bucket = futex_bucket(uaddr);
+	spinlock_irqsave_lock(&bucket->lock);
+
+	// the value has already changed => don't miss the wakeup
+	if (*((volatile int*) uaddr) != val) {
+		spinlock_irqsave_unlock(&bucket->lock);
+		return -EAGAIN;
+	}
+
+	queue_push(bucket, &waiter);
+
+	while(1) {
+		/*
+		 * The lock of the bucket disables the interrupts. Hence, a signal
+		 * is either handled before this check or its IPI arrives after
+		 * blocking the task and wakes it up.
+		 */
+		if (curr_task->signal_intr_count != intr_count) {
+			ret = -EINTR;
+			break;
+		}
+
+		curr_task->flags |= TASK_INTERRUPTIBLE;
+		if (deadline)
+			set_timer_cycles(deadline);
+		else
+			block_current_task();
+
+		spinlock_irqsave_unlock(&bucket->lock);
+
+		reschedule();
+
+		curr_task->flags &= ~TASK_INTERRUPTIBLE;
+
+		bucket = lock_waiter_bucket(&waiter);
+
+		if (waiter.woken)
+			break;
+
+		if (deadline && (get_rdtsc() >= deadline)) {
+			ret = -ETIMEDOUT;
+			break;
+		}
+
+		// spurious wakeup or a signal => check it and wait again
+	}
+
+	if (!waiter.woken)
+		queue_remove(bucket, &waiter);
+
+	spinlock_irqsave_unlock(&bucket->lock);
+
+	return ret;
+}
+
+static int futex_wake(int* uaddr, int nr)
+{
+	futex_bucket_t* bucket = futex_bucket(uaddr);
+	int ret;
+
+	spinlock_irqsave_lock(&bucket->lock);
+	ret = futex_wake_locked(bucket, uaddr, nr);
+	spinlock_irqsave_unlock(&bucket->lock);
+
+	return ret;
+}
+
+static int futex_requeue(int* uaddr, int nr_wake, int nr_requeue, int* uaddr2, int cmp, int val3)
+{
+	futex_bucket_t* b1 = futex_bucket(uaddr);
+	futex_bucket_t* b2 = futex_bucket(uaddr2);
+	futex_waiter_t* waiter;
+	futex_waiter_t* next;
+	int ret, requeued = 0;
+
+	lock_buckets(b1, b2);
+
+	if (cmp && (*((volatile int*) uaddr) != val3)) {
+		ret = -EAGAIN;
+		goto out;
+	}
+
+	ret = futex_wake_locked(b1, uaddr, nr_wake);
+
+	// move the remaining waiters to the second futex
+	waiter = b1->first;
+	while (waiter && (requeued < nr_requeue)) {
+		next = waiter->next;
+
+		if (waiter->uaddr == uaddr) {
+			queue_remove(b1, waiter);
+			waiter->uaddr = uaddr2;
+			queue_push(b2, waiter);
+			requeued++;
+		}
+
+		waiter = next;
+	}
+
+	ret += requeued;
+
+out:
+	unlock_buckets(b1, b2);
+
+	return ret;
+}
+
+static inline int sign_extend12(int val)
+{
+	return (val & 0x800) ? (val | ~0xfff) : val;
+}
+
+static int futex_atomic_op(int op, int oparg, int* uaddr, int* oldval)
+{
+	switch(op) {
+	case FUTEX_OP_SET:
+		*oldval = __atomic_exchange_n(uaddr, oparg, __ATOMIC_SEQ_CST);
+		break;
+	case FUTEX_OP_ADD:
+		*oldval = __atomic_fetch_add(uaddr, oparg, __ATOMIC_SEQ_CST);
+		break;
+	case FUTEX_OP_OR:
+		*oldval = __atomic_fetch_or(uaddr, oparg, __ATOMIC_SEQ_CST);
+		break;
+	case FUTEX_OP_ANDN:
+		*oldval = __atomic_fetch_and(uaddr, ~oparg, __ATOMIC_SEQ_CST);
+		break;
+	case FUTEX_OP_XOR:
+		*oldval = __atomic_fetch_xor(uaddr, oparg, __ATOMIC_SEQ_CST);
+		break;
+	default:
+		return -ENOSYS;
+	}
+
+	return 0;
+}
+
+static int futex_wake_op(int* uaddr, int nr_wake, int nr_wake2, int* uaddr2, int val3)
+{
+	futex_bucket_t* b1 = futex_bucket(uaddr);
+	futex_bucket_t* b2 = futex_bucket(uaddr2);
+	int op = (val3 >> 28) & 0xf;
+	const int cmp = (val3 >> 24) & 0xf;
+	int oparg = sign_extend12((val3 >> 12) & 0xfff);
+	const int cmparg = sign_extend12(val3 & 0xfff);
+	int oldval, cond, ret;
+
+	if (op & FUTEX_OP_OPARG_SHIFT) {
+		if (BUILTIN_EXPECT((oparg < 0) || (oparg > 31), 0))
+			return -EINVAL;
+		oparg = 1 << oparg;
+		op &= ~FUTEX_OP_OPARG_SHIFT;
+	}
+
+	switch(cmp) {
+	case FUTEX_OP_CMP_EQ:
+	case FUTEX_OP_CMP_NE:
+	case FUTEX_OP_CMP_LT:
+	case FUTEX_OP_CMP_LE:
+	case FUTEX_OP_CMP_GT:
+	case FUTEX_OP_CMP_GE:
+		break;
+	default:
+		return -ENOSYS;
+	}
+
+	lock_buckets(b1, b2);
+
+	ret = futex_atomic_op(op, oparg, uaddr2, &oldval);
+	if (BUILTIN_EXPECT(ret, 0))
+		goto out;
+
+	ret = futex_wake_locked(b1, uaddr, nr_wake);
+
+	switch(cmp) {
+	case FUTEX_OP_CMP_EQ:
+		cond = (oldval == cmparg);
+		break;
+	case FUTEX_OP_CMP_NE:
+		cond = (oldval != cmparg);
+		break;
+	case FUTEX_OP_CMP_LT:
+		cond = (oldval < cmparg);
+		break;
+	case FUTEX_OP_CMP_LE:
+		cond = (oldval <= cmparg);
+		break;
+	case FUTEX_OP_CMP_GT:
+		cond = (oldval > cmparg);
+		break;
+	default:
+		cond = (oldval >= cmparg);
+		break;
+	}
+
+	if (cond)
+		ret += futex_wake_locked(b2, uaddr2, nr_wake2);
+
+out:
+	unlock_buckets(b1, b2);
+
+	return ret;
+}
+
+int sys_futex(int* uaddr, int op, int val, const struct timespec* timeout, int* uaddr2, int val3)
+{
+	// FUTEX_REQUEUE and FUTEX_WAKE_OP pass a number instead of the timeout
+	const int val2 = (int) (size_t) timeout;
+
+	if (BUILTIN_EXPECT(!uaddr, 0))
+		return -EFAULT;
+	if (BUILTIN_EXPECT((size_t) uaddr & (sizeof(int)-1), 0))
+		return -EINVAL;
+
+	switch(op & FUTEX_CMD_MASK) {
+	case FUTEX_WAIT:
+		return futex_wait(uaddr, val, timeout);
+	case FUTEX_WAKE:
+		return futex_wake(uaddr, val);
+	case FUTEX_REQUEUE:
+	case FUTEX_CMP_REQUEUE:
+	case FUTEX_WAKE_OP:
+		if (BUILTIN_EXPECT(!uaddr2, 0))
+			return -EFAULT;
+		if (BUILTIN_EXPECT((size_t) uaddr2 & (sizeof(int)-1), 0))
+			return -EINVAL;
+
+		if ((op & FUTEX_CMD_MASK) == FUTEX_WAKE_OP)
+			return futex_wake_op(uaddr, val, val2, uaddr2, val3);
+
+		return futex_requeue(uaddr, val, val2, uaddr2,
+			(op & FUTEX_CMD_MASK) == FUTEX_CMP_REQUEUE, val3);
+	default:
+		return -ENOSYS;
+	}
+}
@@ -0,0 +1,446 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/initramfs.c
+ * @brief Read-only file system, which serves the files of the initrd
+ *
+ * The initrd is a cpio archive in the "newc" format. The files aren't
+ * copied, the nodes refer directly to the content of the archive.
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/stdlib.h>
+#include <hermit/string.h>
+#include <hermit/errno.h>
+#include <hermit/logging.h>
+#include <hermit/vfs.h>
+#include <sys/poll.h>
+#include <sys/stat.h>
+#include <sys/dirent.h>
+
+#define CPIO_MAGIC		"070701"
+#define CPIO_MAGIC_CRC		"070702"
+#define CPIO_TRAILER		"TRAILER!!!"
+#define CPIO_ALIGN(x)		(((x) + 3) & ~3UL)
+
+/* header of the newc format, all numbers are hexadecimal strings */
+typedef struct cpio_header {
+	char magic[6];
+	char ino[8];
+	char mode[8];
+	char uid[8];
+	char gid[8];
+	char nlink[8];
+	char mtime[8];
+	char filesize[8];
+	char devmajor[8];
+	char devminor[8];
+	char rdevmajor[8];
+	char rdevminor[8];
+	char namesize[8];
+	char check[8];
+} __attribute__ ((packed)) cpio_header_t;
+
+typedef struct initramfs_node {
+	/// name of the directory entry (not null-terminated)
+	const char* name;
+	/// length of the name
+	size_t len;
+	/// file type and permissions
+	uint32_t mode;
+	/// inode number
+	uint32_t ino;
+	/// time of last modification
+	uint32_t mtime;
+	/// file size in bytes
+	size_t size;
+	/// file content, located in the archive
+	const char* data;
+	/// parent directory
+	struct initramfs_node* parent;
+	/// first entry of a directory
+	struct initramfs_node* children;
+	/// next entry in the parent directory
+	struct initramfs_node* next;
+} initramfs_node_t;
+
+static uint32_t cpio_number(const char* str)
+{
+	uint32_t ret = 0;
+
+	for(int i=0; i<8; i++) {
+		char c = str[i];
+
+		ret <<= 4;
+		if ((c >= '0') && (c <= '9'))
+			ret |= c - '0';
+		else if ((c >= 'a') && (c <= 'f'))
+			ret |= c - 'a' + 10;
+		else if ((c >= 'A') && (c <= 'F'))
+			ret |= c - 'A' + 10;
+	}
+
+	return ret;
+}
+
+static initramfs_node_t* initramfs_find(initramfs_node_t* dir, const char* name, size_t len)
+{
+	initramfs_node_t* node;
+
+	if ((len == 1) && (name[0] == '.'))
+		return dir;
+	if ((len == 2) && (name[0] == '.') && (name[1] == '.'))
+		return dir->parent ? dir->parent : dir;
+
+	for(node=dir->children; node; node=node->next) {
+		if ((node->len == len) && !strncmp(node->name, name, len))
+			return node;
+	}
+
+	return NULL;
+}
+
+/*
+ * Resolve a path. If create is set, missing directories are added
+ * to the tree. Archives don't need to contain all parent directories.
+ */
+static initramfs_node_t* initramfs_walk(initramfs_node_t* root, const char* path, int create)
+{
+	initramfs_node_t* curr = root;
+	initramfs_node_t* node;
+	size_t len;
+
+	while(*path) {
+		while(*path == '/')
+			path++;
+		if (!*path)
+			break;
+
+		for(len=0; path[len] && (path[len] != '/'); len++)
+			;
+
+		node = initramfs_find(curr, path, len);
+		if (!node) {
+			if (!create)
+				return NULL;
+
+			node = kmalloc(sizeof(initramfs_node_t));
+			if (BUILTIN_EXPECT(!node, 0))
+				return NULL;
+
+			memset(node, 0x00, sizeof(initramfs_node_t));
+			node->name = path;
+			node->len = len;
+			node->mode = S_IFDIR|0555;
+			node->parent = curr;
+			node->next = curr->children;
+			curr->children = node;
+		} else if (path[len] && !S_ISDIR(node->mode)) {
+			return NULL;
+		}
+
+		curr = node;
+		path += len;
+	}
+
+	return curr;
+}
+
+static void initramfs_fill_stat(initramfs_node_t* node, struct stat* st)
+{
+	memset(st, 0x00, sizeof(struct stat));
+	st->st_ino = node->ino;
+	st->st_mode = node->mode;
+	st->st_nlink = 1;
+	st->st_size = node->size;
+	st->st_atime = st->st_mtime = st->st_ctime = node->mtime;
+	st->st_blksize = 512;
+	st->st_blocks = (node->size + 511) / 512;
+}
+
+static ssize_t initramfs_read(file_t* file, char* buf, size_t len)
+{
+	initramfs_node_t* node = (initramfs_node_t*) file->priv;
+	ssize_t ret = 0;
+
+	if (BUILTIN_EXPECT(S_ISDIR(node->mode), 0))
+		return -EISDIR;
+
+	if (file->offset < node->size) {
+		ret = node->size - file->offset;
+		if (ret > len)
+			ret = len;
+
+		memcpy(buf, node->data + file->offset, ret);
+		file->offset += ret;
+	}
+
+	return ret;
+}
+
+static ssize_t initramfs_write(file_t* file, const char* buf, size_t len)
+{
+	return -EBADF;
+}
+
+static off_t initramfs_lseek(file_t* file, off_t offset, int whence)
+{
+	initramfs_node_t* node = (initramfs_node_t*) file->priv;
+	off_t ret;
+
+	switch(whence) {
+	case SEEK_SET:
+		ret = offset;
+		break;
+	case SEEK_CUR:
+		ret = file->offset + offset;
+		break;
+	case SEEK_END:
+		ret = node->size + offset;
+		break;
+	default:
+		return -EINVAL;
+	}
+
+	if (ret < 0)
+		return -EINVAL;
+
+	file->offset = ret;
+
+	return ret;
+}
+
+static int initramfs_fstat(file_t* file, struct stat* st)
+{
+	initramfs_fill_stat((initramfs_node_t*) file->priv, st);
+
+	return 0;
+}
+
+static int initramfs_poll(file_t* file, int events)
+{
+	return events & (POLLIN|POLLRDNORM);
+}
+
+/* The file offset is the index of the next entry, "." and ".." come first */
+static int initramfs_readdir(file_t* file, struct dirent* entry)
+{
+	initramfs_node_t* node = (initramfs_node_t*) file->priv;
+	initramfs_node_t* child;
+
+	if (BUILTIN_EXPECT(!S_ISDIR(node->mode), 0))
+		return -ENOTDIR;
+
+	memset(entry, 0x00, sizeof(struct dirent));
+	if (file->offset == 0) {
+		entry->d_ino = node->ino;
+		entry->d_type = DT_DIR;
+		strcpy(entry->d_name, ".");
+	} else if (file->offset == 1) {
+		entry->d_ino = node->parent ? node->parent->ino : node->ino;
+		entry->d_type = DT_DIR;
+		strcpy(entry->d_name, "..");
+	} else {
+		child = node->children;
+		for(off_t i=2; child && (i<file->offset); i++)
+			child = child->next;
+
+		if (!child)
+			return 0;
+
+		entry->d_ino = child->ino;
+		entry->d_type = S_ISDIR(child->mode) ? DT_DIR : DT_REG;
+		memcpy(entry->d_name, child->name, (child->len < NAME_MAX) ? child->len : NAME_MAX);
+	}
+
+	file->offset++;
+
+	return 1;
+}
+
+static int initramfs_close(file_t* file)
+{
+	return 0;
+}
+
+static const file_ops_t initramfs_file_ops = {
+	.read = initramfs_read,
+	.write = initramfs_write,
+	.lseek = initramfs_lseek,
+	.fstat = initramfs_fstat,
+	.poll = initramfs_poll,
+	.readdir = initramfs_readdir,
+	.close = initramfs_close,
+};
+
+static int initramfs_open(void* fs, const char* path, int flags, int mode, file_t* file)
+{
+	initramfs_node_t* node = initramfs_walk((initramfs_node_t*) fs, path, 0);
+
+	if (!node)
+		return (flags & O_CREAT) ? -EROFS : -ENOENT;
+
+	if ((flags & O_CREAT) && (flags & O_EXCL))
+		return -EEXIST;
+
+	if (((flags & O_ACCMODE) != O_RDONLY) || (flags & O_TRUNC))
+		return S_ISDIR(node->mode) ? -EISDIR : -EROFS;
+
+	file->ops = &initramfs_file_ops;
+	file->priv = node;
+	file->offset = 0;
+
+	return 0;
+}
+
+static int initramfs_stat(void* fs, const char* path, struct stat* st, int follow)
+{
+	initramfs_node_t* node = initramfs_walk((initramfs_node_t*) fs, path, 0);
+
+	if (!node)
+		return -ENOENT;
+
+	initramfs_fill_stat(node, st);
+
+	return 0;
+}
+
+static int initramfs_mkdir(void* fs, const char* path, int mode)
+{
+	return -EROFS;
+}
+
+static int initramfs_remove(void* fs, const char* path)
+{
+	return initramfs_walk((initramfs_node_t*) fs, path, 0) ? -EROFS : -ENOENT;
+}
+
+static int initramfs_rename(void* fs, const char* oldpath, const char* newpath)
+{
+	return initramfs_remove(fs, oldpath);
+}
+
+static int initramfs_opendir(void* fs, const char* path, file_t* file)
+{
+	initramfs_node_t* node = initramfs_walk((initramfs_node_t*) fs, path, 0);
+
+	if (!node)
+		return -ENOENT;
+
+	if (!S_ISDIR(node->mode))
+		return -ENOTDIR;
+
+	file->ops = &initramfs_file_ops;
+	file->priv = node;
+	file->offset = 0;
+
+	return 0;
+}
+
+const fs_ops_t initramfs_ops = {
+	.open = initramfs_open,
+	.stat = initramfs_stat,
+	.mkdir = initramfs_mkdir,
+	.rmdir = initramfs_remove,
+	.unlink = initramfs_remove,
+	.rename = initramfs_rename,
+	.opendir = initramfs_opendir,
+};
+
+void* initramfs_create(void)
+{
+	initramfs_node_t* root;
+	initramfs_node_t* node;
+	const char* archive;
+	size_t size, pos = 0;
+	uint32_t files = 0;
+
+	archive = get_initrd(&size);
+	if (!archive)
+		return NULL;
+
+	root = kmalloc(sizeof(initramfs_node_t));
+	if (BUILTIN_EXPECT(!root, 0))
+		return NULL;
+
+	memset(root, 0x00, sizeof(initramfs_node_t));
+	root->mode = S_IFDIR|0555;
+
+	while(pos + sizeof(cpio_header_t) <= size) {
+		const cpio_header_t* hdr = (const cpio_header_t*) (archive + pos);
+		const char* name = archive + pos + sizeof(cpio_header_t);
+		uint32_t namesize, filesize, mode;
+
+		if (strncmp(hdr->magic, CPIO_MAGIC, 6) && strncmp(hdr->magic, CPIO_MAGIC_CRC, 6)) {
+			LOG_ERROR("initramfs: invalid cpio header at offset 0x%zx\n", pos);
+			break;
+		}
+
+		namesize = cpio_number(hdr->namesize);
+		filesize = cpio_number(hdr->filesize);
+		mode = cpio_number(hdr->mode);
+
+		pos = CPIO_ALIGN(pos + sizeof(cpio_header_t) + namesize);
+		if (BUILTIN_EXPECT(pos + filesize > size, 0)) {
+			LOG_ERROR("initramfs: archive is truncated\n");
+			break;
+		}
+
+		if (!strncmp(name, CPIO_TRAILER, sizeof(CPIO_TRAILER)))
+			break;
+
+		if (!S_ISREG(mode) && !S_ISDIR(mode)) {
+			LOG_DEBUG("initramfs: ignore %s (mode 0x%x)\n", name, mode);
+			goto next;
+		}
+
+		node = initramfs_walk(root, name, 1);
+		if (BUILTIN_EXPECT(!node, 0)) {
+			LOG_ERROR("initramfs: unable to add %s\n", name);
+			goto next;
+		}
+
+		node->mode = mode;
+		node->ino = cpio_number(hdr->ino);
+		node->mtime = cpio_number(hdr->mtime);
+		if (S_ISREG(mode)) {
+			node->size = filesize;
+			node->data = archive + pos;
+			files++;
+		}
+
+next:
+		pos = CPIO_ALIGN(pos + filesize);
+	}
+
+	LOG_INFO("initramfs: found %u files in the initrd\n", files);
+
+	return root;
+}
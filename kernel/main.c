@@ -35,6 +35,9 @@
 #include <hermit/syscall.h>
 #include <hermit/memory.h>
 #include <hermit/logging.h>
+#include <hermit/vfs.h>
+#include <hermit/proxy.h>
+#include <hermit/uhyve_ring.h>
 #include <asm/irq.h>
 #include <asm/page.h>
 #include <asm/uart.h>
@@ -63,9 +66,6 @@
 #include <net/vioif.h>
 #include <net/uhyve-net.h>
 
-#define HERMIT_PORT	0x494E
-#define HERMIT_MAGIC	0x7E317
-
 typedef struct {
 	int argc;
 	int argsz[MAX_ARGC_ENVC];
@@ -132,6 +132,9 @@ static int hermit_init(void)
 	multitasking_init();
 	memory_init();
 	signal_init();
+	vfs_init();
+	uhyve_ring_init();
+	time_init();
 
 	return 0;
 }
@@ -353,6 +356,13 @@ static int initd(void* arg)
 	vma_free(curr_task->heap->start, curr_task->heap->start+PAGE_SIZE);
 	vma_add(curr_task->heap->start, curr_task->heap->start+PAGE_SIZE, VMA_HEAP|VMA_USER);
 
+	// setup the file descriptor table, fds 0-2 are the standard streams
+	err = fd_table_init();
+	if (BUILTIN_EXPECT(err, 0)) {
+		LOG_ERROR("initd: unable to create the file descriptor table!\n");
+		return err;
+	}
+
 #ifndef __aarch64__
 	// initialize network
 	err = init_netifs();
@@ -410,6 +420,14 @@ static int initd(void* arg)
 	if ((err != 0) || !is_proxy())
 	{
 		char* dummy[] = {"app_name", NULL};
+		void* root;
+
+		// without a host, the initrd or a RAM-backed file system is used as root
+		root = initramfs_create();
+		if (root)
+			vfs_mount("/", &initramfs_ops, root);
+		else if ((root = tmpfs_create()) != NULL)
+			vfs_mount("/", &tmpfs_ops, root);
 
 		LOG_INFO("Boot time: %d ms\n", (get_clock_tick() * 1000) / TIMER_FREQ);
 		// call user code
@@ -471,7 +489,7 @@ static int initd(void* arg)
 
 	magic = 0;
 	lwip_read(c, &magic, sizeof(magic));
-	if (magic != HERMIT_MAGIC)
+	if (proxy_handshake(c, magic))
 	{
 		LOG_ERROR("Invalid magic number %d\n", magic);
 		lwip_close(c);
@@ -536,7 +554,7 @@ static int initd(void* arg)
 	}
 
 	// call user code
-	libc_sd = c;
+	proxy_start(c);
 	libc_start(argc, argv, environ);
 
 out:
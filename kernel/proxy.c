@@ -0,0 +1,472 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/proxy.c
+ * @brief Forwarding of system calls to the proxy on the host
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/stdlib.h>
+#include <hermit/string.h>
+#include <hermit/errno.h>
+#include <hermit/tasks.h>
+#include <hermit/spinlock.h>
+#include <hermit/logging.h>
+#include <hermit/proxy.h>
+
+#include <lwip/sockets.h>
+
+extern volatile int libc_sd;
+
+/// Serializes the calls of the legacy protocol
+static spinlock_irqsave_t proxy_lock = SPINLOCK_IRQSAVE_INIT;
+/// Serializes the messages of the multiplexed protocol
+static spinlock_irqsave_t send_lock = SPINLOCK_IRQSAVE_INIT;
+/// Protects the list of pending calls
+static spinlock_irqsave_t pending_lock = SPINLOCK_IRQSAVE_INIT;
+/// Serializes reads and writes of the socket, LwIP doesn't have to support full duplex
+static spinlock_irqsave_t socket_lock = SPINLOCK_IRQSAVE_INIT;
+
+static proxy_call_t* pending = NULL;
+static uint64_t next_id = 1;
+/// Proxy uses the multiplexed protocol
+static uint8_t proxy_mux = 0;
+/// Demultiplexer receives the replies
+static volatile uint8_t demux_running = 0;
+
+static int socket_send(int fd, const void* buf, size_t len)
+{
+	int ret, sz = 0;
+
+	spinlock_irqsave_lock(&socket_lock);
+
+	do {
+		ret = lwip_write(fd, (char*)buf + sz, len-sz);
+		if (ret >= 0)
+			sz += ret;
+		else
+			goto out;
+	} while(sz < len);
+
+	ret = len;
+
+out:
+	spinlock_irqsave_unlock(&socket_lock);
+
+	return ret;
+}
+
+static int socket_recv(int fd, void* buf, size_t len)
+{
+	int ret, sz = 0;
+
+	spinlock_irqsave_lock(&socket_lock);
+
+	do {
+		ret = lwip_read(fd, (char*)buf + sz, len-sz);
+		if (ret > 0)
+			sz += ret;
+		else if (ret == 0) {
+			ret = -EIO; // connection is closed
+			goto out;
+		} else
+			goto out;
+	} while(sz < len);
+
+	ret = len;
+
+out:
+	spinlock_irqsave_unlock(&socket_lock);
+
+	return ret;
+}
+
+/* Block without holding the socket, until the proxy sends the next reply */
+static int socket_wait(int fd)
+{
+	fd_set rset;
+	int ret;
+
+	do {
+		FD_ZERO(&rset);
+		FD_SET(fd, &rset);
+		ret = lwip_select(fd+1, &rset, NULL, NULL, NULL);
+	} while(ret == 0);
+
+	return (ret < 0) ? ret : 0;
+}
+
+int proxy_handshake(int sd, int magic)
+{
+	uint32_t version;
+
+	if (magic == HERMIT_MAGIC) {
+		proxy_mux = 0;
+		return 0;
+	}
+
+	if (magic != HERMIT_MAGIC_MUX)
+		return -EINVAL;
+
+	// the proxy sends its version, we reply with the version in use
+	if (socket_recv(sd, &version, sizeof(version)) < 0)
+		return -EIO;
+	if (version > PROXY_VERSION)
+		version = PROXY_VERSION;
+	if (socket_send(sd, &version, sizeof(version)) < 0)
+		return -EIO;
+
+	proxy_mux = (version >= PROXY_VERSION);
+
+	LOG_INFO("Proxy uses protocol version %u\n", version);
+
+	return 0;
+}
+
+/* Wake up all waiting tasks, the connection is closed */
+static void proxy_abort(void)
+{
+	proxy_call_t* call;
+
+	spinlock_irqsave_lock(&pending_lock);
+
+	demux_running = 0;
+	while(pending) {
+		call = pending;
+		pending = call->next;
+
+		call->err = -EIO;
+		call->done = 1;
+		if (call->waiter)
+			wakeup_task(call->waiter);
+	}
+
+	spinlock_irqsave_unlock(&pending_lock);
+}
+
+static int proxy_demux(void* arg)
+{
+	int sd = (int) (size_t) arg;
+	proxy_header_t header;
+	proxy_call_t* call;
+	proxy_call_t** prev;
+	char* buf;
+
+	LOG_INFO("Proxy demultiplexer is running\n");
+
+	// a blocking read would exclude the senders from the socket until the next reply
+	while((socket_wait(sd) >= 0) && (socket_recv(sd, &header, sizeof(header)) >= 0))
+	{
+		buf = NULL;
+		if (header.len) {
+			buf = kmalloc(header.len);
+			if (BUILTIN_EXPECT(!buf, 0)) {
+				LOG_ERROR("Proxy: unable to allocate a reply of %zd bytes\n", header.len);
+				break;
+			}
+
+			if (socket_recv(sd, buf, header.len) < 0) {
+				kfree(buf);
+				break;
+			}
+		}
+
+		spinlock_irqsave_lock(&pending_lock);
+
+		for(prev=&pending, call=*prev; call; prev=&call->next, call=*prev) {
+			if (call->id == header.id) {
+				*prev = call->next;
+				break;
+			}
+		}
+
+		if (call) {
+			call->reply = buf;
+			call->reply_len = header.len;
+			call->done = 1;
+			if (call->waiter)
+				wakeup_task(call->waiter);
+		}
+
+		spinlock_irqsave_unlock(&pending_lock);
+
+		if (BUILTIN_EXPECT(!call, 0)) {
+			LOG_WARNING("Proxy: drop reply with unknown id %zd\n", header.id);
+			kfree(buf);
+		}
+	}
+
+	LOG_INFO("Proxy connection is closed\n");
+
+	proxy_abort();
+
+	return 0;
+}
+
+int proxy_start(int sd)
+{
+	int ret;
+
+	libc_sd = sd;
+
+	if (!proxy_mux)
+		return 0;
+
+	demux_running = 1;
+	ret = create_kernel_task(NULL, proxy_demux, (void*) (size_t) sd, HIGH_PRIO);
+	if (BUILTIN_EXPECT(ret, 0)) {
+		LOG_ERROR("Unable to create the proxy demultiplexer: %d\n", ret);
+		demux_running = 0;
+	}
+
+	return ret;
+}
+
+void proxy_shutdown(void)
+{
+	int s;
+
+	spinlock_irqsave_lock(&proxy_lock);
+	s = libc_sd;
+	libc_sd = -1;
+	spinlock_irqsave_unlock(&proxy_lock);
+
+	if (s >= 0) {
+		// switch to LwIP thread
+		reschedule();
+
+		lwip_close(s);
+	}
+}
+
+/* Remove a call, whose reply isn't received, from the list of pending calls */
+static void proxy_cancel(proxy_call_t* call)
+{
+	proxy_call_t** prev;
+	proxy_call_t* curr;
+
+	spinlock_irqsave_lock(&pending_lock);
+
+	// otherwise, the demultiplexer already removed the call
+	if (!call->done) {
+		for(prev=&pending, curr=*prev; curr; prev=&curr->next, curr=*prev) {
+			if (curr == call) {
+				*prev = call->next;
+				break;
+			}
+		}
+	}
+
+	spinlock_irqsave_unlock(&pending_lock);
+}
+
+/* Release the connection after the request is sent or has failed */
+static void proxy_sent(proxy_call_t* call, int err)
+{
+	call->sending = 0;
+	call->sent = !err;
+	call->err = err;
+	spinlock_irqsave_unlock(&send_lock);
+}
+
+int proxy_begin(proxy_call_t* call, size_t len, int reply)
+{
+	proxy_header_t header;
+	int ret;
+
+	memset(call, 0x00, sizeof(proxy_call_t));
+
+	if (!proxy_mux) {
+		spinlock_irqsave_lock(&proxy_lock);
+		if (libc_sd < 0) {
+			spinlock_irqsave_unlock(&proxy_lock);
+			return -ENOSYS;
+		}
+
+		return 0;
+	}
+
+	if (libc_sd < 0)
+		return -ENOSYS;
+
+	if (reply) {
+		spinlock_irqsave_lock(&pending_lock);
+
+		if (BUILTIN_EXPECT(!demux_running, 0)) {
+			spinlock_irqsave_unlock(&pending_lock);
+			return -EIO;
+		}
+
+		call->id = next_id++;
+		call->next = pending;
+		pending = call;
+
+		spinlock_irqsave_unlock(&pending_lock);
+	}
+
+	header.id = call->id;
+	header.len = len;
+
+	// the request follows its header without messages of other tasks in between
+	spinlock_irqsave_lock(&send_lock);
+	call->sending = 1;
+	call->req_len = len;
+
+	ret = socket_send(libc_sd, &header, sizeof(header));
+	if (BUILTIN_EXPECT(ret < 0, 0)) {
+		proxy_sent(call, ret);
+		if (reply)
+			proxy_cancel(call);
+		return ret;
+	}
+
+	if (!len)
+		proxy_sent(call, 0);
+
+	return 0;
+}
+
+int proxy_send(proxy_call_t* call, const void* buf, size_t len)
+{
+	int ret;
+
+	if (!proxy_mux)
+		return socket_send(libc_sd, buf, len);
+
+	if (BUILTIN_EXPECT(!call->sending, 0))
+		return call->err ? call->err : -EINVAL;
+	if (BUILTIN_EXPECT(len > call->req_len, 0))
+		return -EINVAL;
+
+	ret = socket_send(libc_sd, buf, len);
+	if (BUILTIN_EXPECT(ret < 0, 0)) {
+		proxy_sent(call, ret);
+		return ret;
+	}
+
+	call->req_len -= len;
+	if (!call->req_len)
+		proxy_sent(call, 0);
+
+	return len;
+}
+
+/* Block the current task until the reply is received */
+static void proxy_wait(proxy_call_t* call)
+{
+	spinlock_irqsave_lock(&pending_lock);
+
+	while(!call->done) {
+		call->waiter = per_core(current_task)->id;
+		block_current_task();
+		spinlock_irqsave_unlock(&pending_lock);
+		reschedule();
+		spinlock_irqsave_lock(&pending_lock);
+	}
+
+	spinlock_irqsave_unlock(&pending_lock);
+}
+
+int proxy_recv(proxy_call_t* call, void* buf, size_t len)
+{
+	if (!proxy_mux)
+		return socket_recv(libc_sd, buf, len);
+
+	if (BUILTIN_EXPECT(call->err, 0))
+		return call->err;
+	if (BUILTIN_EXPECT(!call->sent || !call->id, 0))
+		return -EINVAL;
+
+	proxy_wait(call);
+
+	if (BUILTIN_EXPECT(call->err, 0))
+		return call->err;
+	if (BUILTIN_EXPECT(call->reply_len - call->reply_pos < len, 0))
+		return -EIO;
+
+	memcpy(buf, call->reply + call->reply_pos, len);
+	call->reply_pos += len;
+
+	return len;
+}
+
+int proxy_skip(proxy_call_t* call, size_t len)
+{
+	char buf[64];
+	size_t n;
+	int ret;
+
+	if (proxy_mux) {
+		ret = proxy_recv(call, NULL, 0);
+		if (ret < 0)
+			return ret;
+
+		if (len > call->reply_len - call->reply_pos)
+			len = call->reply_len - call->reply_pos;
+		call->reply_pos += len;
+
+		return 0;
+	}
+
+	while(len) {
+		n = (len < sizeof(buf)) ? len : sizeof(buf);
+
+		ret = socket_recv(libc_sd, buf, n);
+		if (ret < 0)
+			return ret;
+
+		len -= n;
+	}
+
+	return 0;
+}
+
+void proxy_end(proxy_call_t* call)
+{
+	static const char zeros[64] = {[0 ... 63] = 0};
+	size_t n;
+
+	if (!proxy_mux) {
+		spinlock_irqsave_unlock(&proxy_lock);
+		return;
+	}
+
+	// the header announced more bytes => keep the connection in sync
+	while(call->sending) {
+		n = (call->req_len < sizeof(zeros)) ? call->req_len : sizeof(zeros);
+		proxy_send(call, zeros, n);
+	}
+
+	if (call->id)
+		proxy_cancel(call);
+
+	if (call->reply)
+		kfree(call->reply);
+}
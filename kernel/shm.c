@@ -0,0 +1,497 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/shm.c
+ * @brief SysV shared memory
+ *
+ * The segments are allocated from get_pages() or from the high bandwidth
+ * memory. In multi-kernel mode, the table of segments is part of the RCCE
+ * internals. Consequently, all isles share the segments and the table is
+ * protected by the islelock of RCCE. The memory of a segment belongs to the
+ * isle, which created it. If another isle destroys the segment, the slot
+ * stays occupied until the owner releases the memory.
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/string.h>
+#include <hermit/errno.h>
+#include <hermit/spinlock.h>
+#include <hermit/memory.h>
+#include <hermit/vma.h>
+#include <hermit/rcce.h>
+#include <hermit/logging.h>
+#include <hermit/syscall.h>
+#include <asm/page.h>
+#include <sys/shm.h>
+
+/// Identifier of a segment
+#define SHM_ID(slot, seq)	((((seq) & 0xFFFF) * MAX_SHM_SEGMENTS) + (slot))
+#define SHM_SLOT(id)		((id) % MAX_SHM_SEGMENTS)
+#define SHM_SEQ(id)		((id) / MAX_SHM_SEGMENTS)
+
+/** @brief Attachment of a segment to the address space of this isle */
+typedef struct shm_attach {
+	/// Start address of the attachment
+	size_t vaddr;
+	/// Number of mapped pages
+	size_t npages;
+	/// Slot of the segment
+	int slot;
+	/// Next attachment
+	struct shm_attach* next;
+} shm_attach_t;
+
+/// Table of segments, if RCCE isn't available
+static shm_segment_t local_segments[MAX_SHM_SEGMENTS];
+static spinlock_t local_lock = SPINLOCK_INIT;
+
+static shm_attach_t* attachments = NULL;
+static spinlock_t attach_lock = SPINLOCK_INIT;
+
+extern int32_t isle;
+
+static inline int shm_shared(void)
+{
+	return !is_single_kernel() && rcce_shm;
+}
+
+static inline shm_segment_t* shm_table(void)
+{
+	if (shm_shared())
+		return rcce_shm;
+
+	return local_segments;
+}
+
+static inline void shm_lock(void)
+{
+	if (shm_shared())
+		islelock_lock(rcce_lock);
+	else
+		spinlock_lock(&local_lock);
+}
+
+static inline void shm_unlock(void)
+{
+	if (shm_shared())
+		islelock_unlock(rcce_lock);
+	else
+		spinlock_unlock(&local_lock);
+}
+
+/* Returns the slot of a valid identifier or a negative error number */
+static int shm_lookup(shm_segment_t* table, int shmid)
+{
+	int slot;
+
+	if (BUILTIN_EXPECT(shmid < 0, 0))
+		return -EINVAL;
+
+	slot = SHM_SLOT(shmid);
+	if (!(table[slot].flags & SHM_SEG_USED) || ((table[slot].seq & 0xFFFF) != SHM_SEQ(shmid)))
+		return -EINVAL;
+
+	return slot;
+}
+
+/* Fill a new segment with zeros */
+static int shm_clear(size_t paddr, size_t npages)
+{
+	size_t vaddr, bits = PG_RW|PG_GLOBAL;
+
+	vaddr = vma_alloc(npages << PAGE_BITS, VMA_READ|VMA_WRITE|VMA_CACHEABLE);
+	if (BUILTIN_EXPECT(!vaddr, 0))
+		return -ENOMEM;
+
+	if (has_nx())
+		bits |= PG_XD;
+
+	if (BUILTIN_EXPECT(page_map(vaddr, paddr, npages, bits), 0)) {
+		vma_free(vaddr, vaddr + (npages << PAGE_BITS));
+		return -ENOMEM;
+	}
+
+	memset((void*) vaddr, 0x00, npages << PAGE_BITS);
+
+	page_unmap(vaddr, npages);
+	vma_free(vaddr, vaddr + (npages << PAGE_BITS));
+
+	return 0;
+}
+
+/* Return the pages of a segment to the allocator of this isle */
+static void shm_free(size_t paddr, size_t npages, uint32_t flags)
+{
+	if (flags & SHM_SEG_HBMEM)
+		hbmem_put_pages(paddr, npages);
+	else
+		put_pages(paddr, npages);
+}
+
+/* Release a slot, the caller holds the lock */
+static void shm_free_slot(shm_segment_t* seg)
+{
+	shm_free(seg->paddr, PAGE_CEIL(seg->size) >> PAGE_BITS, seg->flags);
+
+	seg->paddr = 0;
+	seg->size = 0;
+	seg->flags = 0;
+}
+
+/* Destroy a segment, the caller holds the lock */
+static void shm_destroy(shm_segment_t* seg)
+{
+	LOG_DEBUG("shm: destroy segment with key %ld at 0x%zx\n", seg->key, seg->paddr);
+
+	// the identifier and the key are invalid from now on
+	seg->key = IPC_PRIVATE;
+	seg->seq++;
+
+	// the memory belongs to the page allocator of another isle
+	if (shm_shared() && (seg->isle != isle)) {
+		seg->flags |= SHM_SEG_ORPHAN;
+		return;
+	}
+
+	shm_free_slot(seg);
+}
+
+/* Free the segments of this isle, which are destroyed by other isles */
+static void shm_reap(shm_segment_t* table)
+{
+	int i;
+
+	for(i=0; i<MAX_SHM_SEGMENTS; i++) {
+		if ((table[i].flags & SHM_SEG_ORPHAN) && (table[i].isle == isle))
+			shm_free_slot(table+i);
+	}
+}
+
+/* Drop one attach of a segment */
+static void shm_release(int slot)
+{
+	shm_segment_t* table = shm_table();
+
+	shm_lock();
+
+	shm_reap(table);
+
+	table[slot].nattch--;
+	if ((table[slot].nattch <= 0) && (table[slot].flags & SHM_SEG_REMOVED))
+		shm_destroy(table+slot);
+
+	shm_unlock();
+}
+
+/* Returns the identifier of an existing segment, -ENOENT or another negative error number */
+static int shm_find(shm_segment_t* table, key_t key, size_t size, int shmflg)
+{
+	int i;
+
+	if (key == IPC_PRIVATE)
+		return -ENOENT;
+
+	for(i=0; i<MAX_SHM_SEGMENTS; i++) {
+		if (((table[i].flags & (SHM_SEG_USED|SHM_SEG_REMOVED)) == SHM_SEG_USED)
+		    && (table[i].key == key))
+			break;
+	}
+
+	if (i >= MAX_SHM_SEGMENTS)
+		return -ENOENT;
+
+	if ((shmflg & IPC_CREAT) && (shmflg & IPC_EXCL))
+		return -EEXIST;
+	if (size > table[i].size)
+		return -EINVAL;
+
+	return SHM_ID(i, table[i].seq);
+}
+
+int sys_shmget(key_t key, size_t size, int shmflg)
+{
+	shm_segment_t* table = shm_table();
+	size_t npages = PAGE_CEIL(size) >> PAGE_BITS;
+	size_t paddr;
+	uint32_t flags;
+	int i, ret;
+
+	shm_lock();
+	ret = shm_find(table, key, size, shmflg);
+	shm_unlock();
+
+	if ((ret != -ENOENT) || ((key != IPC_PRIVATE) && !(shmflg & IPC_CREAT)))
+		return ret;
+
+	if (BUILTIN_EXPECT(!npages, 0))
+		return -EINVAL;
+
+	// allocate and clear the memory without holding the lock
+	if (is_hbmem_available()) {
+		paddr = hbmem_get_pages(npages);
+		flags = SHM_SEG_HBMEM;
+	} else {
+		paddr = get_pages(npages);
+		flags = 0;
+	}
+
+	if (BUILTIN_EXPECT(!paddr, 0))
+		return -ENOMEM;
+
+	if (BUILTIN_EXPECT(shm_clear(paddr, npages), 0)) {
+		ret = -ENOMEM;
+		goto out;
+	}
+
+	shm_lock();
+
+	shm_reap(table);
+
+	// another task could have created the segment in the meantime
+	ret = shm_find(table, key, size, shmflg);
+	if (ret != -ENOENT) {
+		shm_unlock();
+		goto out;
+	}
+
+	for(i=0; i<MAX_SHM_SEGMENTS; i++) {
+		if (!(table[i].flags & SHM_SEG_USED))
+			break;
+	}
+
+	if (i >= MAX_SHM_SEGMENTS) {
+		shm_unlock();
+		ret = -ENOSPC;
+		goto out;
+	}
+
+	table[i].key = key;
+	table[i].size = size;
+	table[i].paddr = paddr;
+	table[i].nattch = 0;
+	table[i].isle = isle;
+	table[i].flags = flags|SHM_SEG_USED;
+	ret = SHM_ID(i, table[i].seq);
+
+	shm_unlock();
+
+	LOG_INFO("shm: create segment with key %ld at 0x%zx (size 0x%zx), id %d\n", key, paddr, size, ret);
+
+	return ret;
+
+out:
+	shm_free(paddr, npages, flags);
+
+	return ret;
+}
+
+void* sys_shmat(int shmid, const void* shmaddr, int shmflg)
+{
+	shm_segment_t* table = shm_table();
+	shm_attach_t* attach;
+	size_t vaddr = (size_t) shmaddr;
+	size_t paddr = 0, npages = 0, bits = PG_USER;
+	uint32_t flags = VMA_READ|VMA_USER|VMA_CACHEABLE;
+	int slot, ret;
+
+	if (shmflg & SHM_RND)
+		vaddr &= ~(SHMLBA-1);
+	if (BUILTIN_EXPECT(vaddr & ~PAGE_MASK, 0))
+		return (void*) -EINVAL;
+
+	attach = kmalloc(sizeof(shm_attach_t));
+	if (BUILTIN_EXPECT(!attach, 0))
+		return (void*) -ENOMEM;
+
+	// reserve the segment, it can't be destroyed while it is attached
+	shm_lock();
+	slot = shm_lookup(table, shmid);
+	if (slot >= 0) {
+		table[slot].nattch++;
+		paddr = table[slot].paddr;
+		npages = PAGE_CEIL(table[slot].size) >> PAGE_BITS;
+	}
+	shm_unlock();
+
+	if (BUILTIN_EXPECT(slot < 0, 0)) {
+		kfree(attach);
+		return (void*) (ssize_t) slot;
+	}
+
+	if (!(shmflg & SHM_RDONLY)) {
+		flags |= VMA_WRITE;
+		bits |= PG_RW;
+	}
+	if (has_nx())
+		bits |= PG_XD;
+
+	if (vaddr) {
+		ret = vma_add(vaddr, vaddr + (npages << PAGE_BITS), flags);
+	} else {
+		vaddr = vma_alloc(npages << PAGE_BITS, flags);
+		ret = vaddr ? 0 : -ENOMEM;
+	}
+	if (BUILTIN_EXPECT(ret, 0)) {
+		ret = -EINVAL;
+		goto out;
+	}
+
+	ret = page_map(vaddr, paddr, npages, bits);
+	if (BUILTIN_EXPECT(ret, 0)) {
+		vma_free(vaddr, vaddr + (npages << PAGE_BITS));
+		ret = -ENOMEM;
+		goto out;
+	}
+
+	attach->vaddr = vaddr;
+	attach->npages = npages;
+	attach->slot = slot;
+
+	spinlock_lock(&attach_lock);
+	attach->next = attachments;
+	attachments = attach;
+	spinlock_unlock(&attach_lock);
+
+	return (void*) vaddr;
+
+out:
+	kfree(attach);
+	shm_release(slot);
+
+	return (void*) (ssize_t) ret;
+}
+
+int sys_shmdt(const void* shmaddr)
+{
+	shm_attach_t* attach;
+	shm_attach_t** prev;
+
+	spinlock_lock(&attach_lock);
+
+	for(prev=&attachments, attach=*prev; attach; prev=&attach->next, attach=*prev) {
+		if (attach->vaddr == (size_t) shmaddr) {
+			*prev = attach->next;
+			break;
+		}
+	}
+
+	spinlock_unlock(&attach_lock);
+
+	if (BUILTIN_EXPECT(!attach, 0))
+		return -EINVAL;
+
+	page_unmap(attach->vaddr, attach->npages);
+	vma_free(attach->vaddr, attach->vaddr + (attach->npages << PAGE_BITS));
+	shm_release(attach->slot);
+	kfree(attach);
+
+	return 0;
+}
+
+int sys_shmctl(int shmid, int cmd, struct shmid_ds* buf)
+{
+	shm_segment_t* table = shm_table();
+	int slot, ret = 0;
+
+	shm_lock();
+
+	shm_reap(table);
+
+	slot = shm_lookup(table, shmid);
+	if (BUILTIN_EXPECT(slot < 0, 0)) {
+		ret = slot;
+		goto out;
+	}
+
+	switch(cmd) {
+	case IPC_RMID:
+		table[slot].flags |= SHM_SEG_REMOVED;
+		if (table[slot].nattch <= 0)
+			shm_destroy(table+slot);
+		break;
+	case IPC_STAT:
+		if (BUILTIN_EXPECT(!buf, 0)) {
+			ret = -EFAULT;
+			break;
+		}
+		buf->shm_perm.key = table[slot].key;
+		buf->shm_segsz = table[slot].size;
+		buf->shm_nattch = table[slot].nattch;
+		break;
+	case IPC_SET:
+		// the segments don't have changeable attributes
+		break;
+	default:
+		ret = -EINVAL;
+	}
+
+out:
+	shm_unlock();
+
+	return ret;
+}
+
+int shmget(key_t key, size_t size, int shmflg)
+{
+	int ret = sys_shmget(key, size, shmflg);
+
+	if (BUILTIN_EXPECT(ret < 0, 0))
+		return -1;
+
+	return ret;
+}
+
+void* shmat(int shmid, const void* shmaddr, int shmflg)
+{
+	void* ret = sys_shmat(shmid, shmaddr, shmflg);
+
+	if (BUILTIN_EXPECT((ssize_t) ret < 0 && (ssize_t) ret > -4096, 0))
+		return (void*) -1;
+
+	return ret;
+}
+
+int shmdt(const void* shmaddr)
+{
+	if (BUILTIN_EXPECT(sys_shmdt(shmaddr) < 0, 0))
+		return -1;
+
+	return 0;
+}
+
+int shmctl(int shmid, int cmd, struct shmid_ds* buf)
+{
+	int ret = sys_shmctl(shmid, cmd, buf);
+
+	if (BUILTIN_EXPECT(ret < 0, 0))
+		return -1;
+
+	return ret;
+}
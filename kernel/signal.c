@@ -0,0 +1,459 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/signal.c
+ * @brief Architecture independent part of the signal handling
+ *
+ * The actions are shared by all tasks, whereas every task has its own
+ * signal mask and pending set. The architecture specific part injects the
+ * handlers of deliverable signals into the control flow of the receiver.
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/stdlib.h>
+#include <hermit/string.h>
+#include <hermit/errno.h>
+#include <hermit/tasks.h>
+#include <hermit/spinlock.h>
+#include <hermit/signal.h>
+#include <hermit/syscall.h>
+#include <hermit/logging.h>
+
+/// signals, which can't be caught, blocked or ignored
+#define SIG_UNBLOCKABLE		(sigmask(SIGKILL) | sigmask(SIGSTOP))
+
+/// signals, whose default action is to ignore them
+#define SIG_DEFAULT_IGNORE	(sigmask(SIGURG) | sigmask(SIGCHLD) | sigmask(SIGWINCH) \
+				| sigmask(SIGCONT) | sigmask(SIGSTOP) | sigmask(SIGTSTP) \
+				| sigmask(SIGTTIN) | sigmask(SIGTTOU))
+
+/// signals of faults, which are delivered before all other signals
+#define SIG_SYNCHRONOUS		(sigmask(SIGSEGV) | sigmask(SIGBUS) | sigmask(SIGFPE) \
+				| sigmask(SIGILL) | sigmask(SIGTRAP))
+
+#define SIG_VALID(sig)		(((sig) > 0) && ((sig) < NSIG))
+
+static struct sigaction sig_actions[NSIG];
+
+/// protects the actions and the signal state of all tasks
+static spinlock_irqsave_t sig_lock = SPINLOCK_IRQSAVE_INIT;
+
+/* default action of signals, which terminate the application */
+static void signal_terminate(int signum)
+{
+	LOG_ERROR("Task %d terminated by signal %d\n", per_core(current_task)->id, signum);
+
+	sys_exit(128 + signum);
+}
+
+static inline int signal_ignored(int signum)
+{
+	const _sig_func_ptr handler = sig_actions[signum].sa_handler;
+
+	if (handler == SIG_IGN)
+		return 1;
+
+	return (handler == SIG_DFL) && (sigmask(signum) & SIG_DEFAULT_IGNORE);
+}
+
+/* does the stack pointer belong to the alternate signal stack? */
+static inline int signal_on_altstack(task_t* task, size_t sp)
+{
+	const stack_t* st = &task->sig_altstack;
+
+	if (!st->ss_size || (st->ss_flags & SS_DISABLE))
+		return 0;
+
+	return (sp > (size_t) st->ss_sp) && (sp <= (size_t) st->ss_sp + st->ss_size);
+}
+
+size_t signal_stack(task_t* task, const struct sigaction* act, size_t sp)
+{
+	const stack_t* st = &task->sig_altstack;
+
+	if (!(act->sa_flags & SA_ONSTACK) || !st->ss_size || (st->ss_flags & SS_DISABLE))
+		return 0;
+
+	// a nested signal continues on the alternate stack
+	if (signal_on_altstack(task, sp))
+		return 0;
+
+	return (size_t) st->ss_sp + st->ss_size;
+}
+
+int signal_deliverable(task_t* task)
+{
+	return (task->sig_pending & ~task->sig_mask) != 0;
+}
+
+int signal_send(tid_t dest, const siginfo_t* info)
+{
+	const int signum = info->si_signo;
+	task_t* task;
+
+	if (BUILTIN_EXPECT(!SIG_VALID(signum), 0))
+		return -EINVAL;
+
+	if (BUILTIN_EXPECT(get_task(dest, &task), 0)) {
+		LOG_ERROR("Trying to send signal %d to invalid task %d\n", signum, dest);
+		return -ENOENT;
+	}
+
+	LOG_DEBUG("Send signal %d from task %d to task %d\n",
+	          signum, per_core(current_task)->id, dest);
+
+	spinlock_irqsave_lock(&sig_lock);
+
+	if (signal_ignored(signum)) {
+		spinlock_irqsave_unlock(&sig_lock);
+		LOG_DEBUG("  Signal %d is ignored\n", signum);
+		return 0;
+	}
+
+	// standard signals aren't queued => keep the information of the first one
+	if (!(task->sig_pending & sigmask(signum))) {
+		task->sig_info[signum] = *info;
+		task->sig_pending |= sigmask(signum);
+	}
+
+	const int deliverable = signal_deliverable(task);
+
+	spinlock_irqsave_unlock(&sig_lock);
+
+	if (deliverable)
+		signal_notify(task);
+
+	return 0;
+}
+
+int signal_dequeue(task_t* task, signal_frame_t* frame, struct sigaction* act)
+{
+	int signum = 0;
+	sigset_t set;
+
+	spinlock_irqsave_lock(&sig_lock);
+
+	while((set = task->sig_pending & ~task->sig_mask) != 0) {
+		// the handler of a fault has to run before the faulting instruction is repeated
+		if (set & SIG_SYNCHRONOUS)
+			set &= SIG_SYNCHRONOUS;
+
+		signum = __builtin_ctzl(set);
+		task->sig_pending &= ~sigmask(signum);
+
+		// the action could have been changed after sending the signal
+		if (!signal_ignored(signum))
+			break;
+
+		signum = 0;
+	}
+
+	if (!signum)
+		goto out;
+
+	*act = sig_actions[signum];
+
+	frame->mask = task->sig_mask;
+	frame->info = task->sig_info[signum];
+
+	task->sig_mask |= act->sa_mask;
+	if (!(act->sa_flags & SA_NODEFER))
+		task->sig_mask |= sigmask(signum);
+	task->sig_mask &= ~SIG_UNBLOCKABLE;
+
+	if (act->sa_flags & SA_RESETHAND) {
+		sig_actions[signum].sa_handler = SIG_DFL;
+		sig_actions[signum].sa_flags &= ~SA_SIGINFO;
+	}
+
+	if (act->sa_handler == SIG_DFL) {
+		act->sa_handler = signal_terminate;
+		act->sa_flags &= ~SA_SIGINFO;
+	}
+
+	task->signal_count++;
+	// without SA_RESTART, interrupted system calls return -EINTR
+	if (!(act->sa_flags & SA_RESTART))
+		task->signal_intr_count++;
+
+out:
+	spinlock_irqsave_unlock(&sig_lock);
+
+	return signum;
+}
+
+int signal_force(const siginfo_t* info)
+{
+	task_t* curr_task = per_core(current_task);
+	const int signum = info->si_signo;
+	int ret = -EINVAL;
+
+	if (BUILTIN_EXPECT(!SIG_VALID(signum), 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&sig_lock);
+
+	// a fault can't be ignored or deferred
+	if ((sig_actions[signum].sa_handler == SIG_DFL) || (sig_actions[signum].sa_handler == SIG_IGN)
+	    || (curr_task->sig_mask & sigmask(signum)))
+		goto out;
+
+	curr_task->sig_info[signum] = *info;
+	curr_task->sig_pending |= sigmask(signum);
+	ret = 0;
+
+out:
+	spinlock_irqsave_unlock(&sig_lock);
+
+	return ret;
+}
+
+void* signal_return(signal_frame_t* frame)
+{
+	task_t* curr_task = per_core(current_task);
+
+	spinlock_irqsave_lock(&sig_lock);
+	curr_task->sig_mask = frame->mask;
+	const int deliverable = signal_deliverable(curr_task);
+	spinlock_irqsave_unlock(&sig_lock);
+
+	// signals, which were blocked by the handler
+	if (deliverable)
+		signal_notify(curr_task);
+
+	return frame->state;
+}
+
+int hermit_kill(tid_t dest, int signum)
+{
+	siginfo_t info;
+	task_t* task;
+
+	// signal 0 checks only the existence of the task
+	if (!signum)
+		return get_task(dest, &task);
+
+	memset(&info, 0x00, sizeof(info));
+	info.si_signo = signum;
+	info.si_code = SI_USER;
+	info.si_pid = per_core(current_task)->id;
+
+	return signal_send(dest, &info);
+}
+
+int hermit_signal(signal_handler_t handler)
+{
+	int signum;
+
+	spinlock_irqsave_lock(&sig_lock);
+
+	for(signum=1; signum<NSIG; signum++) {
+		if (sigmask(signum) & SIG_UNBLOCKABLE)
+			continue;
+
+		sig_actions[signum].sa_handler = handler ? handler : SIG_DFL;
+		sig_actions[signum].sa_mask = 0;
+		sig_actions[signum].sa_flags = 0;
+	}
+
+	spinlock_irqsave_unlock(&sig_lock);
+
+	return 0;
+}
+
+int sys_sigaction(int signum, const struct sigaction* act, struct sigaction* oldact)
+{
+	if (BUILTIN_EXPECT(!SIG_VALID(signum), 0))
+		return -EINVAL;
+
+	if (BUILTIN_EXPECT(act && (sigmask(signum) & SIG_UNBLOCKABLE), 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&sig_lock);
+
+	if (oldact)
+		*oldact = sig_actions[signum];
+
+	// pending signals, which are ignored now, are discarded by signal_dequeue()
+	if (act) {
+		sig_actions[signum] = *act;
+		sig_actions[signum].sa_mask &= ~SIG_UNBLOCKABLE;
+	}
+
+	spinlock_irqsave_unlock(&sig_lock);
+
+	return 0;
+}
+
+int sys_sigaltstack(const stack_t* ss, stack_t* old_ss)
+{
+	task_t* curr_task = per_core(current_task);
+	// the system call runs on the stack of the caller
+	const int on_stack = signal_on_altstack(curr_task, (size_t) &curr_task);
+	stack_t old = curr_task->sig_altstack;
+
+	if (ss) {
+		// the stack can't be changed by a handler, which runs on it
+		if (BUILTIN_EXPECT(on_stack, 0))
+			return -EPERM;
+		if (BUILTIN_EXPECT(ss->ss_flags & ~SS_DISABLE, 0))
+			return -EINVAL;
+
+		if (ss->ss_flags & SS_DISABLE) {
+			curr_task->sig_altstack.ss_sp = NULL;
+			curr_task->sig_altstack.ss_flags = SS_DISABLE;
+			curr_task->sig_altstack.ss_size = 0;
+		} else {
+			if (BUILTIN_EXPECT(ss->ss_size < MINSIGSTKSZ, 0))
+				return -ENOMEM;
+
+			curr_task->sig_altstack = *ss;
+		}
+	}
+
+	if (old_ss) {
+		*old_ss = old;
+		if (on_stack)
+			old_ss->ss_flags = SS_ONSTACK;
+		else if (!old.ss_size)
+			old_ss->ss_flags = SS_DISABLE;
+	}
+
+	return 0;
+}
+
+int sys_sigprocmask(int how, const sigset_t* set, sigset_t* oldset)
+{
+	task_t* curr_task = per_core(current_task);
+	int deliverable;
+
+	if (BUILTIN_EXPECT(set && (how != SIG_SETMASK) && (how != SIG_BLOCK)
+	    && (how != SIG_UNBLOCK), 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&sig_lock);
+
+	if (oldset)
+		*oldset = curr_task->sig_mask;
+
+	if (set) {
+		if (how == SIG_SETMASK)
+			curr_task->sig_mask = *set;
+		else if (how == SIG_BLOCK)
+			curr_task->sig_mask |= *set;
+		else
+			curr_task->sig_mask &= ~*set;
+
+		curr_task->sig_mask &= ~SIG_UNBLOCKABLE;
+	}
+
+	deliverable = signal_deliverable(curr_task);
+
+	spinlock_irqsave_unlock(&sig_lock);
+
+	// unblocked signals are delivered before returning to the caller
+	if (deliverable)
+		signal_notify(curr_task);
+
+	return 0;
+}
+
+int sys_pthread_sigmask(int how, const sigset_t* set, sigset_t* oldset)
+{
+	return sys_sigprocmask(how, set, oldset);
+}
+
+int sys_sigpending(sigset_t* set)
+{
+	task_t* curr_task = per_core(current_task);
+	int signum;
+
+	if (BUILTIN_EXPECT(!set, 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&sig_lock);
+	*set = curr_task->sig_pending & curr_task->sig_mask;
+	for(signum=1; signum<NSIG; signum++) {
+		if ((*set & sigmask(signum)) && signal_ignored(signum))
+			*set &= ~sigmask(signum);
+	}
+	spinlock_irqsave_unlock(&sig_lock);
+
+	return 0;
+}
+
+int sys_sigsuspend(const sigset_t* mask)
+{
+	task_t* curr_task = per_core(current_task);
+	const uint32_t signal_count = curr_task->signal_count;
+	sigset_t oldmask;
+	uint8_t flags;
+
+	if (BUILTIN_EXPECT(!mask, 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&sig_lock);
+	oldmask = curr_task->sig_mask;
+	curr_task->sig_mask = *mask & ~SIG_UNBLOCKABLE;
+	const int deliverable = signal_deliverable(curr_task);
+	spinlock_irqsave_unlock(&sig_lock);
+
+	if (deliverable)
+		signal_notify(curr_task);
+
+	while(1) {
+		flags = irq_nested_disable();
+
+		// the handler has been executed => leave the loop
+		if (curr_task->signal_count != signal_count) {
+			irq_nested_enable(flags);
+			break;
+		}
+
+		curr_task->flags |= TASK_INTERRUPTIBLE;
+		block_current_task();
+
+		irq_nested_enable(flags);
+
+		reschedule();
+
+		curr_task->flags &= ~TASK_INTERRUPTIBLE;
+	}
+
+	spinlock_irqsave_lock(&sig_lock);
+	curr_task->sig_mask = oldmask;
+	const int pending = signal_deliverable(curr_task);
+	spinlock_irqsave_unlock(&sig_lock);
+
+	if (pending)
+		signal_notify(curr_task);
+
+	return -EINTR;
+}
@@ -37,9 +37,15 @@
 #include <hermit/memory.h>
 #include <hermit/signal.h>
 #include <hermit/logging.h>
+#include <hermit/vfs.h>
+#include <hermit/proxy.h>
+#include <hermit/uhyve_ring.h>
 #include <asm/uhyve.h>
 #include <asm/io.h>
 #include <sys/poll.h>
+#include <sys/stat.h>
+#include <sys/dirent.h>
+#include <sys/mman.h>
 
 #include <lwip/sockets.h>
 #include <lwip/err.h>
@@ -51,7 +57,7 @@
  */
 extern const void kernel_start;
 
-//TODO: don't use one big kernel lock to comminicate with all proxies
+/// Serializes the access to LwIP sockets, the proxy uses its own locks
 static spinlock_irqsave_t lwip_lock = SPINLOCK_IRQSAVE_INIT;
 
 extern spinlock_irqsave_t stdio_lock;
@@ -59,34 +65,31 @@ extern int32_t isle;
 extern int32_t possible_isles;
 extern volatile int libc_sd;
 
-static inline int socket_send(int fd, const 	void* buf, size_t len)
-{
-	int ret, sz = 0;
+#ifndef IOV_MAX
+#define IOV_MAX		1024
+#endif
 
-	do {
-		ret = lwip_write(fd, (char*)buf + sz, len-sz);
-		if (ret >= 0)
-			sz += ret;
-		else
-			return ret;
-	} while(sz < len);
+#ifndef SSIZE_MAX
+#define SSIZE_MAX	((ssize_t) (~0ULL >> 1))
+#endif
 
-	return len;
+/* Size of a path in a request to the proxy */
+static inline size_t proxy_path_len(const char* name)
+{
+	return sizeof(size_t) + strlen(name) + 1;
 }
 
-static inline int socket_recv(int fd, void* buf, size_t len)
+/* Send the length of a path and the path itself to the proxy */
+static int proxy_send_path(proxy_call_t* call, const char* name)
 {
-	int ret, sz = 0;
+	size_t len = strlen(name)+1;
+	int ret;
 
-	do {
-		ret = lwip_read(fd, (char*)buf + sz, len-sz);
-		if (ret >= 0)
-			sz += ret;
-		else
-			return ret;
-	} while(sz < len);
+	ret = proxy_send(call, &len, sizeof(len));
+	if (ret < 0)
+		return ret;
 
-	return len;
+	return proxy_send(call, name, len);
 }
 
 tid_t sys_getpid(void)
@@ -110,8 +113,6 @@ int sys_setprio(tid_t* id, int prio)
 	return -ENOSYS;
 }
 
-void NORETURN do_exit(int arg);
-
 typedef struct {
 	int sysnr;
 	int arg;
@@ -124,23 +125,14 @@ void NORETURN sys_exit(int arg)
 		uhyve_send(UHYVE_PORT_EXIT, (unsigned) virt_to_phys((size_t) &arg));
 	} else {
 		sys_exit_t sysargs = {__NR_exit, arg};
+		proxy_call_t call;
 
-		spinlock_irqsave_lock(&lwip_lock);
-		if (libc_sd >= 0)
-		{
-			int s = libc_sd;
-
-			socket_send(s, &sysargs, sizeof(sysargs));
-			libc_sd = -1;
-
-			spinlock_irqsave_unlock(&lwip_lock);
-
-			// switch to LwIP thread
-			reschedule();
+		if (!proxy_begin(&call, sizeof(sysargs), 0)) {
+			// the proxy doesn't reply to exit
+			proxy_send(&call, &sysargs, sizeof(sysargs));
+			proxy_end(&call);
 
-			lwip_close(s);
-		} else {
-			spinlock_irqsave_unlock(&lwip_lock);
+			proxy_shutdown();
 		}
 	}
 
@@ -153,73 +145,93 @@ typedef struct {
 	size_t len;
 } __attribute__((packed)) sys_read_t;
 
-typedef struct {
-	int fd;
-	char* buf;
-        size_t len;
-	ssize_t ret;
-} __attribute__((packed)) uhyve_read_t;
+static ssize_t uhyve_iov(unsigned short port, int fd, const struct iovec* iov, int iovcnt);
 
-ssize_t sys_read(int fd, char* buf, size_t len)
+static ssize_t host_read(file_t* file, char* buf, size_t len)
 {
+	int fd = file->handle;
 	sys_read_t sysargs = {__NR_read, fd, len};
-	ssize_t j, ret;
+	proxy_call_t call;
+	ssize_t j, ret, surplus = 0;
 
-	// do we have an LwIP file descriptor?
-	if (fd & LWIP_FD_BIT) {
-		spinlock_irqsave_lock(&lwip_lock);
-		ret = lwip_read(fd & ~LWIP_FD_BIT, buf, len);
-		spinlock_irqsave_unlock(&lwip_lock);
-		if (ret < 0)
-			return -errno;
+	if (is_uhyve()) {
+		struct iovec iov = {buf, len};
 
-		return ret;
+		if (!len)
+			return 0;
+
+		return uhyve_iov(UHYVE_PORT_READV, fd, &iov, 1);
 	}
 
-	if (is_uhyve()) {
-		uhyve_read_t uhyve_args = {fd, (char*) buf, len, -1};
+	ret = proxy_begin(&call, sizeof(sysargs), 1);
+	if (ret)
+		return ret;
 
-		uhyve_send(UHYVE_PORT_READ, (unsigned)virt_to_phys((size_t)&uhyve_args));
+	proxy_send(&call, &sysargs, sizeof(sysargs));
+	ret = proxy_recv(&call, &j, sizeof(j));
 
-		return uhyve_args.ret;
+	// don't trust the host => drop the surplus bytes
+	if ((ret >= 0) && (j > (ssize_t) len)) {
+		LOG_WARNING("Proxy returns %zd bytes for a read of %zu bytes\n", j, len);
+		surplus = j - len;
+		j = len;
 	}
 
-	if (libc_sd < 0)
-		return -ENOSYS;
+	if ((ret >= 0) && (j > 0))
+		ret = proxy_recv(&call, buf, j);
+	if ((ret >= 0) && surplus)
+		ret = proxy_skip(&call, surplus);
 
-	spinlock_irqsave_lock(&lwip_lock);
+	proxy_end(&call);
 
-	int s = libc_sd;
-	socket_send(s, &sysargs, sizeof(sysargs));
-	socket_recv(s, &j, sizeof(j));
+	return (ret < 0) ? ret : j;
+}
 
-	ssize_t i=0;
-	while(i < j)
-	{
-		ret = lwip_read(s, (char*)buf+i, j-i);
-		if (ret < 0) {
-			spinlock_irqsave_unlock(&lwip_lock);
-			return ret;
-		}
+/*
+ * Validates an iovec array and returns the total number of bytes,
+ * which is described by the array.
+ */
+static ssize_t iov_length(const struct iovec* iov, int iovcnt)
+{
+	ssize_t len = 0;
 
-		i += ret;
-	}
+	if (BUILTIN_EXPECT((iovcnt < 0) || (iovcnt > IOV_MAX), 0))
+		return -EINVAL;
 
-	spinlock_irqsave_unlock(&lwip_lock);
+	// an empty request transfers nothing
+	if (!iovcnt)
+		return 0;
 
-	return j;
-}
+	if (BUILTIN_EXPECT(!iov, 0))
+		return -EINVAL;
 
-ssize_t readv(int d, const struct iovec *iov, int iovcnt)
-{
-	return -ENOSYS;
+	for(int i=0; i<iovcnt; i++) {
+		if (BUILTIN_EXPECT(!iov[i].iov_base && iov[i].iov_len, 0))
+			return -EFAULT;
+
+		// the sum of all buffer lengths has to fit in a ssize_t
+		if (BUILTIN_EXPECT(iov[i].iov_len > (size_t) (SSIZE_MAX - len), 0))
+			return -EINVAL;
+
+		len += iov[i].iov_len;
+	}
+
+	return len;
 }
 
 typedef struct {
-	int sysnr;
 	int fd;
+	const struct iovec* iov;
+	int iovcnt;
+	ssize_t ret;
+} __attribute__((packed)) uhyve_iov_t;
+
+typedef struct {
+	int fd;
+	char* buf;
 	size_t len;
-} __attribute__((packed)) sys_write_t;
+	ssize_t ret;
+} __attribute__((packed)) uhyve_read_t;
 
 typedef struct {
 	int fd;
@@ -227,31 +239,203 @@ typedef struct {
 	size_t len;
 } __attribute__((packed)) uhyve_write_t;
 
-ssize_t sys_write(int fd, const char* buf, size_t len)
+/// Maximum number of physical segments per hypercall
+#define UHYVE_MAX_SEGMENTS	32
+
+/* Passes one buffer to a host, which doesn't support segment lists */
+static ssize_t uhyve_rw(unsigned short port, int fd, char* buf, size_t len)
 {
-	if (BUILTIN_EXPECT(!buf, 0))
-		return -EINVAL;
+	if (port == UHYVE_PORT_READV) {
+		uhyve_read_t uhyve_args = {fd, buf, len, -1};
 
-	ssize_t i, ret;
-	sys_write_t sysargs = {__NR_write, fd, len};
+		uhyve_call(UHYVE_PORT_READ, (unsigned)virt_to_phys((size_t)&uhyve_args));
+
+		return uhyve_args.ret;
+	} else {
+		uhyve_write_t uhyve_args = {fd, buf, len};
+
+		uhyve_call(UHYVE_PORT_WRITE, (unsigned)virt_to_phys((size_t)&uhyve_args));
+
+		return uhyve_args.len;
+	}
+}
+
+static ssize_t uhyve_segments(unsigned short port, int fd, const struct iovec* seg, int nseg)
+{
+	uhyve_iov_t uhyve_args = {fd, (const struct iovec*) virt_to_phys((size_t) seg), nseg, -1};
+
+	uhyve_call(port, (unsigned)virt_to_phys((size_t)&uhyve_args));
+
+	return uhyve_args.ret;
+}
+
+/*
+ * With UHYVE_FEATURE_IOV, uhyve receives a list of guest physical
+ * segments. A buffer isn't physically contiguous, e.g. the page fault
+ * handler maps the heap piecewise. Therefore, the vector is translated
+ * page by page, where physically contiguous pages are merged. A long
+ * list is passed in chunks of UHYVE_MAX_SEGMENTS segments, until the
+ * host reports a short transfer.
+ */
+static ssize_t uhyve_iov(unsigned short port, int fd, const struct iovec* iov, int iovcnt)
+{
+	// the list is passed by its physical address => it mustn't cross a page boundary
+	struct iovec seg[UHYVE_MAX_SEGMENTS] __attribute__((aligned(UHYVE_MAX_SEGMENTS*sizeof(struct iovec))));
+	size_t addr, remain, n, phys, chunk = 0;
+	ssize_t ret, total = 0;
+	int nseg = 0;
+
+	// the host doesn't check the access permissions of the guest
+	if (port == UHYVE_PORT_READV) {
+		for(int i=0; i<iovcnt; i++) {
+			addr = (size_t) iov[i].iov_base;
+			if (BUILTIN_EXPECT(!page_writable(addr, addr + iov[i].iov_len), 0))
+				return -EFAULT;
+		}
+	}
+
+	if (!(get_uhyve_features() & UHYVE_FEATURE_IOV)) {
+		for(int i=0; i<iovcnt; i++) {
+			if (!iov[i].iov_len)
+				continue;
+
+			ret = uhyve_rw(port, fd, iov[i].iov_base, iov[i].iov_len);
+			if (ret < 0)
+				return total ? total : ret;
+
+			total += ret;
+			if ((size_t) ret < iov[i].iov_len)
+				break;
+		}
+
+		return total;
+	}
+
+	for(int i=0; i<iovcnt; i++) {
+		addr = (size_t) iov[i].iov_base;
+		remain = iov[i].iov_len;
+
+		while(remain) {
+			n = PAGE_SIZE - (addr & ~PAGE_MASK);
+			if (n > remain)
+				n = remain;
+
+			// touch the page to map it on demand, before it's translated
+			(void) *((volatile const char*) addr);
+			phys = virt_to_phys(addr);
+
+			if (nseg && ((size_t) seg[nseg-1].iov_base + seg[nseg-1].iov_len == phys)) {
+				seg[nseg-1].iov_len += n;
+			} else {
+				if (nseg == UHYVE_MAX_SEGMENTS) {
+					ret = uhyve_segments(port, fd, seg, nseg);
+					if (ret < 0)
+						return total ? total : ret;
+
+					total += ret;
+					if ((size_t) ret < chunk)
+						return total;
+
+					nseg = 0;
+					chunk = 0;
+				}
+
+				seg[nseg].iov_base = (void*) phys;
+				seg[nseg].iov_len = n;
+				nseg++;
+			}
+
+			chunk += n;
+			addr += n;
+			remain -= n;
+		}
+	}
 
-	// do we have an LwIP file descriptor?
-	if (fd & LWIP_FD_BIT) {
-		spinlock_irqsave_lock(&lwip_lock);
-		ret = lwip_write(fd & ~LWIP_FD_BIT, buf, len);
-		spinlock_irqsave_unlock(&lwip_lock);
+	if (nseg) {
+		ret = uhyve_segments(port, fd, seg, nseg);
 		if (ret < 0)
-			return -errno;
+			return total ? total : ret;
+
+		total += ret;
+	}
+
+	return total;
+}
+
+static ssize_t host_readv(file_t* file, const struct iovec *iov, int iovcnt)
+{
+	int fd = file->handle;
+	ssize_t i, j, ret, len, surplus = 0;
+
+	len = iov_length(iov, iovcnt);
+	if (BUILTIN_EXPECT(len < 0, 0))
+		return len;
+
+	if (!len)
+		return 0;
+
+	if (is_uhyve())
+		return uhyve_iov(UHYVE_PORT_READV, fd, iov, iovcnt);
 
+	// the proxy handles the request as one read of all bytes
+	sys_read_t sysargs = {__NR_read, fd, len};
+	proxy_call_t call;
+
+	ret = proxy_begin(&call, sizeof(sysargs), 1);
+	if (ret)
 		return ret;
+
+	proxy_send(&call, &sysargs, sizeof(sysargs));
+	ret = proxy_recv(&call, &j, sizeof(j));
+
+	// don't trust the host => drop the surplus bytes
+	if ((ret >= 0) && (j > len)) {
+		LOG_WARNING("Proxy returns %zd bytes for a read of %zd bytes\n", j, len);
+		surplus = j - len;
+		j = len;
 	}
 
+	// scatter the received bytes over all buffers
+	i = 0;
+	for(int k=0; (ret >= 0) && (k<iovcnt) && (i<j); k++)
+	{
+		size_t n = iov[k].iov_len;
+
+		if (n > j-i)
+			n = j-i;
+
+		ret = proxy_recv(&call, iov[k].iov_base, n);
+		i += n;
+	}
+
+	if ((ret >= 0) && surplus)
+		ret = proxy_skip(&call, surplus);
+
+	proxy_end(&call);
+
+	return (ret < 0) ? ret : j;
+}
+
+typedef struct {
+	int sysnr;
+	int fd;
+	size_t len;
+} __attribute__((packed)) sys_write_t;
+
+static ssize_t host_write(file_t* file, const char* buf, size_t len)
+{
+	int fd = file->handle;
+	ssize_t i, ret;
+	sys_write_t sysargs = {__NR_write, fd, len};
+	proxy_call_t call;
+
 	if (is_uhyve()) {
-		uhyve_write_t uhyve_args = {fd, (const char*) buf, len};
+		struct iovec iov = {(void*) buf, len};
 
-		uhyve_send(UHYVE_PORT_WRITE, (unsigned)virt_to_phys((size_t)&uhyve_args));
+		if (!len)
+			return 0;
 
-		return uhyve_args.len;
+		return uhyve_iov(UHYVE_PORT_WRITEV, fd, &iov, 1);
 	}
 
 	if (libc_sd < 0)
@@ -264,40 +448,77 @@ ssize_t sys_write(int fd, const char* buf, size_t len)
 		return len;
 	}
 
-	spinlock_irqsave_lock(&lwip_lock);
+	// the proxy doesn't reply to writes on stdout and stderr
+	ret = proxy_begin(&call, sizeof(sysargs) + len, fd > 2);
+	if (ret)
+		return ret;
+
+	proxy_send(&call, &sysargs, sizeof(sysargs));
+	ret = proxy_send(&call, buf, len);
+
+	i = len;
+	if ((ret >= 0) && (fd > 2))
+		ret = proxy_recv(&call, &i, sizeof(i));
 
-	int s = libc_sd;
-	socket_send(s, &sysargs, sizeof(sysargs));
+	proxy_end(&call);
 
-	i=0;
-	while(i < len)
+	return (ret < 0) ? ret : i;
+}
+
+static ssize_t host_writev(file_t* file, const struct iovec *iov, int iovcnt)
+{
+	int fd = file->handle;
+	ssize_t i, ret, len;
+
+	len = iov_length(iov, iovcnt);
+	if (BUILTIN_EXPECT(len < 0, 0))
+		return len;
+
+	if (!len)
+		return 0;
+
+	if (is_uhyve())
+		return uhyve_iov(UHYVE_PORT_WRITEV, fd, iov, iovcnt);
+
+	if (libc_sd < 0)
 	{
-		ret = lwip_write(s, (char*)buf+i, len-i);
-		if (ret < 0) {
-			spinlock_irqsave_unlock(&lwip_lock);
-			return ret;
+		spinlock_irqsave_lock(&stdio_lock);
+		for(int k=0; k<iovcnt; k++) {
+			for(i=0; i<iov[k].iov_len; i++)
+				kputchar(((const char*) iov[k].iov_base)[i]);
 		}
+		spinlock_irqsave_unlock(&stdio_lock);
 
-		i += ret;
+		return len;
 	}
 
-	if (fd > 2)
+	/*
+	 * The proxy handles the request as one write of all bytes.
+	 * Consequently, a partial write is reported in the same way
+	 * as by sys_write.
+	 */
+	sys_write_t sysargs = {__NR_write, fd, len};
+	proxy_call_t call;
+
+	ret = proxy_begin(&call, sizeof(sysargs) + len, fd > 2);
+	if (ret)
+		return ret;
+
+	ret = proxy_send(&call, &sysargs, sizeof(sysargs));
+
+	for(int k=0; (ret >= 0) && (k<iovcnt); k++)
 	{
-		int temp = socket_recv(s, &i, sizeof(i));
-		if (temp < 0)
-		{
-			return (ssize_t) temp;
-		}
+		if (iov[k].iov_len)
+			ret = proxy_send(&call, iov[k].iov_base, iov[k].iov_len);
 	}
 
-	spinlock_irqsave_unlock(&lwip_lock);
+	i = len;
+	if ((ret >= 0) && (fd > 2))
+		ret = proxy_recv(&call, &i, sizeof(i));
 
-	return i;
-}
+	proxy_end(&call);
 
-ssize_t writev(int fildes, const struct iovec *iov, int iovcnt)
-{
-	return -ENOSYS;
+	return (ret < 0) ? ret : i;
 }
 
 ssize_t sys_sbrk(ssize_t incr)
@@ -336,129 +557,463 @@ ssize_t sys_sbrk(ssize_t incr)
 	return ret;
 }
 
-typedef struct {
-	const char* name;
-	int flags;
-	int mode;
-	int ret;
-} __attribute__((packed)) uhyve_open_t;
-
-int sys_open(const char* name, int flags, int mode)
+/* Convert the memory protection of mmap into VMA flags */
+static uint32_t prot_to_vma(int prot, uint32_t type)
 {
-	if (is_uhyve()) {
-		uhyve_open_t uhyve_open = {(const char*)virt_to_phys((size_t)name), flags, mode, -1};
+	uint32_t flags = type|VMA_USER|VMA_CACHEABLE;
+
+	if (prot & PROT_READ)
+		flags |= VMA_READ;
+	if (prot & PROT_WRITE)
+		flags |= VMA_READ|VMA_WRITE;
+	if (prot & PROT_EXEC)
+		flags |= VMA_EXECUTE;
+	if (!(prot & (PROT_READ|PROT_WRITE|PROT_EXEC)))
+		flags |= VMA_NO_ACCESS;
+
+	return flags;
+}
 
-		uhyve_send(UHYVE_PORT_OPEN, (unsigned)virt_to_phys((size_t) &uhyve_open));
+static const file_ops_t host_ops;
+
+/** @brief Description of a file-backed mapping */
+typedef struct mmap_file {
+	/// Start address of the mapping
+	size_t start;
+	/// End address of the mapping
+	size_t end;
+	/// Mapped file, the mapping holds a reference
+	file_t* file;
+	/// File offset, which belongs to start
+	off_t offset;
+	/// Next mapping
+	struct mmap_file* next;
+} mmap_file_t;
 
-		return uhyve_open.ret;
-	}
+static mmap_file_t* mmap_files = NULL;
+static spinlock_irqsave_t mmap_lock = SPINLOCK_IRQSAVE_INIT;
 
-	int s, i, ret, sysnr = __NR_open;
+typedef struct {
+	int fd;
+	char* buf;
 	size_t len;
+	off_t offset;
+	ssize_t ret;
+} __attribute__((packed)) uhyve_pread_t;
 
-	spinlock_irqsave_lock(&lwip_lock);
-	if (libc_sd < 0) {
-		ret = -EINVAL;
-		goto out;
-	}
-
-	s = libc_sd;
-	len = strlen(name)+1;
-
-	//i = 0;
-	//lwip_setsockopt(s, IPPROTO_TCP, TCP_NODELAY, (char *) &i, sizeof(i));
+ssize_t mmap_file_read(size_t viraddr, size_t phyaddr)
+{
+	mmap_file_t* map;
+	ssize_t ret = -EFAULT;
 
-	ret = socket_send(s, &sysnr, sizeof(sysnr));
-	if (ret < 0)
-		goto out;
+	viraddr &= PAGE_MASK;
 
-	ret = socket_send(s, &len, sizeof(len));
-	if (ret < 0)
-		goto out;
+	spinlock_irqsave_lock(&mmap_lock);
 
-	i=0;
-	while(i<len)
-	{
-		ret = socket_send(s, name+i, len-i);
-		if (ret < 0)
-			goto out;
-		i += ret;
+	for(map=mmap_files; map; map=map->next) {
+		if ((viraddr >= map->start) && (viraddr < map->end))
+			break;
 	}
 
-	ret = socket_send(s, &flags, sizeof(flags));
-	if (ret < 0)
-		goto out;
-
-	ret = socket_send(s, &mode, sizeof(mode));
-	if (ret < 0)
-		goto out;
+	if (map) {
+		// uhyve copies the file contents directly into the frame
+		uhyve_pread_t uhyve_args = {map->file->handle, (char*) phyaddr, PAGE_SIZE,
+			map->offset + (off_t) (viraddr - map->start), -1};
 
-	//i = 1;
-	//lwip_setsockopt(s, IPPROTO_TCP, TCP_NODELAY, (char *) &i, sizeof(i));
+		uhyve_send(UHYVE_PORT_PREAD, (unsigned)virt_to_phys((size_t)&uhyve_args));
 
-	socket_recv(s, &ret, sizeof(ret));
+		ret = uhyve_args.ret;
+	}
 
-out:
-	spinlock_irqsave_unlock(&lwip_lock);
+	spinlock_irqsave_unlock(&mmap_lock);
 
 	return ret;
 }
 
-typedef struct {
-	int sysnr;
-	int fd;
-} __attribute__((packed)) sys_close_t;
+static int mmap_file_add(size_t start, size_t end, file_t* file, off_t offset)
+{
+	mmap_file_t* map = kmalloc(sizeof(mmap_file_t));
 
-typedef struct {
-        int fd;
-        int ret;
-} __attribute__((packed)) uhyve_close_t;
+	if (BUILTIN_EXPECT(!map, 0))
+		return -ENOMEM;
 
-int sys_close(int fd)
-{
-	int ret, s;
-	sys_close_t sysargs = {__NR_close, fd};
+	atomic_int32_inc(&file->refs);
+	map->start = start;
+	map->end = end;
+	map->file = file;
+	map->offset = offset;
 
-	// do we have an LwIP file descriptor?
-	if (fd & LWIP_FD_BIT) {
-		ret = lwip_close(fd & ~LWIP_FD_BIT);
-		if (ret < 0)
-			return -errno;
+	spinlock_irqsave_lock(&mmap_lock);
+	map->next = mmap_files;
+	mmap_files = map;
+	spinlock_irqsave_unlock(&mmap_lock);
 
-		return 0;
-	}
+	return 0;
+}
 
-	if (is_uhyve()) {
-		uhyve_close_t uhyve_close = {fd, -1};
+/* Remove the range [start, end) from the file mappings */
+static int mmap_file_remove(size_t start, size_t end)
+{
+	mmap_file_t* map;
+	mmap_file_t** prev;
+	mmap_file_t* released = NULL;
+	mmap_file_t* split;
+	int ret = 0;
 
-		uhyve_send(UHYVE_PORT_CLOSE, (unsigned)virt_to_phys((size_t) &uhyve_close));
+	// allocate a descriptor in advance, which is required to split a mapping
+	split = kmalloc(sizeof(mmap_file_t));
+	if (BUILTIN_EXPECT(!split, 0))
+		return -ENOMEM;
 
-		return uhyve_close.ret;
-	}
+	spinlock_irqsave_lock(&mmap_lock);
 
-	spinlock_irqsave_lock(&lwip_lock);
-	if (libc_sd < 0) {
-		ret = 0;
-		goto out;
+	for(prev=&mmap_files, map=*prev; map; map=*prev) {
+		if ((map->end <= start) || (map->start >= end)) {
+			prev = &map->next;
+			continue;
+		}
+
+		if ((start <= map->start) && (end >= map->end)) {
+			*prev = map->next;
+			map->next = released;
+			released = map;
+		} else if (start <= map->start) {
+			map->offset += end - map->start;
+			map->start = end;
+			prev = &map->next;
+		} else if (end >= map->end) {
+			map->end = start;
+			prev = &map->next;
+		} else {
+			atomic_int32_inc(&map->file->refs);
+			split->start = end;
+			split->end = map->end;
+			split->file = map->file;
+			split->offset = map->offset + (end - map->start);
+			split->next = map->next;
+			map->end = start;
+			map->next = split;
+			split = NULL;
+			break;
+		}
 	}
 
-	s = libc_sd;
-	ret = socket_send(s, &sysargs, sizeof(sysargs));
-	if (ret != sizeof(sysargs))
-		goto out;
-	socket_recv(s, &ret, sizeof(ret));
+	spinlock_irqsave_unlock(&mmap_lock);
 
-out:
-	spinlock_irqsave_unlock(&lwip_lock);
+	kfree(split);
+
+	// close the files outside of the lock
+	while(released) {
+		map = released;
+		released = map->next;
+		fd_put(map->file);
+		kfree(map);
+	}
 
 	return ret;
 }
 
-int sys_spinlock_init(spinlock_t** lock)
+/* Release the pages, the file mappings and the VMAs of a mapping of one type */
+static int mmap_release(size_t start, size_t end, uint32_t type)
 {
 	int ret;
 
-	if (BUILTIN_EXPECT(!lock, 0))
+	// describe the range by a single VMA, which could be released at once
+	if (BUILTIN_EXPECT(vma_set_flags(start, end, type|VMA_USER), 0))
+		return -ENOMEM;
+
+	if (type & VMA_FILE) {
+		ret = mmap_file_remove(start, end);
+		if (BUILTIN_EXPECT(ret, 0))
+			return ret;
+	}
+
+	page_unmap_put(start, (end - start) >> PAGE_BITS);
+
+	return vma_free(start, end);
+}
+
+/* Unmap the anonymous and file mappings in the range, other areas aren't replaceable */
+static int mmap_replace(size_t start, size_t end)
+{
+	uint32_t type, next_type;
+	size_t addr, next;
+	int ret;
+
+	// check the whole range first => a failure leaves the mappings untouched
+	for(addr=start; addr<end; addr+=PAGE_SIZE) {
+		if (!vma_get_flags(addr, &type) && !(type & (VMA_ANON|VMA_FILE)))
+			return -EINVAL;
+	}
+
+	for(addr=start; addr<end; addr=next) {
+		next = addr + PAGE_SIZE;
+		if (vma_get_flags(addr, &type))
+			continue;
+
+		// release the pages of the same type at once
+		type &= VMA_ANON|VMA_FILE;
+		while((next < end) && !vma_get_flags(next, &next_type)
+		      && ((next_type & (VMA_ANON|VMA_FILE)) == type))
+			next += PAGE_SIZE;
+
+		ret = mmap_release(addr, next, type);
+		if (BUILTIN_EXPECT(ret, 0))
+			return ret;
+	}
+
+	return 0;
+}
+
+void* sys_mmap(void* addr, size_t length, int prot, int flags, int fd, off_t offset)
+{
+	size_t start = (size_t) addr;
+	file_t* file = NULL;
+	uint32_t type = VMA_ANON;
+	int ret;
+
+	if (BUILTIN_EXPECT(!length, 0))
+		return (void*) -EINVAL;
+	if (BUILTIN_EXPECT(prot & ~(PROT_READ|PROT_WRITE|PROT_EXEC), 0))
+		return (void*) -EINVAL;
+	if (BUILTIN_EXPECT(!(flags & MAP_SHARED) == !(flags & MAP_PRIVATE), 0))
+		return (void*) -EINVAL;
+
+	if (!(flags & MAP_ANONYMOUS)) {
+		// file mappings are read-only and supported by uhyve
+		if (BUILTIN_EXPECT(offset & ~PAGE_MASK, 0))
+			return (void*) -EINVAL;
+		if (BUILTIN_EXPECT(prot & PROT_WRITE, 0))
+			return (void*) -EACCES;
+
+		ret = fd_get(fd, &file);
+		if (BUILTIN_EXPECT(ret, 0))
+			return (void*) (ssize_t) ret;
+
+		if (BUILTIN_EXPECT(!is_uhyve() || (file->ops != &host_ops), 0)) {
+			ret = -ENODEV;
+			goto out;
+		}
+		if (BUILTIN_EXPECT((file->flags & O_ACCMODE) == O_WRONLY, 0)) {
+			ret = -EACCES;
+			goto out;
+		}
+
+		type = VMA_FILE;
+	} else if (flags & MAP_SHARED) {
+		// shared anonymous memory isn't supported
+		return (void*) -ENOSYS;
+	}
+
+	length = PAGE_CEIL(length);
+
+	if (flags & MAP_FIXED) {
+		if (BUILTIN_EXPECT(!start || (start & ~PAGE_MASK), 0)) {
+			ret = -EINVAL;
+			goto out;
+		}
+
+		// the new mapping replaces the existing ones
+		ret = mmap_replace(start, start + length);
+		if (BUILTIN_EXPECT(ret, 0))
+			goto out;
+
+		if (BUILTIN_EXPECT(vma_add(start, start + length, prot_to_vma(prot, type)), 0)) {
+			ret = -ENOMEM;
+			goto out;
+		}
+	} else {
+		// the address is only a hint => ignore it
+		start = vma_alloc(length, prot_to_vma(prot, type));
+		if (BUILTIN_EXPECT(!start, 0)) {
+			ret = -ENOMEM;
+			goto out;
+		}
+	}
+
+	ret = 0;
+	if (file) {
+		ret = mmap_file_add(start, start + length, file, offset);
+		if (BUILTIN_EXPECT(ret, 0))
+			vma_free(start, start + length);
+	}
+
+	// the pages are mapped on demand by the page fault handler
+
+out:
+	if (file)
+		fd_put(file);
+
+	if (BUILTIN_EXPECT(ret, 0))
+		return (void*) (ssize_t) ret;
+
+	return (void*) start;
+}
+
+int sys_munmap(void* addr, size_t length)
+{
+	size_t start = (size_t) addr;
+	size_t end = start + PAGE_CEIL(length);
+	uint32_t type;
+
+	if (BUILTIN_EXPECT((start & ~PAGE_MASK) || !length, 0))
+		return -EINVAL;
+	if (BUILTIN_EXPECT(vma_get_flags(start, &type), 0))
+		return -EINVAL;
+
+	type &= VMA_ANON|VMA_FILE;
+	if (BUILTIN_EXPECT(!type || !vma_check(start, end, type), 0))
+		return -EINVAL;
+
+	return mmap_release(start, end, type);
+}
+
+int sys_mprotect(void* addr, size_t length, int prot)
+{
+	size_t start = (size_t) addr;
+	size_t end = start + PAGE_CEIL(length);
+	uint32_t type, flags;
+	int ret;
+
+	if (BUILTIN_EXPECT((start & ~PAGE_MASK) || !length, 0))
+		return -EINVAL;
+	if (BUILTIN_EXPECT(prot & ~(PROT_READ|PROT_WRITE|PROT_EXEC), 0))
+		return -EINVAL;
+	if (BUILTIN_EXPECT(vma_get_flags(start, &type), 0))
+		return -ENOMEM;
+
+	type &= VMA_ANON|VMA_FILE;
+	if (BUILTIN_EXPECT(!type || !vma_check(start, end, type), 0))
+		return -ENOMEM;
+
+	// file mappings are read-only
+	if (BUILTIN_EXPECT((type & VMA_FILE) && (prot & PROT_WRITE), 0))
+		return -EACCES;
+
+	flags = prot_to_vma(prot, type);
+	ret = vma_set_flags(start, end, flags);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	return page_set_flags(start, (end - start) >> PAGE_BITS, flags);
+}
+
+void* mmap(void* addr, size_t length, int prot, int flags, int fd, off_t offset)
+{
+	void* ret = sys_mmap(addr, length, prot, flags, fd, offset);
+
+	if (BUILTIN_EXPECT((ssize_t) ret < 0 && (ssize_t) ret > -4096, 0))
+		return MAP_FAILED;
+
+	return ret;
+}
+
+int munmap(void* addr, size_t length)
+{
+	if (BUILTIN_EXPECT(sys_munmap(addr, length) < 0, 0))
+		return -1;
+
+	return 0;
+}
+
+int mprotect(void* addr, size_t len, int prot)
+{
+	if (BUILTIN_EXPECT(sys_mprotect(addr, len, prot) < 0, 0))
+		return -1;
+
+	return 0;
+}
+
+typedef struct {
+	const char* name;
+	int flags;
+	int mode;
+	int ret;
+} __attribute__((packed)) uhyve_open_t;
+
+static int host_open_fd(const char* name, int flags, int mode)
+{
+	if (is_uhyve()) {
+		uhyve_open_t uhyve_open = {(const char*)virt_to_phys((size_t)name), flags, mode, -1};
+
+		uhyve_call(UHYVE_PORT_OPEN, (unsigned)virt_to_phys((size_t) &uhyve_open));
+
+		return uhyve_open.ret;
+	}
+
+	int ret, sysnr = __NR_open;
+	proxy_call_t call;
+
+	if (proxy_begin(&call, sizeof(sysnr) + proxy_path_len(name) + sizeof(flags) + sizeof(mode), 1))
+		return -EINVAL;
+
+	ret = proxy_send(&call, &sysnr, sizeof(sysnr));
+	if (ret < 0)
+		goto out;
+
+	ret = proxy_send_path(&call, name);
+	if (ret < 0)
+		goto out;
+
+	ret = proxy_send(&call, &flags, sizeof(flags));
+	if (ret < 0)
+		goto out;
+
+	ret = proxy_send(&call, &mode, sizeof(mode));
+	if (ret < 0)
+		goto out;
+
+	proxy_recv(&call, &ret, sizeof(ret));
+
+out:
+	proxy_end(&call);
+
+	return ret;
+}
+
+typedef struct {
+	int sysnr;
+	int fd;
+} __attribute__((packed)) sys_close_t;
+
+typedef struct {
+        int fd;
+        int ret;
+} __attribute__((packed)) uhyve_close_t;
+
+static int host_close(file_t* file)
+{
+	int ret, fd = file->handle;
+	sys_close_t sysargs = {__NR_close, fd};
+	proxy_call_t call;
+
+	if (is_uhyve()) {
+		uhyve_close_t uhyve_close = {fd, -1};
+
+		uhyve_call(UHYVE_PORT_CLOSE, (unsigned)virt_to_phys((size_t) &uhyve_close));
+
+		return uhyve_close.ret;
+	}
+
+	if (proxy_begin(&call, sizeof(sysargs), 1))
+		return 0;
+
+	ret = proxy_send(&call, &sysargs, sizeof(sysargs));
+	if (ret != sizeof(sysargs))
+		goto out;
+	proxy_recv(&call, &ret, sizeof(ret));
+
+out:
+	proxy_end(&call);
+
+	return ret;
+}
+
+int sys_spinlock_init(spinlock_t** lock)
+{
+	int ret;
+
+	if (BUILTIN_EXPECT(!lock, 0))
 		return -EINVAL;
 
 	*lock = (spinlock_t*) kmalloc(sizeof(spinlock_t));
@@ -506,10 +1061,8 @@ int sys_spinlock_unlock(spinlock_t* lock)
 
 void sys_msleep(unsigned int ms)
 {
-	if (ms * TIMER_FREQ / 1000 > 0)
-		timer_wait(ms * TIMER_FREQ / 1000);
-	else if (ms > 0)
-		udelay(ms * 1000);
+	if (ms > 0)
+		timer_wait_cycles(get_rdtsc() + ns_to_cycles(ms * 1000000ULL), 0);
 }
 
 int sys_sem_init(sem_t** sem, unsigned int value)
@@ -583,6 +1136,146 @@ int sys_clone(tid_t* id, void* ep, void* argv)
 	return clone_task(id, ep, argv, per_core(current_task)->prio);
 }
 
+int sys_clone_on_core(tid_t* id, void* ep, void* argv, unsigned int core_id)
+{
+	if (BUILTIN_EXPECT(core_id >= MAX_CORES, 0))
+		return -EINVAL;
+
+	return clone_task_on_core(id, ep, argv, per_core(current_task)->prio, core_id, 0);
+}
+
+int sys_clone_flags(tid_t* id, void* ep, void* argv, unsigned int flags)
+{
+	return clone_task_on_core(id, ep, argv, per_core(current_task)->prio, MAX_CORES, flags);
+}
+
+int sys_join(tid_t id, int* status)
+{
+	return join_task(id, status);
+}
+
+int sys_detach(tid_t id)
+{
+	return detach_task(id);
+}
+
+int sys_sched_setaffinity(tid_t id, size_t cpusetsize, const void* mask)
+{
+	cpu_set_t affinity;
+
+	if (BUILTIN_EXPECT(!mask, 0))
+		return -EFAULT;
+
+	// cores beyond the user's set are excluded
+	CPU_ZERO(&affinity);
+	memcpy(&affinity, mask, (cpusetsize < sizeof(cpu_set_t)) ? cpusetsize : sizeof(cpu_set_t));
+
+	return set_task_affinity(id, &affinity);
+}
+
+int sys_sched_getaffinity(tid_t id, size_t cpusetsize, void* mask)
+{
+	cpu_set_t affinity;
+	int ret;
+
+	if (BUILTIN_EXPECT(!mask, 0))
+		return -EFAULT;
+
+	// the set has to be able to describe all cores
+	if (BUILTIN_EXPECT(cpusetsize * 8 < MAX_CORES, 0))
+		return -EINVAL;
+
+	ret = get_task_affinity(id, &affinity);
+	if (ret)
+		return ret;
+
+	memset(mask, 0x00, cpusetsize);
+	memcpy(mask, &affinity, (cpusetsize < sizeof(cpu_set_t)) ? cpusetsize : sizeof(cpu_set_t));
+
+	return 0;
+}
+
+int sys_sched_setscheduler(tid_t id, int policy, const struct sched_param* param)
+{
+	int prio = 0;
+
+	// without parameters, the priority of the task is kept
+	if (param) {
+		prio = param->sched_priority;
+		if (BUILTIN_EXPECT((prio < 0) || (prio > MAX_PRIO), 0))
+			return -EINVAL;
+	}
+
+	return set_task_scheduler(id, policy, (uint8_t) prio);
+}
+
+int sys_sched_getscheduler(tid_t id)
+{
+	int policy, ret;
+
+	ret = get_task_scheduler(id, &policy, NULL);
+	if (ret)
+		return ret;
+
+	return policy;
+}
+
+int sys_sched_getparam(tid_t id, struct sched_param* param)
+{
+	uint8_t prio;
+	int ret;
+
+	if (BUILTIN_EXPECT(!param, 0))
+		return -EFAULT;
+
+	ret = get_task_scheduler(id, NULL, &prio);
+	if (ret)
+		return ret;
+
+	param->sched_priority = prio;
+
+	return 0;
+}
+
+int sys_sched_setattr(tid_t id, const struct sched_attr* attr, unsigned int flags)
+{
+	if (BUILTIN_EXPECT(!attr || flags, 0))
+		return -EINVAL;
+	if (BUILTIN_EXPECT(attr->size < sizeof(struct sched_attr), 0))
+		return -EINVAL;
+
+	if (attr->sched_policy == SCHED_DEADLINE)
+		return set_task_deadline(id, attr->sched_runtime, attr->sched_deadline, attr->sched_period);
+
+	if (BUILTIN_EXPECT(attr->sched_priority > MAX_PRIO, 0))
+		return -EINVAL;
+
+	return set_task_scheduler(id, attr->sched_policy, (uint8_t) attr->sched_priority);
+}
+
+int sys_sched_getattr(tid_t id, struct sched_attr* attr, unsigned int size, unsigned int flags)
+{
+	int policy, ret;
+	uint8_t prio;
+
+	if (BUILTIN_EXPECT(!attr || flags || (size < sizeof(struct sched_attr)), 0))
+		return -EINVAL;
+
+	ret = get_task_scheduler(id, &policy, &prio);
+	if (ret)
+		return ret;
+
+	memset(attr, 0x00, sizeof(struct sched_attr));
+	attr->size = sizeof(struct sched_attr);
+	attr->sched_policy = policy;
+	attr->sched_priority = prio;
+
+	if (policy == SCHED_DEADLINE)
+		ret = get_task_deadline(id, &attr->sched_runtime, &attr->sched_deadline, &attr->sched_period);
+
+	return ret;
+}
+
 typedef struct {
 	int sysnr;
 	int fd;
@@ -596,34 +1289,33 @@ typedef struct {
 	int whence;
 } __attribute__((packed)) uhyve_lseek_t;
 
-off_t sys_lseek(int fd, off_t offset, int whence)
+static off_t host_lseek(file_t* file, off_t offset, int whence)
 {
+	int fd = file->handle;
+
 	if (is_uhyve()) {
 		uhyve_lseek_t uhyve_lseek = { fd, offset, whence };
 
-		outportl(UHYVE_PORT_LSEEK, (unsigned)virt_to_phys((size_t) &uhyve_lseek));
+		uhyve_call(UHYVE_PORT_LSEEK, (unsigned)virt_to_phys((size_t) &uhyve_lseek));
 
 		return uhyve_lseek.offset;
 	}
 
 	off_t off;
 	sys_lseek_t sysargs = {__NR_lseek, fd, offset, whence};
-	int s;
-
-	spinlock_irqsave_lock(&lwip_lock);
+	proxy_call_t call;
+	int ret;
 
-	if (libc_sd < 0) {
-		spinlock_irqsave_unlock(&lwip_lock);
-		return -ENOSYS;
-	}
+	ret = proxy_begin(&call, sizeof(sysargs), 1);
+	if (ret)
+		return ret;
 
-	s = libc_sd;
-	socket_send(s, &sysargs, sizeof(sysargs));
-	socket_recv(s, &off, sizeof(off));
+	proxy_send(&call, &sysargs, sizeof(sysargs));
+	ret = proxy_recv(&call, &off, sizeof(off));
 
-	spinlock_irqsave_unlock(&lwip_lock);
+	proxy_end(&call);
 
-	return off;
+	return (ret < 0) ? ret : off;
 }
 
 int sys_rcce_init(int session_id)
@@ -789,9 +1481,1198 @@ size_t sys_get_ticks(void)
 	return get_clock_tick();
 }
 
-int sys_stat(const char* file, /*struct stat *st*/ void* st)
-{
-	return -ENOSYS;
+/*
+ * Fixed layout of struct stat, which is exchanged with uhyve and the proxy.
+ * All fields have an explicit width, the host fills in its values and
+ * the kernel translates them into newlib's layout. The file type bits of
+ * st_mode use the same encoding on Linux and newlib.
+ */
+typedef struct {
+	uint64_t st_dev;
+	uint64_t st_ino;
+	uint32_t st_mode;
+	uint32_t st_nlink;
+	uint32_t st_uid;
+	uint32_t st_gid;
+	uint64_t st_rdev;
+	int64_t  st_size;
+	int64_t  st_blksize;
+	int64_t  st_blocks;
+	int64_t  st_atime_sec;
+	int64_t  st_atime_nsec;
+	int64_t  st_mtime_sec;
+	int64_t  st_mtime_nsec;
+	int64_t  st_ctime_sec;
+	int64_t  st_ctime_nsec;
+} __attribute__((packed)) hermit_stat_t;
+
+#define HERMIT_STAT_FOLLOW	0
+#define HERMIT_STAT_NOFOLLOW	1
+
+typedef struct {
+	const char* name;
+	int fd;
+	int flags;
+	hermit_stat_t* st;
+	int ret;
+} __attribute__((packed)) uhyve_stat_t;
+
+typedef struct {
+	int sysnr;
+	int fd;
+} __attribute__((packed)) sys_fstat_t;
+
+static void stat_to_newlib(const hermit_stat_t* hst, struct stat* st)
+{
+	memset(st, 0x00, sizeof(struct stat));
+
+	st->st_dev = (int16_t) hst->st_dev;
+	st->st_ino = (uint16_t) hst->st_ino;
+	st->st_mode = hst->st_mode;
+	st->st_nlink = (uint16_t) hst->st_nlink;
+	st->st_uid = (uint16_t) hst->st_uid;
+	st->st_gid = (uint16_t) hst->st_gid;
+	st->st_rdev = (int16_t) hst->st_rdev;
+	st->st_size = hst->st_size;
+	st->st_atime = hst->st_atime_sec;
+	st->st_mtime = hst->st_mtime_sec;
+	st->st_ctime = hst->st_ctime_sec;
+	st->st_blksize = hst->st_blksize;
+	st->st_blocks = hst->st_blocks;
+}
+
+static int uhyve_stat(const char* name, int fd, int flags, struct stat* st)
+{
+	hermit_stat_t hst;
+	uhyve_stat_t uhyve_args = {
+		name ? (const char*) virt_to_phys((size_t) name) : NULL,
+		fd, flags,
+		(hermit_stat_t*) virt_to_phys((size_t) &hst), -EINVAL};
+
+	uhyve_call(UHYVE_PORT_STAT, (unsigned)virt_to_phys((size_t) &uhyve_args));
+
+	if (!uhyve_args.ret)
+		stat_to_newlib(&hst, st);
+
+	return uhyve_args.ret;
+}
+
+/*
+ * Message layout for the proxy:
+ *   stat/lstat: sysnr, length of the path, path
+ *   fstat:      sysnr, fd
+ * The proxy answers with the return value and, on success,
+ * with a hermit_stat_t.
+ */
+static int proxy_stat(int sysnr, const char* name, int fd, struct stat* st)
+{
+	hermit_stat_t hst;
+	proxy_call_t call;
+	int ret, err;
+
+	if (name)
+		ret = proxy_begin(&call, sizeof(sysnr) + proxy_path_len(name), 1);
+	else
+		ret = proxy_begin(&call, sizeof(sys_fstat_t), 1);
+	if (ret)
+		return ret;
+
+	if (name) {
+		ret = proxy_send(&call, &sysnr, sizeof(sysnr));
+		if (ret < 0)
+			goto out;
+
+		ret = proxy_send_path(&call, name);
+		if (ret < 0)
+			goto out;
+	} else {
+		sys_fstat_t sysargs = {sysnr, fd};
+
+		ret = proxy_send(&call, &sysargs, sizeof(sysargs));
+		if (ret < 0)
+			goto out;
+	}
+
+	err = proxy_recv(&call, &ret, sizeof(ret));
+	if (err < 0) {
+		ret = err;
+		goto out;
+	}
+
+	if (!ret) {
+		err = proxy_recv(&call, &hst, sizeof(hst));
+		if (err < 0) {
+			ret = err;
+			goto out;
+		}
+
+		stat_to_newlib(&hst, st);
+	}
+
+out:
+	proxy_end(&call);
+
+	return ret;
+}
+
+static int host_stat(void* fs, const char* file, struct stat* st, int follow)
+{
+	if (is_uhyve())
+		return uhyve_stat(file, -1, follow ? HERMIT_STAT_FOLLOW : HERMIT_STAT_NOFOLLOW, st);
+
+	return proxy_stat(follow ? __NR_stat : __NR_lstat, file, -1, st);
+}
+
+static int host_fstat(file_t* file, struct stat* st)
+{
+	int fd = file->handle;
+
+	if (is_uhyve())
+		return uhyve_stat(NULL, fd, HERMIT_STAT_FOLLOW, st);
+
+	// without a proxy, the standard streams are mapped to the console
+	if ((libc_sd < 0) && (fd >= 0) && (fd <= 2)) {
+		memset(st, 0x00, sizeof(struct stat));
+		st->st_mode = S_IFCHR|0620;
+		st->st_blksize = 1;
+
+		return 0;
+	}
+
+	return proxy_stat(__NR_fstat, NULL, fd, st);
+}
+
+static int host_poll(file_t* file, int events)
+{
+	// host files never block
+	return events & (POLLIN|POLLRDNORM|POLLOUT|POLLWRNORM);
+}
+
+static const file_ops_t host_ops = {
+	.read = host_read,
+	.write = host_write,
+	.readv = host_readv,
+	.writev = host_writev,
+	.lseek = host_lseek,
+	.fstat = host_fstat,
+	.poll = host_poll,
+	.close = host_close,
+};
+
+file_t* host_file_alloc(int host_fd, int flags)
+{
+	file_t* file = file_alloc(&host_ops, flags);
+
+	if (file)
+		file->handle = host_fd;
+
+	return file;
+}
+
+static int host_open(void* fs, const char* path, int flags, int mode, file_t* file)
+{
+	int ret = host_open_fd(path, flags, mode);
+
+	if (ret < 0)
+		return ret;
+
+	file->ops = &host_ops;
+	file->handle = ret;
+
+	return 0;
+}
+
+typedef struct {
+	const char* name;
+	int mode;
+	int ret;
+} __attribute__((packed)) uhyve_mkdir_t;
+
+typedef struct {
+	const char* name;
+	int ret;
+} __attribute__((packed)) uhyve_path_t;
+
+typedef struct {
+	const char* oldname;
+	const char* newname;
+	int ret;
+} __attribute__((packed)) uhyve_rename_t;
+
+typedef struct {
+	int fd;
+	struct dirent* entry;
+	int ret;
+} __attribute__((packed)) uhyve_readdir_t;
+
+typedef struct {
+	int fd;
+	int ret;
+} __attribute__((packed)) uhyve_closedir_t;
+
+typedef struct {
+	int sysnr;
+	int fd;
+} __attribute__((packed)) sys_dir_t;
+
+/*
+ * Message layout for the proxy:
+ *   sysnr, length of the path, path[, length of the 2nd path, 2nd path][, mode]
+ * The proxy answers with the return value or a negative error number.
+ */
+static int proxy_path(int sysnr, const char* name, const char* name2, const int* mode)
+{
+	proxy_call_t call;
+	size_t len;
+	int ret;
+
+	len = sizeof(sysnr) + proxy_path_len(name);
+	if (name2)
+		len += proxy_path_len(name2);
+	if (mode)
+		len += sizeof(*mode);
+
+	ret = proxy_begin(&call, len, 1);
+	if (ret)
+		return ret;
+
+	ret = proxy_send(&call, &sysnr, sizeof(sysnr));
+	if (ret < 0)
+		goto out;
+
+	ret = proxy_send_path(&call, name);
+	if (ret < 0)
+		goto out;
+
+	if (name2) {
+		ret = proxy_send_path(&call, name2);
+		if (ret < 0)
+			goto out;
+	}
+
+	if (mode) {
+		ret = proxy_send(&call, mode, sizeof(*mode));
+		if (ret < 0)
+			goto out;
+	}
+
+	proxy_recv(&call, &ret, sizeof(ret));
+
+out:
+	proxy_end(&call);
+
+	return ret;
+}
+
+static int uhyve_path(unsigned port, const char* name)
+{
+	uhyve_path_t uhyve_args = {(const char*) virt_to_phys((size_t) name), -EINVAL};
+
+	uhyve_call(port, (unsigned)virt_to_phys((size_t) &uhyve_args));
+
+	return uhyve_args.ret;
+}
+
+static int host_mkdir(void* fs, const char* path, int mode)
+{
+	if (is_uhyve()) {
+		uhyve_mkdir_t uhyve_args = {(const char*) virt_to_phys((size_t) path), mode, -EINVAL};
+
+		uhyve_call(UHYVE_PORT_MKDIR, (unsigned)virt_to_phys((size_t) &uhyve_args));
+
+		return uhyve_args.ret;
+	}
+
+	return proxy_path(__NR_mkdir, path, NULL, &mode);
+}
+
+static int host_rmdir(void* fs, const char* path)
+{
+	if (is_uhyve())
+		return uhyve_path(UHYVE_PORT_RMDIR, path);
+
+	return proxy_path(__NR_rmdir, path, NULL, NULL);
+}
+
+static int host_unlink(void* fs, const char* path)
+{
+	if (is_uhyve())
+		return uhyve_path(UHYVE_PORT_UNLINK, path);
+
+	return proxy_path(__NR_unlink, path, NULL, NULL);
+}
+
+static int host_rename(void* fs, const char* oldpath, const char* newpath)
+{
+	if (is_uhyve()) {
+		uhyve_rename_t uhyve_args = {
+			(const char*) virt_to_phys((size_t) oldpath),
+			(const char*) virt_to_phys((size_t) newpath), -EINVAL};
+
+		uhyve_call(UHYVE_PORT_RENAME, (unsigned)virt_to_phys((size_t) &uhyve_args));
+
+		return uhyve_args.ret;
+	}
+
+	return proxy_path(__NR_rename, oldpath, newpath, NULL);
+}
+
+static ssize_t host_dir_read(file_t* file, char* buf, size_t len)
+{
+	return -EISDIR;
+}
+
+static ssize_t host_dir_write(file_t* file, const char* buf, size_t len)
+{
+	return -EBADF;
+}
+
+/*
+ * The proxy answers with 1 and the entry, 0 at the end of the directory
+ * or with a negative error number.
+ */
+static int host_readdir(file_t* file, struct dirent* entry)
+{
+	sys_dir_t sysargs = {__NR_readdir, file->handle};
+	proxy_call_t call;
+	int ret;
+
+	if (is_uhyve()) {
+		uhyve_readdir_t uhyve_args = {file->handle,
+			(struct dirent*) virt_to_phys((size_t) entry), -EINVAL};
+
+		uhyve_call(UHYVE_PORT_READDIR, (unsigned)virt_to_phys((size_t) &uhyve_args));
+
+		return uhyve_args.ret;
+	}
+
+	ret = proxy_begin(&call, sizeof(sysargs), 1);
+	if (ret)
+		return ret;
+
+	ret = proxy_send(&call, &sysargs, sizeof(sysargs));
+	if (ret < 0)
+		goto out;
+
+	proxy_recv(&call, &ret, sizeof(ret));
+	if ((ret == 1) && (proxy_recv(&call, entry, sizeof(struct dirent)) < 0))
+		ret = -EIO;
+
+out:
+	proxy_end(&call);
+
+	// the host doesn't have to terminate the name
+	if (ret == 1)
+		entry->d_name[NAME_MAX] = '\0';
+
+	return ret;
+}
+
+static int host_closedir(file_t* file)
+{
+	sys_dir_t sysargs = {__NR_closedir, file->handle};
+	proxy_call_t call;
+	int ret;
+
+	if (is_uhyve()) {
+		uhyve_closedir_t uhyve_args = {file->handle, -EINVAL};
+
+		uhyve_call(UHYVE_PORT_CLOSEDIR, (unsigned)virt_to_phys((size_t) &uhyve_args));
+
+		return uhyve_args.ret;
+	}
+
+	if (proxy_begin(&call, sizeof(sysargs), 1))
+		return 0;
+
+	ret = proxy_send(&call, &sysargs, sizeof(sysargs));
+	if (ret < 0)
+		goto out;
+
+	proxy_recv(&call, &ret, sizeof(ret));
+
+out:
+	proxy_end(&call);
+
+	return ret;
+}
+
+static const file_ops_t host_dir_ops = {
+	.read = host_dir_read,
+	.write = host_dir_write,
+	.readdir = host_readdir,
+	.close = host_closedir,
+};
+
+static int host_opendir(void* fs, const char* path, file_t* file)
+{
+	int ret;
+
+	if (is_uhyve())
+		ret = uhyve_path(UHYVE_PORT_OPENDIR, path);
+	else
+		ret = proxy_path(__NR_opendir, path, NULL, NULL);
+
+	if (ret < 0)
+		return ret;
+
+	file->ops = &host_dir_ops;
+	file->handle = ret;
+
+	return 0;
+}
+
+const fs_ops_t host_fs_ops = {
+	.open = host_open,
+	.stat = host_stat,
+	.mkdir = host_mkdir,
+	.rmdir = host_rmdir,
+	.unlink = host_unlink,
+	.rename = host_rename,
+	.opendir = host_opendir,
+};
+
+static ssize_t socket_read(file_t* file, char* buf, size_t len)
+{
+	ssize_t ret;
+
+	spinlock_irqsave_lock(&lwip_lock);
+	ret = lwip_read(file->handle, buf, len);
+	spinlock_irqsave_unlock(&lwip_lock);
+	if (ret < 0)
+		return -errno;
+
+	return ret;
+}
+
+static ssize_t socket_write(file_t* file, const char* buf, size_t len)
+{
+	ssize_t ret;
+
+	spinlock_irqsave_lock(&lwip_lock);
+	ret = lwip_write(file->handle, buf, len);
+	spinlock_irqsave_unlock(&lwip_lock);
+	if (ret < 0)
+		return -errno;
+
+	return ret;
+}
+
+static ssize_t socket_readv(file_t* file, const struct iovec* iov, int iovcnt)
+{
+	ssize_t ret;
+
+	spinlock_irqsave_lock(&lwip_lock);
+	ret = lwip_readv(file->handle, iov, iovcnt);
+	spinlock_irqsave_unlock(&lwip_lock);
+	if (ret < 0)
+		return -errno;
+
+	return ret;
+}
+
+static ssize_t socket_writev(file_t* file, const struct iovec* iov, int iovcnt)
+{
+	ssize_t ret;
+
+	spinlock_irqsave_lock(&lwip_lock);
+	ret = lwip_writev(file->handle, iov, iovcnt);
+	spinlock_irqsave_unlock(&lwip_lock);
+	if (ret < 0)
+		return -errno;
+
+	return ret;
+}
+
+static off_t socket_lseek(file_t* file, off_t offset, int whence)
+{
+	return -ESPIPE;
+}
+
+static int socket_fstat(file_t* file, struct stat* st)
+{
+	memset(st, 0x00, sizeof(struct stat));
+	st->st_mode = S_IFSOCK|0777;
+
+	return 0;
+}
+
+static int socket_ioctl(file_t* file, unsigned long cmd, void* arg)
+{
+	int ret = lwip_ioctl(file->handle, (long) cmd, arg);
+
+	if (ret < 0)
+		return -errno;
+
+	return ret;
+}
+
+static const file_ops_t socket_ops = {
+	.read = socket_read,
+	.write = socket_write,
+	.readv = socket_readv,
+	.writev = socket_writev,
+	.lseek = socket_lseek,
+	.fstat = socket_fstat,
+	.ioctl = socket_ioctl,
+};
+
+/*
+ * LwIP manages its own descriptors. Each socket owns a static file object,
+ * which keeps one reference forever. Hence, releasing the object neither
+ * frees it nor closes the socket.
+ */
+static file_t socket_files[MEMP_NUM_NETCONN] = {
+	[0 ... MEMP_NUM_NETCONN-1] = {
+		.ops = &socket_ops,
+		.refs = ATOMIC_INIT(1),
+		.flags = O_RDWR
+	}
+};
+
+int socket_get_file(int fd, file_t** file)
+{
+	int s = (fd & ~LWIP_FD_BIT) - LWIP_SOCKET_OFFSET;
+	file_t* f;
+
+	if (BUILTIN_EXPECT((s < 0) || (s >= MEMP_NUM_NETCONN), 0))
+		return -EBADF;
+
+	f = &socket_files[s];
+	f->handle = s + LWIP_SOCKET_OFFSET;
+	atomic_int32_inc(&f->refs);
+	*file = f;
+
+	return 0;
+}
+
+int socket_close(int fd)
+{
+	int s = (fd & ~LWIP_FD_BIT) - LWIP_SOCKET_OFFSET;
+	int ret;
+
+	if (BUILTIN_EXPECT((s < 0) || (s >= MEMP_NUM_NETCONN), 0))
+		return -EBADF;
+
+	ret = lwip_close(fd & ~LWIP_FD_BIT);
+	if (ret < 0)
+		return -errno;
+
+	return 0;
+}
+
+ssize_t sys_read(int fd, char* buf, size_t len)
+{
+	file_t* file;
+	ssize_t ret;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	ret = file->ops->read(file, buf, len);
+	fd_put(file);
+
+	return ret;
+}
+
+ssize_t sys_write(int fd, const char* buf, size_t len)
+{
+	file_t* file;
+	ssize_t ret;
+
+	if (BUILTIN_EXPECT(!buf, 0))
+		return -EINVAL;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	ret = file->ops->write(file, buf, len);
+	fd_put(file);
+
+	return ret;
+}
+
+ssize_t sys_readv(int fd, const struct iovec *iov, int iovcnt)
+{
+	file_t* file;
+	ssize_t i, ret, len;
+
+	len = iov_length(iov, iovcnt);
+	if (BUILTIN_EXPECT(len < 0, 0))
+		return len;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	// an empty request only validates the descriptor
+	if (!iovcnt)
+		goto out;
+
+	if (file->ops->readv) {
+		ret = file->ops->readv(file, iov, iovcnt);
+		goto out;
+	}
+
+	// fill one buffer after the other, a short read ends the request
+	i = 0;
+	for(int k=0; k<iovcnt; k++)
+	{
+		if (!iov[k].iov_len)
+			continue;
+
+		ret = file->ops->read(file, iov[k].iov_base, iov[k].iov_len);
+		if (ret < 0)
+			break;
+
+		i += ret;
+		if (ret < iov[k].iov_len)
+			break;
+	}
+
+	if (i > 0)
+		ret = i;
+	else if (ret > 0)
+		ret = 0;
+
+out:
+	fd_put(file);
+
+	return ret;
+}
+
+ssize_t readv(int d, const struct iovec *iov, int iovcnt)
+{
+	return sys_readv(d, iov, iovcnt);
+}
+
+ssize_t sys_writev(int fd, const struct iovec *iov, int iovcnt)
+{
+	file_t* file;
+	ssize_t i, ret, len;
+
+	len = iov_length(iov, iovcnt);
+	if (BUILTIN_EXPECT(len < 0, 0))
+		return len;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	// an empty request only validates the descriptor
+	if (!iovcnt)
+		goto out;
+
+	if (file->ops->writev) {
+		ret = file->ops->writev(file, iov, iovcnt);
+		goto out;
+	}
+
+	/*
+	 * Write one buffer after the other. A partial write ends the request
+	 * and the number of written bytes is returned (POSIX semantics).
+	 */
+	i = 0;
+	for(int k=0; k<iovcnt; k++)
+	{
+		if (!iov[k].iov_len)
+			continue;
+
+		ret = file->ops->write(file, iov[k].iov_base, iov[k].iov_len);
+		if (ret < 0)
+			break;
+
+		i += ret;
+		if (ret < iov[k].iov_len)
+			break;
+	}
+
+	if (i > 0)
+		ret = i;
+	else if (ret > 0)
+		ret = 0;
+
+out:
+	fd_put(file);
+
+	return ret;
+}
+
+ssize_t writev(int fildes, const struct iovec *iov, int iovcnt)
+{
+	return sys_writev(fildes, iov, iovcnt);
+}
+
+int sys_open(const char* name, int flags, int mode)
+{
+	return vfs_open(name, flags, mode);
+}
+
+int sys_stat(const char* file, struct stat* st)
+{
+	if (BUILTIN_EXPECT(!file || !st, 0))
+		return -EINVAL;
+
+	return vfs_stat(file, st, 1);
+}
+
+int sys_lstat(const char* file, struct stat* st)
+{
+	if (BUILTIN_EXPECT(!file || !st, 0))
+		return -EINVAL;
+
+	return vfs_stat(file, st, 0);
+}
+
+int sys_opendir(const char* path)
+{
+	return vfs_opendir(path);
+}
+
+int sys_readdir(int fd, struct dirent* entry)
+{
+	file_t* file;
+	int ret;
+
+	if (BUILTIN_EXPECT(!entry, 0))
+		return -EINVAL;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	if (file->ops->readdir)
+		ret = file->ops->readdir(file, entry);
+	else
+		ret = -ENOTDIR;
+	fd_put(file);
+
+	return ret;
+}
+
+int sys_mkdir(const char* path, int mode)
+{
+	return vfs_mkdir(path, mode);
+}
+
+int sys_rmdir(const char* path)
+{
+	return vfs_rmdir(path);
+}
+
+int sys_unlink(const char* path)
+{
+	return vfs_unlink(path);
+}
+
+int sys_rename(const char* oldpath, const char* newpath)
+{
+	return vfs_rename(oldpath, newpath);
+}
+
+int sys_close(int fd)
+{
+	return fd_close(fd);
+}
+
+off_t sys_lseek(int fd, off_t offset, int whence)
+{
+	file_t* file;
+	off_t ret;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	if (file->ops->lseek)
+		ret = file->ops->lseek(file, offset, whence);
+	else
+		ret = -ESPIPE;
+	fd_put(file);
+
+	return ret;
+}
+
+int sys_fstat(int fd, struct stat* st)
+{
+	file_t* file;
+	int ret;
+
+	if (BUILTIN_EXPECT(!st, 0))
+		return -EINVAL;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	if (file->ops->fstat)
+		ret = file->ops->fstat(file, st);
+	else
+		ret = -ENOSYS;
+	fd_put(file);
+
+	return ret;
+}
+
+int sys_ftruncate(int fd, off_t length)
+{
+	file_t* file;
+	int ret;
+
+	if (BUILTIN_EXPECT(length < 0, 0))
+		return -EINVAL;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	if ((file->flags & O_ACCMODE) == O_RDONLY)
+		ret = -EBADF;
+	else if (file->ops->truncate)
+		ret = file->ops->truncate(file, length);
+	else
+		ret = -EINVAL;
+	fd_put(file);
+
+	return ret;
+}
+
+int sys_truncate(const char* path, off_t length)
+{
+	int fd, ret;
+
+	fd = sys_open(path, O_WRONLY, 0);
+	if (fd < 0)
+		return fd;
+
+	ret = sys_ftruncate(fd, length);
+	sys_close(fd);
+
+	return ret;
+}
+
+int sys_ioctl(int fd, unsigned long cmd, void* arg)
+{
+	file_t* file;
+	int ret;
+
+	ret = fd_get(fd, &file);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	if (file->ops->ioctl)
+		ret = file->ops->ioctl(file, cmd, arg);
+	else
+		ret = -ENOTTY;
+	fd_put(file);
+
+	return ret;
+}
+
+#define POLL_READ	(POLLIN|POLLRDNORM|POLLRDBAND|POLLPRI)
+#define POLL_WRITE	(POLLOUT|POLLWRNORM|POLLWRBAND)
+
+static inline int poll_is_socket(int fd)
+{
+	int s = (fd & ~LWIP_FD_BIT) - LWIP_SOCKET_OFFSET;
+
+	return (fd & LWIP_FD_BIT) && (s >= 0) && (s < MEMP_NUM_NETCONN);
+}
+
+/*
+ * Check all descriptors without blocking. Sockets are only collected
+ * in the sets of LwIP, all other files are asked by their poll operation.
+ * Files without a poll operation are always ready.
+ */
+static int poll_scan(struct pollfd* fds, nfds_t nfds, fd_set* rset, fd_set* wset, fd_set* eset, int* maxfd)
+{
+	file_t* file;
+	int ready = 0;
+
+	FD_ZERO(rset);
+	FD_ZERO(wset);
+	FD_ZERO(eset);
+	*maxfd = -1;
+
+	for(nfds_t i=0; i<nfds; i++) {
+		int fd = fds[i].fd;
+
+		fds[i].revents = 0;
+		if (fd < 0)
+			continue;
+
+		if (poll_is_socket(fd)) {
+			int s = fd & ~LWIP_FD_BIT;
+
+			if (fds[i].events & POLL_READ)
+				FD_SET(s, rset);
+			if (fds[i].events & POLL_WRITE)
+				FD_SET(s, wset);
+			FD_SET(s, eset);
+			if (s > *maxfd)
+				*maxfd = s;
+			continue;
+		}
+
+		if ((fd & LWIP_FD_BIT) || fd_get(fd, &file)) {
+			fds[i].revents = POLLNVAL;
+			ready++;
+			continue;
+		}
+
+		if (file->ops->poll)
+			fds[i].revents = file->ops->poll(file, fds[i].events);
+		else
+			fds[i].revents = fds[i].events & (POLLIN|POLLRDNORM|POLLOUT|POLLWRNORM);
+		fd_put(file);
+
+		if (fds[i].revents)
+			ready++;
+	}
+
+	return ready;
+}
+
+/*
+ * The readiness of the files doesn't change => sleep until the deadline
+ * or until a signal handler interrupts the call. The deadline is ignored,
+ * if it's zero.
+ */
+static int poll_sleep(uint64_t deadline, uint32_t intr_count)
+{
+	task_t* curr_task = per_core(current_task);
+	uint8_t flags;
+
+	while(!deadline || (get_rdtsc() < deadline)) {
+		// a signal IPI is handled after blocking the task and wakes it up again
+		flags = irq_nested_disable();
+
+		if (curr_task->signal_intr_count != intr_count) {
+			irq_nested_enable(flags);
+			return -EINTR;
+		}
+
+		curr_task->flags |= TASK_INTERRUPTIBLE;
+		if (deadline)
+			set_timer_cycles(deadline);
+		else
+			block_current_task();
+		irq_nested_enable(flags);
+
+		reschedule();
+
+		curr_task->flags &= ~TASK_INTERRUPTIBLE;
+	}
+
+	return 0;
+}
+
+/*
+ * Wait for the events of the descriptors. Without wait, the descriptors
+ * are only checked. The deadline (get_rdtsc()) is ignored, if it's zero.
+ */
+static int poll_until(struct pollfd* fds, nfds_t nfds, int wait, uint64_t deadline)
+{
+	const uint32_t intr_count = per_core(current_task)->signal_intr_count;
+	fd_set rset, wset, eset;
+	struct timeval tv, *ptv;
+	uint64_t now;
+	int ready, maxfd, ret;
+
+	ready = poll_scan(fds, nfds, &rset, &wset, &eset, &maxfd);
+
+	// only the sockets are able to become ready
+	if (maxfd < 0) {
+		if (ready || !wait)
+			return ready;
+
+		return poll_sleep(deadline, intr_count);
+	}
+
+	ptv = &tv;
+	tv.tv_sec = tv.tv_usec = 0;
+
+	if (!ready && wait && !deadline) {
+		ptv = NULL;
+	} else if (!ready && wait) {
+		now = get_rdtsc();
+		if (deadline > now) {
+			// round up to wait at least until the deadline
+			const uint64_t us = (cycles_to_ns(deadline - now) + NSEC_PER_USEC - 1) / NSEC_PER_USEC;
+
+			tv.tv_sec = us / 1000000;
+			tv.tv_usec = us % 1000000;
+		}
+	}
+
+	/*
+	 * lwip_select is thread-safe and blocks on a semaphore, which is
+	 * signaled by the sockets. The readiness of the other files doesn't
+	 * change, so waiting on the sockets doesn't miss an event of a file.
+	 */
+	ret = lwip_select(maxfd+1, &rset, &wset, &eset, ptv);
+	if (ret < 0)
+		return -errno;
+
+	for(nfds_t i=0; i<nfds; i++) {
+		int s = fds[i].fd & ~LWIP_FD_BIT;
+
+		if ((fds[i].fd < 0) || !poll_is_socket(fds[i].fd))
+			continue;
+
+		if (FD_ISSET(s, &rset))
+			fds[i].revents |= fds[i].events & POLL_READ;
+		if (FD_ISSET(s, &wset))
+			fds[i].revents |= fds[i].events & POLL_WRITE;
+		if (FD_ISSET(s, &eset))
+			fds[i].revents |= POLLERR;
+
+		if (fds[i].revents)
+			ready++;
+	}
+
+	return ready;
+}
+
+int sys_poll(struct pollfd* fds, nfds_t nfds, int timeout)
+{
+	uint64_t deadline = 0;
+
+	if (BUILTIN_EXPECT(nfds && !fds, 0))
+		return -EFAULT;
+	if (BUILTIN_EXPECT(nfds > MAX_FILES + MEMP_NUM_NETCONN, 0))
+		return -EINVAL;
+
+	if (timeout > 0)
+		deadline = get_rdtsc() + ns_to_cycles((uint64_t) timeout * (NSEC_PER_SEC / 1000));
+
+	return poll_until(fds, nfds, timeout != 0, deadline);
+}
+
+int poll(struct pollfd* fds, nfds_t nfds, int timeout)
+{
+	return sys_poll(fds, nfds, timeout);
+}
+
+/* the sets are accessed bytewise, because the size of fd_set differs between newlib and LwIP */
+static inline int select_isset(const unsigned long* set, int i)
+{
+	return set && (((const uint8_t*) set)[i / 8] & (1 << (i % 8)));
+}
+
+static inline void select_set(unsigned long* set, int i)
+{
+	if (set)
+		((uint8_t*) set)[i / 8] |= 1 << (i % 8);
+}
+
+/* the FD_SET macros of LwIP strip LWIP_FD_BIT => a bit without open file refers to a socket */
+static int select_fd(int i)
+{
+	file_t* file;
+
+	if ((i < MAX_FILES) && !fd_get(i, &file)) {
+		fd_put(file);
+		return i;
+	}
+
+	if (poll_is_socket(i | LWIP_FD_BIT))
+		return i | LWIP_FD_BIT;
+
+	// poll_scan() reports an invalid descriptor
+	return i;
+}
+
+int sys_select(int nfds, unsigned long* readfds, unsigned long* writefds, unsigned long* exceptfds, struct timeval* timeout)
+{
+	struct pollfd* fds;
+	uint64_t deadline = 0;
+	int i, n = 0, ret, wait = 1;
+	size_t bytes;
+
+	if (BUILTIN_EXPECT((nfds < 0) || (nfds > MAX_FILES + LWIP_SOCKET_OFFSET + MEMP_NUM_NETCONN), 0))
+		return -EINVAL;
+
+	if (timeout) {
+		if (BUILTIN_EXPECT((timeout->tv_sec < 0) || (timeout->tv_usec < 0), 0))
+			return -EINVAL;
+
+		wait = timeout->tv_sec || timeout->tv_usec;
+		if (wait)
+			deadline = get_rdtsc() + ns_to_cycles((uint64_t) timeout->tv_sec * NSEC_PER_SEC
+			           + (uint64_t) timeout->tv_usec * NSEC_PER_USEC);
+	}
+
+	fds = kmalloc(nfds ? nfds * sizeof(struct pollfd) : sizeof(struct pollfd));
+	if (BUILTIN_EXPECT(!fds, 0))
+		return -ENOMEM;
+
+	for(i=0; i<nfds; i++) {
+		short events = 0;
+
+		if (select_isset(readfds, i))
+			events |= POLLIN;
+		if (select_isset(writefds, i))
+			events |= POLLOUT;
+		if (select_isset(exceptfds, i))
+			events |= POLLPRI;
+		if (!events)
+			continue;
+
+		fds[n].fd = select_fd(i);
+		fds[n].events = events;
+		fds[n].revents = 0;
+		n++;
+	}
+
+	ret = poll_until(fds, n, wait, deadline);
+	if (ret < 0)
+		goto out;
+
+	bytes = (nfds + 7) / 8;
+	if (readfds)
+		memset(readfds, 0x00, bytes);
+	if (writefds)
+		memset(writefds, 0x00, bytes);
+	if (exceptfds)
+		memset(exceptfds, 0x00, bytes);
+
+	ret = 0;
+	for(i=0; i<n; i++) {
+		int bit = fds[i].fd & ~LWIP_FD_BIT;
+		short revents = fds[i].revents;
+
+		if (revents & POLLNVAL) {
+			ret = -EBADF;
+			goto out;
+		}
+
+		if ((fds[i].events & POLLIN) && (revents & (POLLIN|POLLHUP|POLLERR))) {
+			select_set(readfds, bit);
+			ret++;
+		}
+		if ((fds[i].events & POLLOUT) && (revents & (POLLOUT|POLLERR))) {
+			select_set(writefds, bit);
+			ret++;
+		}
+		if ((fds[i].events & POLLPRI) && (revents & POLLPRI)) {
+			select_set(exceptfds, bit);
+			ret++;
+		}
+	}
+
+out:
+	kfree(fds);
+
+	return ret;
+}
+
+int select(int nfds, fd_set* readfds, fd_set* writefds, fd_set* exceptfds, struct timeval* timeout)
+{
+	return sys_select(nfds, (unsigned long*) readfds, (unsigned long*) writefds,
+	                  (unsigned long*) exceptfds, timeout);
 }
 
 void sys_yield(void)
@@ -801,7 +2682,7 @@ void sys_yield(void)
 #else
 	if (BUILTIN_EXPECT(go_down, 0))
 		shutdown_system();
-	check_scheduling();
+	yield_task();
 #endif
 }
 
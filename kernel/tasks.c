@@ -37,7 +37,10 @@
 #include <hermit/syscall.h>
 #include <hermit/memory.h>
 #include <hermit/logging.h>
+#include <hermit/vfs.h>
+#include <hermit/signal.h>
 #include <asm/processor.h>
+#include <sys/time.h>
 
 /*
  * Note that linker symbols are not variables, they have no memory allocated for
@@ -52,16 +55,19 @@ volatile uint32_t go_down = 0;
  * A task's id will be its position in this array.
  */
 static task_t task_table[MAX_TASKS] = { \
-        [0]                 = {0, TASK_IDLE, 0, NULL, NULL, NULL, TASK_DEFAULT_FLAGS, 0, 0, 0, 0, NULL, 0, NULL, NULL, 0, 0, 0, NULL, FPU_STATE_INIT}, \
-        [1 ... MAX_TASKS-1] = {0, TASK_INVALID, 0, NULL, NULL, NULL, TASK_DEFAULT_FLAGS, 0, 0, 0, 0, NULL, 0, NULL, NULL, 0, 0, 0, NULL, FPU_STATE_INIT}};
+        [0]                 = {0, TASK_IDLE, 0, NULL, NULL, NULL, TASK_DEFAULT_FLAGS, 0, 0, 0, 0, 0, NULL, NULL, 0, NULL, NULL, 0, 0, 0, 0, 0, FPU_STATE_INIT}, \
+        [1 ... MAX_TASKS-1] = {0, TASK_INVALID, 0, NULL, NULL, NULL, TASK_DEFAULT_FLAGS, 0, 0, 0, 0, 0, NULL, NULL, 0, NULL, NULL, 0, 0, 0, 0, 0, FPU_STATE_INIT}};
 
 static spinlock_irqsave_t table_lock = SPINLOCK_IRQSAVE_INIT;
 
+/// consumed CPU cycles of all terminated tasks
+static atomic_int64_t exited_cycles = ATOMIC_INIT(0);
+
 #if MAX_CORES > 1
 static readyqueues_t readyqueues[MAX_CORES] = { \
-		[0 ... MAX_CORES-1]   = {NULL, NULL, 0, 0, 0, {[0 ... MAX_PRIO-2] = {NULL, NULL}}, {NULL, NULL}, SPINLOCK_IRQSAVE_INIT}};
+		[0 ... MAX_CORES-1]   = {NULL, NULL, 0, 0, 0, {[0 ... MAX_PRIO-2] = {NULL, NULL}}, {NULL, NULL}, NULL, 0, {NULL, NULL}, 0, SPINLOCK_IRQSAVE_INIT}};
 #else
-static readyqueues_t readyqueues[1] = {[0] = {task_table+0, NULL, 0, 0, 0, {[0 ... MAX_PRIO-2] = {NULL, NULL}}, {NULL, NULL}, SPINLOCK_IRQSAVE_INIT}};
+static readyqueues_t readyqueues[1] = {[0] = {task_table+0, NULL, 0, 0, 0, {[0 ... MAX_PRIO-2] = {NULL, NULL}}, {NULL, NULL}, NULL, 0, {NULL, NULL}, 0, SPINLOCK_IRQSAVE_INIT}};
 #endif
 
 DEFINE_PER_CORE(task_t*, current_task, task_table+0);
@@ -70,14 +76,118 @@ DEFINE_PER_CORE(task_t*, current_task, task_table+0);
 DEFINE_PER_CORE(uint32_t, __core_id, 0);
 #endif
 
-static void update_timer(task_t* first)
+/* length of a time slice in cycles */
+static inline uint64_t quantum_cycles(void)
+{
+	return ns_to_cycles(SCHED_RR_QUANTUM * 1000ULL);
+}
+
+/* tasks with the same priority share the core by time slices */
+static inline int time_sliced(task_t* task)
+{
+	return (task->status != TASK_IDLE) && ((task->policy == SCHED_OTHER) || (task->policy == SCHED_RR));
+}
+
+/// fixed point shift of the utilization of deadline tasks
+#define DL_BW_SHIFT	20
+/// the deadline tasks may use at most 95% of a core
+#define DL_BW_LIMIT	((95ULL << DL_BW_SHIFT) / 100)
+
+/* utilization of a deadline task */
+static inline uint64_t dl_bandwidth(uint64_t runtime, uint64_t period)
+{
+	return (runtime << DL_BW_SHIFT) / period;
+}
+
+/* charge the consumed cycles to the budget of a deadline task */
+static inline void dl_charge(task_t* task, uint64_t now)
+{
+	if (now > task->dl_tsc)
+		task->dl_budget -= (int64_t) (now - task->dl_tsc);
+	task->dl_tsc = now;
+}
+
+/* start a new job of a deadline task */
+static inline void dl_replenish(task_t* task, uint64_t now)
+{
+	task->dl_abs_deadline = now + task->dl_deadline;
+	task->dl_budget = (int64_t) task->dl_runtime;
+	task->dl_tsc = now;
+}
+
+/* counter value, at which a CPU time timer of the current task expires, or 0 */
+static uint64_t cpu_timers_deadline(task_t* task)
+{
+	uint64_t deadline = 0;
+	uint64_t cycles, now;
+	uint32_t i;
+
+	if (!task->itimers[ITIMER_VIRTUAL].expires && !task->itimers[ITIMER_PROF].expires)
+		return 0;
+
+	// the task consumes at most the elapsed cycles
+	cycles = get_task_cycles();
+	now = get_rdtsc();
+	for(i=ITIMER_VIRTUAL; i<MAX_ITIMERS; i++) {
+		const uint64_t expires = task->itimers[i].expires;
+		uint64_t tsc;
+
+		if (!expires)
+			continue;
+
+		tsc = (expires > cycles) ? now + (expires - cycles) : now;
+		if (!deadline || (tsc < deadline))
+			deadline = tsc;
+	}
+
+	return deadline;
+}
+
+static void update_timer(uint32_t core_id)
 {
-	if (first) {
-		if(first->timeout > get_clock_tick()) {
-			timer_deadline((uint32_t) (first->timeout - get_clock_tick()));
+	readyqueues_t* readyqueue = &readyqueues[core_id];
+	uint64_t deadline = 0;
+
+	if (readyqueue->timers.first)
+		deadline = readyqueue->timers.first->timeout;
+
+	if (readyqueue->itimers && (!deadline || (readyqueue->itimers->expires < deadline)))
+		deadline = readyqueue->itimers->expires;
+
+	// the CPU time timers and the time slice depend on the current task of this core
+	if (core_id == CORE_ID) {
+		task_t* curr_task = per_core(current_task);
+		const uint64_t cpu_deadline = cpu_timers_deadline(curr_task);
+
+		if (cpu_deadline && (!deadline || (cpu_deadline < deadline)))
+			deadline = cpu_deadline;
+
+		if (time_sliced(curr_task)) {
+			const uint64_t now = get_rdtsc();
+			// an expired time slice is renewed by the scheduler
+			const uint64_t slice_end = (curr_task->slice_end > now) ? curr_task->slice_end : now + quantum_cycles();
+
+			if (!deadline || (slice_end < deadline))
+				deadline = slice_end;
+		}
+
+		// the scheduler throttles a deadline task with an exhausted budget
+		if ((curr_task->policy == SCHED_DEADLINE) && (curr_task->status == TASK_RUNNING)) {
+			const uint64_t budget_end = (curr_task->dl_budget > 0) ? curr_task->dl_tsc + curr_task->dl_budget : get_rdtsc();
+
+			if (!deadline || (budget_end < deadline))
+				deadline = budget_end;
+		}
+	}
+
+	if (deadline) {
+		const uint64_t now = get_rdtsc();
+
+		if(deadline > now) {
+			timer_deadline_cycles(deadline - now);
 		} else {
 			// workaround: start timer so new head will be serviced
-			timer_deadline(1);
+			timer_deadline_cycles(1);
 		}
 	} else {
 		// prevent spurious interrupts
@@ -94,15 +204,16 @@ static void timer_queue_remove(uint32_t core_id, task_t* task)
 
 	task_list_t* timer_queue = &readyqueues[core_id].timers;
 
+	const int first = (timer_queue->first == task);
+
+	task_list_remove_task(timer_queue, task);
+
 #ifdef DYNAMIC_TICKS
 	// if task is first in timer queue, we need to update the oneshot
 	// timer for the next task
-	if(timer_queue->first == task) {
-		update_timer(task->next);
-	}
+	if (first)
+		update_timer(core_id);
 #endif
-
-	task_list_remove_task(timer_queue, task);
 }
 
 
@@ -119,7 +230,7 @@ static void timer_queue_push(uint32_t core_id, task_t* task)
 		task->next = task->prev = NULL;
 
 #ifdef DYNAMIC_TICKS
-		update_timer(task);
+		update_timer(core_id);
 #endif
 	} else {
 		// lookup position where to insert task
@@ -147,7 +258,7 @@ static void timer_queue_push(uint32_t core_id, task_t* task)
 				timer_queue->first = task;
 
 #ifdef DYNAMIC_TICKS
-				update_timer(task);
+				update_timer(core_id);
 #endif
 			}
 		}
@@ -157,8 +268,120 @@ static void timer_queue_push(uint32_t core_id, task_t* task)
 }
 
 
+/*
+ * The budget of a deadline task is exhausted => block the task until its next period.
+ * The caller has to hold the lock of the ready queue.
+ */
+static void dl_throttle(uint32_t core_id, task_t* task)
+{
+	LOG_DEBUG("throttle deadline task %d on core %d\n", task->id, core_id);
+
+	task->status = TASK_BLOCKED;
+	task->flags |= TASK_TIMER|TASK_DL_THROTTLED;
+	// the next period starts at the current deadline - relative deadline + period
+	task->timeout = task->dl_abs_deadline - task->dl_deadline + task->dl_period;
+
+	timer_queue_push(core_id, task);
+}
+
+
+/*
+ * A deadline task becomes ready => check, if the current job can be continued.
+ * Returns 0, if the task is throttled until its next period.
+ * The caller has to hold the lock of the ready queue.
+ */
+static int dl_wakeup(uint32_t core_id, task_t* task)
+{
+	const uint64_t now = get_rdtsc();
+
+	task->flags &= ~TASK_DL_THROTTLED;
+
+	// the budget of the current period is already consumed
+	if ((task->dl_budget <= 0) && (now < task->dl_abs_deadline - task->dl_deadline + task->dl_period)) {
+		dl_throttle(core_id, task);
+		return 0;
+	}
+
+	// the deadline is missed or the remaining budget exceeds the
+	// utilization of the task until the deadline => start a new job
+	if ((now >= task->dl_abs_deadline) || (task->dl_budget <= 0)
+	    || (dl_bandwidth(task->dl_budget, task->dl_abs_deadline - now) > dl_bandwidth(task->dl_runtime, task->dl_period)))
+		dl_replenish(task, now);
+
+	return 1;
+}
+
+
+/* The caller has to hold the lock of the ready queue */
+static void itimer_queue_push(readyqueues_t* readyqueue, itimer_t* timer)
+{
+	itimer_t* prev = NULL;
+	itimer_t* tmp = readyqueue->itimers;
+
+	// lookup position where to insert the timer
+	while(tmp && (timer->expires >= tmp->expires)) {
+		prev = tmp;
+		tmp = tmp->next;
+	}
+
+	timer->prev = prev;
+	timer->next = tmp;
+	if (tmp)
+		tmp->prev = timer;
+	if (prev)
+		prev->next = timer;
+	else
+		readyqueue->itimers = timer;
+}
+
+
+/* The caller has to hold the lock of the ready queue */
+static void itimer_queue_remove(readyqueues_t* readyqueue, itimer_t* timer)
+{
+	if (timer->prev)
+		timer->prev->next = timer->next;
+	else
+		readyqueue->itimers = timer->next;
+
+	if (timer->next)
+		timer->next->prev = timer->prev;
+
+	timer->next = timer->prev = NULL;
+}
+
+
+/* insert a deadline task in front of all tasks with a later deadline */
+static void dl_queue_push(task_list_t* list, task_t* task)
+{
+	task_t* tmp = list->first;
+
+	while(tmp && (tmp->dl_abs_deadline <= task->dl_abs_deadline))
+		tmp = tmp->next;
+
+	if (!tmp) {
+		task_list_push_back(list, task);
+		return;
+	}
+
+	task->next = tmp;
+	task->prev = tmp->prev;
+	tmp->prev = task;
+
+	if (task->prev)
+		task->prev->next = task;
+	else
+		list->first = task;
+}
+
+
 static inline void readyqueues_push_back(uint32_t core_id, task_t* task)
 {
+	// deadline tasks don't use the priority queues
+	if (task->policy == SCHED_DEADLINE) {
+		dl_queue_push(&readyqueues[core_id].dl_queue, task);
+		return;
+	}
+
 	// idle task (prio=0) doesn't have a queue
 	task_list_t* readyqueue = &readyqueues[core_id].queue[task->prio - 1];
 
@@ -171,6 +394,11 @@ static inline void readyqueues_push_back(uint32_t core_id, task_t* task)
 
 static inline void readyqueues_remove(uint32_t core_id, task_t* task)
 {
+	if (task->policy == SCHED_DEADLINE) {
+		task_list_remove_task(&readyqueues[core_id].dl_queue, task);
+		return;
+	}
+
 	// idle task (prio=0) doesn't have a queue
 	task_list_t* readyqueue = &readyqueues[core_id].queue[task->prio - 1];
 
@@ -182,6 +410,12 @@ static inline void readyqueues_remove(uint32_t core_id, task_t* task)
 }
 
 
+static inline int core_allowed(task_t* task, uint32_t core_id)
+{
+	return CPU_ISSET(core_id, &task->affinity);
+}
+
+
 void fpu_handler(void)
 {
 	task_t* task = per_core(current_task);
@@ -210,36 +444,238 @@ void fpu_handler(void)
 	restore_fpu_state(&task->fpu);
 }
 
+void task_fpu_save(task_t* task, union fpu_state* state)
+{
+	uint8_t flags = irq_nested_disable();
+
+#ifdef SAVE_FPU
+	if (readyqueues[CORE_ID].fpu_owner == task->id) {
+		// the registers of this core hold the current state
+		flush_fpu_state(state);
+	} else if (task->flags & TASK_FPU_INIT) {
+		memcpy(state, &task->fpu, sizeof(union fpu_state));
+	} else {
+		// the task didn't use the FPU yet
+		fpu_init(state);
+	}
+#else
+	flush_fpu_state(state);
+#endif
+
+	irq_nested_enable(flags);
+}
+
+void task_fpu_restore(task_t* task, union fpu_state* state)
+{
+	uint8_t flags = irq_nested_disable();
+
+#ifdef SAVE_FPU
+	memcpy(&task->fpu, state, sizeof(union fpu_state));
+	task->flags |= TASK_FPU_INIT;
+
+	// otherwise, the next FPU access loads the state
+	if (readyqueues[CORE_ID].fpu_owner == task->id)
+		load_fpu_state(&task->fpu);
+#else
+	load_fpu_state(state);
+#endif
+
+	irq_nested_enable(flags);
+}
+
+/// tasks, which ran within this time, are cache hot and aren't migrated
+#define MIGRATION_COST_NS	500000ULL
+/// minimal time between two periodic load balancings of a core
+#define BALANCE_INTERVAL_NS	4000000ULL
+
+/* lock the ready queues of two cores in a fixed order to avoid deadlocks */
+static inline void readyqueues_lock_pair(uint32_t core1, uint32_t core2)
+{
+	if (core1 < core2) {
+		spinlock_irqsave_lock(&readyqueues[core1].lock);
+		spinlock_irqsave_lock(&readyqueues[core2].lock);
+	} else {
+		spinlock_irqsave_lock(&readyqueues[core2].lock);
+		spinlock_irqsave_lock(&readyqueues[core1].lock);
+	}
+}
+
+static inline void readyqueues_unlock_pair(uint32_t core1, uint32_t core2)
+{
+	spinlock_irqsave_unlock(&readyqueues[core1].lock);
+	spinlock_irqsave_unlock(&readyqueues[core2].lock);
+}
+
+/*
+ * Move a ready task with the highest priority from core src to core dst.
+ * The caller has to hold the locks of both ready queues.
+ */
+static task_t* steal_task(uint32_t src, uint32_t dst)
+{
+	readyqueues_t* queue = &readyqueues[src];
+	const uint64_t now = get_rdtsc();
+	const uint64_t cost = ns_to_cycles(MIGRATION_COST_NS);
+	uint32_t prio;
+	task_t* task;
+
+	for(prio=MAX_PRIO-1; prio>IDLE_PRIO; prio--) {
+		if (!(queue->prio_bitmap & (1 << prio)))
+			continue;
+
+		for(task=queue->queue[prio-1].first; task; task=task->next) {
+			if (!core_allowed(task, dst))
+				continue;
+
+			// the FPU of the source core holds the state of the task
+			if (queue->fpu_owner == task->id)
+				continue;
+
+			// the cache of the source core is still hot
+			if (task->last_tsc && (now - task->last_tsc < cost))
+				continue;
+
+			goto found;
+		}
+	}
+
+	return NULL;
+
+found:
+	readyqueues_remove(src, task);
+	queue->nr_tasks--;
+
+	task->last_core = dst;
+
+	readyqueues_push_back(dst, task);
+	readyqueues[dst].nr_tasks++;
+
+	LOG_DEBUG("migrate task %d from core %d to core %d\n", task->id, src, dst);
+
+	return task;
+}
+
+/* an idle core steals a task from the busiest core */
+static int balance_idle(uint32_t core_id)
+{
+	uint32_t i, busiest = MAX_CORES;
+	uint32_t max_tasks = 1;
+	task_t* task = NULL;
+
+	// the numbers of tasks are only read to find a candidate
+	for(i=0; i<MAX_CORES; i++) {
+		if ((i != core_id) && readyqueues[i].idle && (readyqueues[i].nr_tasks > max_tasks)) {
+			max_tasks = readyqueues[i].nr_tasks;
+			busiest = i;
+		}
+	}
+
+	if (busiest >= MAX_CORES)
+		return 0;
+
+	readyqueues_lock_pair(core_id, busiest);
+	if (!readyqueues[core_id].nr_tasks && (readyqueues[busiest].nr_tasks > 1))
+		task = steal_task(busiest, core_id);
+	readyqueues_unlock_pair(core_id, busiest);
+
+	return task != NULL;
+}
+
+void check_balance(void)
+{
+	const uint32_t core_id = CORE_ID;
+	readyqueues_t* readyqueue = &readyqueues[core_id];
+	const uint64_t now = get_rdtsc();
+	uint32_t i, idlest = MAX_CORES;
+	uint32_t min_tasks, nr_tasks;
+	task_t* task = NULL;
+
+	if (now - readyqueue->balance_tsc < ns_to_cycles(BALANCE_INTERVAL_NS))
+		return;
+	readyqueue->balance_tsc = now;
+
+	// at least one task is waiting => push it to a less loaded core
+	if (readyqueue->nr_tasks < 2)
+		return;
+
+	min_tasks = readyqueue->nr_tasks - 1;
+	for(i=0; i<MAX_CORES; i++) {
+		if ((i != core_id) && readyqueues[i].idle && (readyqueues[i].nr_tasks < min_tasks)) {
+			min_tasks = readyqueues[i].nr_tasks;
+			idlest = i;
+		}
+	}
+
+	if (idlest >= MAX_CORES)
+		return;
+
+	readyqueues_lock_pair(core_id, idlest);
+	if (readyqueues[idlest].nr_tasks + 1 < readyqueue->nr_tasks)
+		task = steal_task(core_id, idlest);
+	nr_tasks = readyqueues[idlest].nr_tasks;
+	readyqueues_unlock_pair(core_id, idlest);
+
+	if (task && (nr_tasks == 1))
+		wakeup_core(idlest);
+}
+
 int is_task_available(void)
 {
 	uint32_t core_id = CORE_ID;
 
+	// nothing to do => steal work from another core
+	if (!readyqueues[core_id].nr_tasks)
+		balance_idle(core_id);
+
 	return readyqueues[core_id].nr_tasks > 0 ? 1 : 0;
 }
 
+int need_reschedule(void)
+{
+	task_t* curr_task = per_core(current_task);
+	task_t* dl_task = readyqueues[CORE_ID].dl_queue.first;
+
+	// deadline tasks precede the priority queues => earliest deadline first
+	if ((curr_task->policy == SCHED_DEADLINE) && (curr_task->status == TASK_RUNNING))
+		return dl_task && (dl_task->dl_abs_deadline < curr_task->dl_abs_deadline);
+	if (dl_task)
+		return 1;
+
+	return get_highest_priority() > curr_task->prio;
+}
+
 void check_scheduling(void)
 {
-	uint32_t prio = get_highest_priority();
 	task_t* curr_task = per_core(current_task);
 
-	if (prio > curr_task->prio) {
+	if (need_reschedule()) {
 		reschedule();
+	} else if ((curr_task->policy == SCHED_DEADLINE) && (curr_task->status == TASK_RUNNING)) {
+		// the budget of the current job is exhausted => the scheduler throttles the task
+		if ((int64_t) (get_rdtsc() - curr_task->dl_tsc) >= curr_task->dl_budget)
+			reschedule();
 #ifdef DYNAMIC_TICKS
-	} else if ((prio > 0) && (prio == curr_task->prio)) {
-		// if a task is ready, check if the current task runs already one tick (one time slice)
+	} else if ((get_highest_priority() == curr_task->prio) && time_sliced(curr_task)) {
+		// if a task is ready, check if the time slice of the current task is expired
 		// => reschedule to realize round robin
-
-		const uint64_t diff_cycles = get_rdtsc() - curr_task->last_tsc;
-		const uint64_t cpu_freq_hz = 1000000ULL * (uint64_t) get_cpu_frequency();
-
-		if (((diff_cycles * (uint64_t) TIMER_FREQ) / cpu_freq_hz) > 0) {
-			LOG_DEBUG("Time slice expired for task %d on core %d. New task has priority %u.\n", curr_task->id, CORE_ID, prio);
+		if (get_rdtsc() >= curr_task->slice_end) {
+			LOG_DEBUG("Time slice expired for task %d on core %d. New task has priority %u.\n", curr_task->id, CORE_ID, (uint32_t) curr_task->prio);
 			reschedule();
 		}
 #endif
 	}
 }
 
+void yield_task(void)
+{
+	task_t* curr_task = per_core(current_task);
+	uint8_t flags = irq_nested_disable();
+
+	// the scheduler switches to a ready task with the same priority
+	curr_task->flags |= TASK_YIELD;
+	irq_nested_enable(flags);
+
+	reschedule();
+}
 
 uint32_t get_highest_priority(void)
 {
@@ -305,6 +741,7 @@ tid_t set_idle_task(void)
 			task_table[i].ist_addr = NULL;
 			task_table[i].prio = IDLE_PRIO;
 			task_table[i].heap = NULL;
+			task_table[i].fd_table = NULL;
 			readyqueues[core_id].idle = task_table+i;
 			set_per_core(current_task, readyqueues[core_id].idle);
 
@@ -317,6 +754,78 @@ tid_t set_idle_task(void)
 	return id;
 }
 
+static uint32_t get_next_core_id(const cpu_set_t* affinity)
+{
+	uint32_t i, next, min_tasks = ~0U;
+	static uint32_t core_id = MAX_CORES;
+
+	if (core_id >= MAX_CORES)
+		core_id = CORE_ID;
+
+	// search the least loaded core, starting behind the last choice
+	// => round robin for OpenMP applications with one thread per core
+	// => the load balancer corrects the placement later on
+	for(i=0, next=(core_id+1)%MAX_CORES; i<MAX_CORES; i++, next=(next+1)%MAX_CORES) {
+		if (readyqueues[next].idle && CPU_ISSET(next, affinity)
+		    && (readyqueues[next].nr_tasks < min_tasks)) {
+			min_tasks = readyqueues[next].nr_tasks;
+			core_id = next;
+		}
+	}
+
+	if (BUILTIN_EXPECT(!readyqueues[core_id].idle || !CPU_ISSET(core_id, affinity), 0)) {
+		LOG_ERROR("BUG: no core available!\n");
+		return MAX_CORES;
+	}
+
+	return core_id;
+}
+
+/* enqueue a ready task, which isn't part of a ready queue, on an allowed core */
+static void migrate_task(task_t* task)
+{
+	const uint32_t core_id = get_next_core_id(&task->affinity);
+
+	if (BUILTIN_EXPECT(core_id >= MAX_CORES, 0)) {
+		LOG_ERROR("Kernel panic: no core for task %d\n", task->id);
+		while(1);
+	}
+
+	spinlock_irqsave_lock(&readyqueues[core_id].lock);
+
+	task->last_core = core_id;
+	readyqueues_push_back(core_id, task);
+	readyqueues[core_id].nr_tasks++;
+
+	if (readyqueues[core_id].nr_tasks == 1)
+		wakeup_core(core_id);
+
+	spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+
+	LOG_DEBUG("migrate task %d to core %d\n", task->id, core_id);
+}
+
+/* a detached task is reaped, otherwise it waits to be joined */
+static void release_task(task_t* task)
+{
+	tid_t joiner = 0;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (task->detached) {
+		/* signalizes that this task could be reused */
+		task->status = TASK_INVALID;
+	} else {
+		task->status = TASK_ZOMBIE;
+		joiner = task->joiner;
+	}
+
+	spinlock_irqsave_unlock(&table_lock);
+
+	if (joiner)
+		wakeup_task(joiner);
+}
+
 void finish_task_switch(void)
 {
 	task_t* old;
@@ -350,11 +859,29 @@ void finish_task_switch(void)
 			if (readyqueues[core_id].fpu_owner == old->id)
 				readyqueues[core_id].fpu_owner = 0;
 
-			/* signalizes that this task could be reused */
-			old->status = TASK_INVALID;
-		} else {
+			atomic_int64_add(&exited_cycles, old->cpu_cycles);
+
+			spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+
+			release_task(old);
+			return;
+		} else if (core_allowed(old, core_id)) {
 			// re-enqueue old task
 			readyqueues_push_back(core_id, old);
+		} else {
+			// the task isn't allowed to run on this core anymore
+			// => the next core restores the FPU state from the task
+			if (readyqueues[core_id].fpu_owner == old->id) {
+				flush_fpu_state(&old->fpu);
+				old->flags &= ~TASK_FPU_USED;
+				readyqueues[core_id].fpu_owner = 0;
+			}
+
+			readyqueues[core_id].nr_tasks--;
+			spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+
+			migrate_task(old);
+			return;
 		}
 	}
 
@@ -362,6 +889,22 @@ void finish_task_switch(void)
 }
 
 
+/* disarm all interval timers of a terminating task */
+static void disarm_itimers(task_t* task)
+{
+	itimer_t* timer = task->itimers+ITIMER_REAL;
+
+	if (timer->expires) {
+		spinlock_irqsave_lock(&readyqueues[timer->core].lock);
+		if (timer->expires)
+			itimer_queue_remove(&readyqueues[timer->core], timer);
+		spinlock_irqsave_unlock(&readyqueues[timer->core].lock);
+	}
+
+	memset(task->itimers, 0x00, sizeof(task->itimers));
+}
+
+
 void NORETURN do_exit(int arg)
 {
 	task_t* curr_task = per_core(current_task);
@@ -369,16 +912,29 @@ void NORETURN do_exit(int arg)
 
 	LOG_INFO("Terminate task: %u, return value %d\n", curr_task->id, arg);
 
+	// a joining task receives the exit code
+	curr_task->exit_code = arg;
+
+	// the last task, which shares the file descriptor table, closes all files
+	fd_table_put();
+
 	uint8_t flags = irq_nested_disable();
 
 	// decrease the number of active tasks
 	spinlock_irqsave_lock(&readyqueues[core_id].lock);
 	readyqueues[core_id].nr_tasks--;
+	// a deadline task releases its utilization of the core
+	if (curr_task->policy == SCHED_DEADLINE) {
+		readyqueues[core_id].dl_bw -= dl_bandwidth(curr_task->dl_runtime, curr_task->dl_period);
+		curr_task->policy = SCHED_OTHER;
+	}
 	spinlock_irqsave_unlock(&readyqueues[core_id].lock);
 
 	// release the thread local storage
 	destroy_tls();
 
+	disarm_itimers(curr_task);
+
 	curr_task->status = TASK_FINISHED;
 
 	reschedule();
@@ -405,38 +961,108 @@ void NORETURN do_abort(void) {
 }
 
 
-static uint32_t get_next_core_id(void)
+int join_task(tid_t id, int* exit_code)
 {
-	uint32_t i;
-	static uint32_t core_id = MAX_CORES;
-
-	if (core_id >= MAX_CORES)
-		core_id = CORE_ID;
+	task_t* curr_task = per_core(current_task);
+	task_t* task;
+	int ret = 0;
+
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+	if (BUILTIN_EXPECT(id == curr_task->id, 0))
+		return -EDEADLK;
+
+	task = task_table + id;
 
-	// we assume OpenMP applications
-	// => number of threads is (normaly) equal to the number of cores
-	// => search next available core
-	for(i=0, core_id=(core_id+1)%MAX_CORES; i<2*MAX_CORES; i++, core_id=(core_id+1)%MAX_CORES)
-		if (readyqueues[core_id].idle)
+	spinlock_irqsave_lock(&table_lock);
+
+	while(1) {
+		if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_IDLE), 0)) {
+			ret = -ESRCH;
 			break;
+		}
 
-	if (BUILTIN_EXPECT(!readyqueues[core_id].idle, 0)) {
-		LOG_ERROR("BUG: no core available!\n");
-		return MAX_CORES;
+		// a detached task can't be joined and only one task may join
+		if (BUILTIN_EXPECT(task->detached || (task->joiner && (task->joiner != curr_task->id)), 0)) {
+			ret = -EINVAL;
+			break;
+		}
+
+		if (task->status == TASK_ZOMBIE) {
+			if (exit_code)
+				*exit_code = task->exit_code;
+
+			/* signalizes that this task could be reused */
+			task->joiner = 0;
+			task->status = TASK_INVALID;
+			break;
+		}
+
+		// release_task() wakes up the joining task
+		task->joiner = curr_task->id;
+		block_current_task();
+		spinlock_irqsave_unlock(&table_lock);
+		reschedule();
+		spinlock_irqsave_lock(&table_lock);
 	}
 
-	return core_id;
+	spinlock_irqsave_unlock(&table_lock);
+
+	return ret;
+}
+
+
+int detach_task(tid_t id)
+{
+	task_t* task;
+	int ret = 0;
+
+	if (!id)
+		id = per_core(current_task)->id;
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+
+	task = task_table + id;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_IDLE), 0)) {
+		ret = -ESRCH;
+		goto out;
+	}
+
+	if (BUILTIN_EXPECT(task->detached || task->joiner, 0)) {
+		ret = -EINVAL;
+		goto out;
+	}
+
+	task->detached = 1;
+
+	// a finished task is reaped immediately
+	if (task->status == TASK_ZOMBIE)
+		task->status = TASK_INVALID;
+
+out:
+	spinlock_irqsave_unlock(&table_lock);
+
+	return ret;
 }
 
 
 int clone_task(tid_t* id, entry_point_t ep, void* arg, uint8_t prio)
+{
+	return clone_task_on_core(id, ep, arg, prio, MAX_CORES, 0);
+}
+
+
+int clone_task_on_core(tid_t* id, entry_point_t ep, void* arg, uint8_t prio, uint32_t core_id, uint32_t flags)
 {
 	int ret = -EINVAL;
 	uint32_t i;
 	void* stack = NULL;
 	void* ist = NULL;
 	task_t* curr_task;
-	uint32_t core_id;
+	cpu_set_t affinity;
 
 	if (BUILTIN_EXPECT(!ep, 0))
 		return -EINVAL;
@@ -444,9 +1070,22 @@ int clone_task(tid_t* id, entry_point_t ep, void* arg, uint8_t prio)
 		return -EINVAL;
 	if (BUILTIN_EXPECT(prio > MAX_PRIO, 0))
 		return -EINVAL;
+	if (BUILTIN_EXPECT(flags & ~CLONE_JOINABLE, 0))
+		return -EINVAL;
+
+	if (BUILTIN_EXPECT((core_id < MAX_CORES) && !readyqueues[core_id].idle, 0))
+		return -EINVAL;
 
 	curr_task = per_core(current_task);
 
+	// a task on a specific core is pinned, otherwise it inherits the affinity
+	if (core_id < MAX_CORES) {
+		CPU_ZERO(&affinity);
+		CPU_SET(core_id, &affinity);
+	} else {
+		affinity = curr_task->affinity;
+	}
+
 	stack = create_stack(DEFAULT_STACK_SIZE);
 	if (BUILTIN_EXPECT(!stack, 0))
 		return -ENOMEM;
@@ -459,7 +1098,8 @@ int clone_task(tid_t* id, entry_point_t ep, void* arg, uint8_t prio)
 
 	spinlock_irqsave_lock(&table_lock);
 
-	core_id = get_next_core_id();
+	if (core_id >= MAX_CORES)
+		core_id = get_next_core_id(&affinity);
 	if (BUILTIN_EXPECT(core_id >= MAX_CORES, 0))
 	{
 		spinlock_irqsave_unlock(&table_lock);
@@ -476,14 +1116,30 @@ int clone_task(tid_t* id, entry_point_t ep, void* arg, uint8_t prio)
 			task_table[i].stack = stack;
 			task_table[i].prio = prio;
 			task_table[i].heap = curr_task->heap;
+			task_table[i].fd_table = curr_task->fd_table;
 			task_table[i].start_tick = get_clock_tick();
 			task_table[i].last_tsc = 0;
+			task_table[i].cpu_cycles = 0;
+			memset(task_table[i].itimers, 0x00, sizeof(task_table[i].itimers));
 			task_table[i].parent = curr_task->id;
 			task_table[i].tls_addr = curr_task->tls_addr;
 			task_table[i].tls_size = curr_task->tls_size;
 			task_table[i].ist_addr = ist;
 			task_table[i].lwip_err = 0;
-			task_table[i].signal_handler = NULL;
+			task_table[i].sig_mask = curr_task->sig_mask;
+			task_table[i].sig_pending = 0;
+			// a new thread doesn't inherit the alternate signal stack
+			task_table[i].sig_altstack.ss_sp = NULL;
+			task_table[i].sig_altstack.ss_flags = SS_DISABLE;
+			task_table[i].sig_altstack.ss_size = 0;
+			task_table[i].affinity = affinity;
+			// the utilization of a deadline task isn't inherited
+			task_table[i].policy = (curr_task->policy == SCHED_DEADLINE) ? SCHED_OTHER : curr_task->policy;
+			task_table[i].slice_end = 0;
+			// only a joinable task occupies its slot until it's joined
+			task_table[i].exit_code = 0;
+			task_table[i].joiner = 0;
+			task_table[i].detached = !(flags & CLONE_JOINABLE);
 
 			if (id)
 				*id = i;
@@ -492,6 +1148,9 @@ int clone_task(tid_t* id, entry_point_t ep, void* arg, uint8_t prio)
 			if (ret)
 				goto out;
 
+			// the new task shares the file descriptor table
+			fd_table_get(task_table[i].fd_table);
+
                         // add task in the readyqueues
 			spinlock_irqsave_lock(&readyqueues[core_id].lock);
 			readyqueues[core_id].prio_bitmap |= (1 << prio);
@@ -578,14 +1237,29 @@ int create_task(tid_t* id, entry_point_t ep, void* arg, uint8_t prio, uint32_t c
 			task_table[i].stack = stack;
 			task_table[i].prio = prio;
 			task_table[i].heap = NULL;
+			task_table[i].fd_table = NULL;
 			task_table[i].start_tick = get_clock_tick();
 			task_table[i].last_tsc = 0;
+			task_table[i].cpu_cycles = 0;
+			memset(task_table[i].itimers, 0x00, sizeof(task_table[i].itimers));
 			task_table[i].parent = 0;
 			task_table[i].ist_addr = ist;
 			task_table[i].tls_addr = 0;
 			task_table[i].tls_size = 0;
 			task_table[i].lwip_err = 0;
-			task_table[i].signal_handler = NULL;
+			task_table[i].sig_mask = 0;
+			task_table[i].sig_pending = 0;
+			task_table[i].sig_altstack.ss_sp = NULL;
+			task_table[i].sig_altstack.ss_flags = SS_DISABLE;
+			task_table[i].sig_altstack.ss_size = 0;
+			// the load balancer is allowed to migrate the task
+			memset(&task_table[i].affinity, 0xFF, sizeof(cpu_set_t));
+			task_table[i].policy = SCHED_OTHER;
+			task_table[i].slice_end = 0;
+			// nobody joins a kernel task
+			task_table[i].exit_code = 0;
+			task_table[i].joiner = 0;
+			task_table[i].detached = 1;
 
 			if (id)
 				*id = i;
@@ -657,7 +1331,19 @@ int wakeup_task(tid_t id)
 	task = &task_table[id];
 	core_id = task->last_core;
 
+	// a throttled deadline task waits for its next period
+	if ((task->flags & TASK_DL_THROTTLED) && (get_rdtsc() < task->timeout)) {
+		spinlock_irqsave_unlock(&table_lock);
+		return -EINVAL;
+	}
+
 	if (task->status == TASK_BLOCKED) {
+		// the affinity has changed while the task was blocked
+		if (!core_allowed(task, core_id)) {
+			core_id = get_next_core_id(&task->affinity);
+			task->last_core = core_id;
+		}
+
 		LOG_DEBUG("wakeup task %d on core %d\n", id, core_id);
 
 		task->status = TASK_READY;
@@ -674,6 +1360,12 @@ int wakeup_task(tid_t id)
 			timer_queue_remove(core_id, task);
 		}
 
+		// a deadline task may have to wait for its next period
+		if ((task->policy == SCHED_DEADLINE) && !dl_wakeup(core_id, task)) {
+			spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+			return ret;
+		}
+
 		// add task to the ready queue
 		readyqueues_push_back(core_id, task);
 
@@ -683,6 +1375,9 @@ int wakeup_task(tid_t id)
 		// should we wakeup the core?
 		if (readyqueues[core_id].nr_tasks == 1)
 			wakeup_core(core_id);
+		// a deadline task preempts the current task of its core
+		else if (task->policy == SCHED_DEADLINE)
+			preempt_core(core_id);
 
 		LOG_DEBUG("update nr_tasks on core %d to %d\n", core_id, readyqueues[core_id].nr_tasks);
 
@@ -739,6 +1434,20 @@ int block_current_task(void)
 
 
 int set_timer(uint64_t deadline)
+{
+	const uint64_t freq = get_tsc_frequency();
+	const uint64_t now = get_clock_tick();
+	uint64_t cycles = 0;
+
+	// convert the tick into a value of the cycle counter
+	if (deadline > now)
+		cycles = ((deadline - now) * freq) / TIMER_FREQ;
+
+	return set_timer_cycles(get_rdtsc() + cycles);
+}
+
+
+int set_timer_cycles(uint64_t deadline)
 {
 	task_t* curr_task;
 	uint32_t core_id;
@@ -770,38 +1479,178 @@ int set_timer(uint64_t deadline)
 }
 
 
+#define MAX_EXPIRED_ITIMERS	8
+
 void check_timers(void)
 {
 	readyqueues_t* readyqueue = &readyqueues[CORE_ID];
+	sig_t expired[MAX_EXPIRED_ITIMERS];
+	uint32_t i, nexpired = 0;
+	itimer_t* timer;
+
 	spinlock_irqsave_lock(&readyqueue->lock);
 
-	// since IRQs are disabled, get_clock_tick() won't increase here
-	const uint64_t current_tick = get_clock_tick();
+	const uint64_t now = get_rdtsc();
 
 	// wakeup tasks whose deadline has expired
 	task_t* task;
-	while ((task = readyqueue->timers.first) && (task->timeout <= current_tick))
+	while ((task = readyqueue->timers.first) && (task->timeout <= now))
 	{
 		// pops task from timer queue, so next iteration has new first element
 		wakeup_task(task->id);
 	}
 
-#ifdef DYNAMIC_TICKS
-	task = readyqueue->timers.first;
-	if (task) {
-		update_timer(task);
+	// expired ITIMER_REAL timers, the remaining ones are handled by the next tick
+	while ((timer = readyqueue->itimers) && (timer->expires <= now)
+	       && (nexpired < MAX_EXPIRED_ITIMERS))
+	{
+		itimer_queue_remove(readyqueue, timer);
+
+		expired[nexpired].dest = timer->owner;
+		expired[nexpired].signum = SIGALRM;
+		nexpired++;
+
+		if (timer->interval) {
+			timer->expires += timer->interval;
+			if (timer->expires <= now)
+				timer->expires = now + timer->interval;
+			itimer_queue_push(readyqueue, timer);
+		} else {
+			timer->expires = 0;
+		}
 	}
+
+	// CPU time timers of the current task
+	task = per_core(current_task);
+	if ((task->status != TASK_IDLE) && (task->itimers[ITIMER_VIRTUAL].expires
+	    || task->itimers[ITIMER_PROF].expires)) {
+		const uint64_t cycles = get_task_cycles();
+
+		for(i=ITIMER_VIRTUAL; (i<MAX_ITIMERS) && (nexpired < MAX_EXPIRED_ITIMERS); i++) {
+			timer = task->itimers+i;
+
+			if (!timer->expires || (timer->expires > cycles))
+				continue;
+
+			expired[nexpired].dest = task->id;
+			expired[nexpired].signum = (i == ITIMER_VIRTUAL) ? SIGVTALRM : SIGPROF;
+			nexpired++;
+
+			timer->expires = timer->interval ? cycles + timer->interval : 0;
+		}
+	}
+
+#ifdef DYNAMIC_TICKS
+	update_timer(CORE_ID);
 #endif
 
 	spinlock_irqsave_unlock(&readyqueue->lock);
+
+	// deliver the signals without holding the lock
+	for(i=0; i<nexpired; i++) {
+		siginfo_t info;
+
+		memset(&info, 0x00, sizeof(info));
+		info.si_signo = expired[i].signum;
+		info.si_code = SI_TIMER;
+		signal_send(expired[i].dest, &info);
+	}
+}
+
+
+int set_itimer(uint32_t which, uint64_t value, uint64_t interval,
+               uint64_t* old_value, uint64_t* old_interval)
+{
+	task_t* curr_task;
+	itimer_t* timer;
+	uint32_t core_id;
+	uint64_t now;
+	uint8_t flags;
+
+	if (BUILTIN_EXPECT(which >= MAX_ITIMERS, 0))
+		return -EINVAL;
+
+	flags = irq_nested_disable();
+
+	curr_task = per_core(current_task);
+	core_id = CORE_ID;
+	timer = curr_task->itimers+which;
+	now = (which == ITIMER_REAL) ? get_rdtsc() : get_task_cycles();
+
+	// disarm the previous timer, a real-time timer is part of the queue of its core
+	spinlock_irqsave_lock(&readyqueues[timer->core].lock);
+
+	if (old_value)
+		*old_value = timer->expires ? ((timer->expires > now) ? timer->expires - now : 1) : 0;
+	if (old_interval)
+		*old_interval = timer->interval;
+
+	if ((which == ITIMER_REAL) && timer->expires)
+		itimer_queue_remove(&readyqueues[timer->core], timer);
+	timer->expires = 0;
+
+	spinlock_irqsave_unlock(&readyqueues[timer->core].lock);
+
+	spinlock_irqsave_lock(&readyqueues[core_id].lock);
+
+	timer->owner = curr_task->id;
+	timer->core = core_id;
+	timer->interval = value ? interval : 0;
+	timer->expires = value ? now + value : 0;
+
+	if ((which == ITIMER_REAL) && timer->expires)
+		itimer_queue_push(&readyqueues[core_id], timer);
+
+#ifdef DYNAMIC_TICKS
+	update_timer(core_id);
+#endif
+
+	spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+
+	irq_nested_enable(flags);
+
+	return 0;
 }
 
 
+int get_itimer(uint32_t which, uint64_t* value, uint64_t* interval)
+{
+	task_t* curr_task;
+	itimer_t* timer;
+	uint64_t now;
+	uint8_t flags;
+
+	if (BUILTIN_EXPECT(which >= MAX_ITIMERS, 0))
+		return -EINVAL;
+
+	flags = irq_nested_disable();
+
+	curr_task = per_core(current_task);
+	timer = curr_task->itimers+which;
+	now = (which == ITIMER_REAL) ? get_rdtsc() : get_task_cycles();
+
+	spinlock_irqsave_lock(&readyqueues[timer->core].lock);
+
+	if (value)
+		*value = timer->expires ? ((timer->expires > now) ? timer->expires - now : 1) : 0;
+	if (interval)
+		*interval = timer->interval;
+
+	spinlock_irqsave_unlock(&readyqueues[timer->core].lock);
+
+	irq_nested_enable(flags);
+
+	return 0;
+}
+
+
+
 size_t** scheduler(void)
 {
 	task_t* orig_task;
 	task_t* curr_task;
 	const uint32_t core_id = CORE_ID;
+	const uint64_t now = get_rdtsc();
 	uint64_t prio;
 
 	orig_task = curr_task = per_core(current_task);
@@ -809,11 +1658,24 @@ size_t** scheduler(void)
 
 	spinlock_irqsave_lock(&readyqueues[core_id].lock);
 
+	const int yield = curr_task->flags & TASK_YIELD;
+	curr_task->flags &= ~TASK_YIELD;
+
 	/* signalizes that this task could be realized */
 	if (curr_task->status == TASK_FINISHED)
 		readyqueues[core_id].old_task = curr_task;
 	else readyqueues[core_id].old_task = NULL; // reset old task
 
+	// charge the budget of a deadline task and throttle it on an overrun
+	if (curr_task->policy == SCHED_DEADLINE) {
+		dl_charge(curr_task, now);
+
+		if ((curr_task->status == TASK_RUNNING) && (curr_task->dl_budget <= 0)) {
+			dl_throttle(core_id, curr_task);
+			readyqueues[core_id].nr_tasks--;
+		}
+	}
+
 	// do we receive a shutdown IPI => only the idle task should get the core
 	if (BUILTIN_EXPECT(go_down, 0)) {
 		if (curr_task->status == TASK_IDLE)
@@ -825,17 +1687,50 @@ size_t** scheduler(void)
 	// determine highest priority
 	prio = msb(readyqueues[core_id].prio_bitmap);
 
+	// the affinity of the current task excludes this core => finish_task_switch() migrates it
+	const int migrate = (curr_task->status == TASK_RUNNING) && !core_allowed(curr_task, core_id);
+
+	// deadline tasks precede the priority queues => earliest deadline first
+	task_t* dl_task = readyqueues[core_id].dl_queue.first;
+	if ((curr_task->policy == SCHED_DEADLINE) && (curr_task->status == TASK_RUNNING)
+	    && (!dl_task || (curr_task->dl_abs_deadline <= dl_task->dl_abs_deadline)))
+		goto get_task_out;
+
+	if (dl_task) {
+		// mark current task for later cleanup by finish_task_switch()
+		if (curr_task->status == TASK_RUNNING) {
+			curr_task->status = TASK_READY;
+			readyqueues[core_id].old_task = curr_task;
+		}
+
+		curr_task = task_list_pop_front(&readyqueues[core_id].dl_queue);
+		curr_task->status = TASK_RUNNING;
+		set_per_core(current_task, curr_task);
+
+		goto get_task_out;
+	}
+
 	const int readyqueue_empty = prio > MAX_PRIO;
 	if (readyqueue_empty) {
 
-		if ((curr_task->status == TASK_RUNNING) || (curr_task->status == TASK_IDLE))
+		if (((curr_task->status == TASK_RUNNING) && !migrate) || (curr_task->status == TASK_IDLE))
 			goto get_task_out;
+
+		if (migrate) {
+			curr_task->status = TASK_READY;
+			readyqueues[core_id].old_task = curr_task;
+		}
+
 		curr_task = readyqueues[core_id].idle;
 		set_per_core(current_task, curr_task);
 	} else {
-		// Does the current task have an higher priority? => no task switch
-		if ((curr_task->prio > prio) && (curr_task->status == TASK_RUNNING))
-			goto get_task_out;
+		// Does the current task have an higher priority or time left in its slice? => no task switch
+		if ((curr_task->status == TASK_RUNNING) && !migrate) {
+			if (curr_task->prio > prio)
+				goto get_task_out;
+			if ((curr_task->prio == prio) && !yield && !(time_sliced(curr_task) && (now >= curr_task->slice_end)))
+				goto get_task_out;
+		}
 
 		// mark current task for later cleanup by finish_task_switch()
 		if (curr_task->status == TASK_RUNNING) {
@@ -864,13 +1759,31 @@ size_t** scheduler(void)
 
 		// finally make it the new current task
 		curr_task->status = TASK_RUNNING;
-#ifdef DYNAMIC_TICKS
-		curr_task->last_tsc = get_rdtsc();
-#endif
 		set_per_core(current_task, curr_task);
 	}
 
 get_task_out:
+	if (curr_task != orig_task) {
+		// account the time slice of the previous task
+		if (orig_task->last_tsc)
+			orig_task->cpu_cycles += now - orig_task->last_tsc;
+		curr_task->last_tsc = now;
+		curr_task->slice_end = now + quantum_cycles();
+		curr_task->dl_tsc = now;
+
+#ifdef DYNAMIC_TICKS
+		// the CPU time timers and the time slice depend on the current task
+		update_timer(core_id);
+#endif
+	} else if (time_sliced(curr_task) && (now >= curr_task->slice_end)) {
+		// no other task with the same priority is ready => start a new time slice
+		curr_task->slice_end = now + quantum_cycles();
+
+#ifdef DYNAMIC_TICKS
+		update_timer(core_id);
+#endif
+	}
+
 	spinlock_irqsave_unlock(&readyqueues[core_id].lock);
 
 	if (curr_task != orig_task) {
@@ -883,6 +1796,360 @@ get_task_out:
 }
 
 
+int set_task_affinity(tid_t id, const cpu_set_t* affinity)
+{
+	task_t* curr_task = per_core(current_task);
+	task_t* task;
+	uint32_t i, core_id;
+	int available = 0;
+
+	if (!id)
+		id = curr_task->id;
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+
+	for(i=0; i<MAX_CORES; i++) {
+		if (readyqueues[i].idle && CPU_ISSET(i, affinity))
+			available = 1;
+	}
+
+	if (BUILTIN_EXPECT(!available, 0))
+		return -EINVAL;
+
+	task = task_table + id;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_IDLE)
+	    || (task->status == TASK_FINISHED) || (task->status == TASK_ZOMBIE), 0)) {
+		spinlock_irqsave_unlock(&table_lock);
+		return -ESRCH;
+	}
+
+	// a deadline task is bound to the core, which accounts its utilization
+	if (BUILTIN_EXPECT(task->policy == SCHED_DEADLINE, 0)) {
+		spinlock_irqsave_unlock(&table_lock);
+		return -EBUSY;
+	}
+
+	task->affinity = *affinity;
+
+	spinlock_irqsave_unlock(&table_lock);
+
+	// the current task leaves the core by the scheduler
+	if (task == curr_task) {
+		if (!core_allowed(task, CORE_ID))
+			reschedule();
+		return 0;
+	}
+
+	// move a waiting task immediately, running and blocked tasks are
+	// migrated by the scheduler or by wakeup_task()
+	core_id = task->last_core;
+	if (core_allowed(task, core_id))
+		return 0;
+
+	spinlock_irqsave_lock(&readyqueues[core_id].lock);
+
+	if ((task->status == TASK_READY) && (task->last_core == core_id)
+	    && (readyqueues[core_id].old_task != task)
+	    && (readyqueues[core_id].fpu_owner != task->id)) {
+		readyqueues_remove(core_id, task);
+		readyqueues[core_id].nr_tasks--;
+
+		spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+
+		migrate_task(task);
+	} else {
+		spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+	}
+
+	return 0;
+}
+
+int get_task_affinity(tid_t id, cpu_set_t* affinity)
+{
+	task_t* task;
+
+	if (!id)
+		id = per_core(current_task)->id;
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+
+	task = task_table + id;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_FINISHED)
+	    || (task->status == TASK_ZOMBIE), 0)) {
+		spinlock_irqsave_unlock(&table_lock);
+		return -ESRCH;
+	}
+
+	*affinity = task->affinity;
+
+	spinlock_irqsave_unlock(&table_lock);
+
+	return 0;
+}
+
+/* lock the ready queue of the core, on which the task was running the last time */
+static uint32_t readyqueues_lock_task(task_t* task)
+{
+	uint32_t core_id;
+
+	// the load balancer may move the task in the meantime
+	while(1) {
+		core_id = task->last_core;
+		spinlock_irqsave_lock(&readyqueues[core_id].lock);
+		if (task->last_core == core_id)
+			return core_id;
+		spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+	}
+}
+
+int set_task_scheduler(tid_t id, int policy, uint8_t prio)
+{
+	task_t* task;
+	uint32_t core_id;
+	int queued;
+
+	if (!id)
+		id = per_core(current_task)->id;
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+	if (BUILTIN_EXPECT((policy != SCHED_OTHER) && (policy != SCHED_FIFO) && (policy != SCHED_RR), 0))
+		return -EINVAL;
+	if (BUILTIN_EXPECT(prio > MAX_PRIO, 0))
+		return -EINVAL;
+
+	task = task_table + id;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_IDLE)
+	    || (task->status == TASK_FINISHED) || (task->status == TASK_ZOMBIE), 0)) {
+		spinlock_irqsave_unlock(&table_lock);
+		return -ESRCH;
+	}
+
+	spinlock_irqsave_unlock(&table_lock);
+
+	// the policy and the priority select the ready queue
+	core_id = readyqueues_lock_task(task);
+
+	queued = (task->status == TASK_READY) && (readyqueues[core_id].old_task != task);
+	if (queued)
+		readyqueues_remove(core_id, task);
+
+	// a deadline task releases its utilization of the core
+	if (task->policy == SCHED_DEADLINE) {
+		readyqueues[core_id].dl_bw -= dl_bandwidth(task->dl_runtime, task->dl_period);
+		task->flags &= ~TASK_DL_THROTTLED;
+	}
+
+	task->policy = policy;
+	if (prio)
+		task->prio = prio;
+
+	if (queued)
+		readyqueues_push_back(core_id, task);
+
+	spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+
+	// a task with a higher priority may be ready
+	check_scheduling();
+
+	return 0;
+}
+
+int get_task_scheduler(tid_t id, int* policy, uint8_t* prio)
+{
+	task_t* task;
+
+	if (!id)
+		id = per_core(current_task)->id;
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+
+	task = task_table + id;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_FINISHED)
+	    || (task->status == TASK_ZOMBIE), 0)) {
+		spinlock_irqsave_unlock(&table_lock);
+		return -ESRCH;
+	}
+
+	if (policy)
+		*policy = task->policy;
+	if (prio)
+		*prio = task->prio;
+
+	spinlock_irqsave_unlock(&table_lock);
+
+	return 0;
+}
+
+int set_task_deadline(tid_t id, uint64_t runtime, uint64_t deadline, uint64_t period)
+{
+	task_t* task;
+	uint64_t bw, old_bw = 0;
+	uint32_t core_id;
+	int queued, ret = 0;
+
+	if (!id)
+		id = per_core(current_task)->id;
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+
+	// without a period, the task is released at its deadline
+	if (!period)
+		period = deadline;
+	if (BUILTIN_EXPECT(!runtime || (runtime > deadline) || (deadline > period), 0))
+		return -EINVAL;
+
+	runtime = ns_to_cycles(runtime);
+	deadline = ns_to_cycles(deadline);
+	period = ns_to_cycles(period);
+	if (BUILTIN_EXPECT(!runtime, 0))
+		return -EINVAL;
+
+	bw = dl_bandwidth(runtime, period);
+	task = task_table + id;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_IDLE)
+	    || (task->status == TASK_FINISHED) || (task->status == TASK_ZOMBIE), 0)) {
+		spinlock_irqsave_unlock(&table_lock);
+		return -ESRCH;
+	}
+
+	spinlock_irqsave_unlock(&table_lock);
+
+	core_id = readyqueues_lock_task(task);
+
+	if (task->policy == SCHED_DEADLINE)
+		old_bw = dl_bandwidth(task->dl_runtime, task->dl_period);
+
+	// admission control => the deadline tasks of a core mustn't exceed DL_BW_LIMIT
+	if (readyqueues[core_id].dl_bw - old_bw + bw > DL_BW_LIMIT) {
+		LOG_INFO("Reject deadline task %d on core %d: utilization exceeded\n", id, core_id);
+		ret = -EBUSY;
+		goto out;
+	}
+
+	queued = (task->status == TASK_READY) && (readyqueues[core_id].old_task != task);
+	if (queued)
+		readyqueues_remove(core_id, task);
+
+	readyqueues[core_id].dl_bw = readyqueues[core_id].dl_bw - old_bw + bw;
+
+	task->policy = SCHED_DEADLINE;
+	task->dl_runtime = runtime;
+	task->dl_deadline = deadline;
+	task->dl_period = period;
+	task->flags &= ~TASK_DL_THROTTLED;
+	dl_replenish(task, get_rdtsc());
+
+	// the utilization is accounted on this core => bind the task to it
+	CPU_ZERO(&task->affinity);
+	CPU_SET(core_id, &task->affinity);
+
+	if (queued)
+		readyqueues_push_back(core_id, task);
+
+out:
+	spinlock_irqsave_unlock(&readyqueues[core_id].lock);
+
+	// the deadline task may preempt the current task
+	if (!ret)
+		check_scheduling();
+
+	return ret;
+}
+
+int get_task_deadline(tid_t id, uint64_t* runtime, uint64_t* deadline, uint64_t* period)
+{
+	task_t* task;
+	int ret = 0;
+
+	if (!id)
+		id = per_core(current_task)->id;
+	if (BUILTIN_EXPECT(id >= MAX_TASKS, 0))
+		return -ESRCH;
+
+	task = task_table + id;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	if (BUILTIN_EXPECT((task->status == TASK_INVALID) || (task->status == TASK_FINISHED)
+	    || (task->status == TASK_ZOMBIE), 0)) {
+		ret = -ESRCH;
+		goto out;
+	}
+
+	if (BUILTIN_EXPECT(task->policy != SCHED_DEADLINE, 0)) {
+		ret = -EINVAL;
+		goto out;
+	}
+
+	if (runtime)
+		*runtime = cycles_to_ns(task->dl_runtime);
+	if (deadline)
+		*deadline = cycles_to_ns(task->dl_deadline);
+	if (period)
+		*period = cycles_to_ns(task->dl_period);
+
+out:
+	spinlock_irqsave_unlock(&table_lock);
+
+	return ret;
+}
+
+
+uint64_t get_task_cycles(void)
+{
+	uint8_t flags = irq_nested_disable();
+	task_t* curr_task = per_core(current_task);
+	uint64_t cycles = curr_task->cpu_cycles;
+
+	if (curr_task->last_tsc)
+		cycles += get_rdtsc() - curr_task->last_tsc;
+	irq_nested_enable(flags);
+
+	return cycles;
+}
+
+uint64_t get_process_cycles(void)
+{
+	uint64_t cycles = (uint64_t) atomic_int64_read(&exited_cycles);
+	uint32_t i;
+
+	spinlock_irqsave_lock(&table_lock);
+
+	const uint64_t now = get_rdtsc();
+
+	for(i=0; i<MAX_TASKS; i++) {
+		task_t* task = task_table+i;
+
+		if ((task->status == TASK_INVALID) || (task->status == TASK_IDLE)
+		    || (task->status == TASK_FINISHED) || (task->status == TASK_ZOMBIE))
+			continue;
+
+		cycles += task->cpu_cycles;
+		// add the current time slice of running tasks
+		if ((task->status == TASK_RUNNING) && task->last_tsc && (now > task->last_tsc))
+			cycles += now - task->last_tsc;
+	}
+
+	spinlock_irqsave_unlock(&table_lock);
+
+	return cycles;
+}
+
 int get_task(tid_t id, task_t** task)
 {
 	if (BUILTIN_EXPECT(task == NULL, 0)) {
@@ -0,0 +1,417 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/time.c
+ * @brief Clocks of the kernel, based on the cycle counter
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/errno.h>
+#include <hermit/time.h>
+#include <hermit/tasks.h>
+#include <hermit/spinlock.h>
+#include <hermit/syscall.h>
+#include <hermit/logging.h>
+#include <hermit/vma.h>
+#include <hermit/time_page.h>
+#include <asm/page.h>
+#include <sys/time.h>
+
+#define TIME_PAGE_SHIFT		32
+
+/// shorter sleeps are realized by busy waiting
+#define SLEEP_SPIN_NS		10000ULL
+
+/// the time page fills a whole page, because it is mapped into the user space
+static union {
+	time_page_t tp;
+	uint8_t page[PAGE_SIZE];
+} time_data __attribute__ ((aligned (PAGE_SIZE)));
+
+/// read-only alias of the time page, which is accessible by the user space
+static const time_page_t* user_time_page = NULL;
+
+/// serializes updates of the time page
+static spinlock_irqsave_t time_lock = SPINLOCK_IRQSAVE_INIT;
+
+uint64_t cycles_to_ns(uint64_t cycles)
+{
+	const time_page_t* tp = &time_data.tp;
+
+	if (BUILTIN_EXPECT(tp->mult, 1))
+		return time_page_cycles_to_ns(tp, cycles);
+
+	// the time page isn't initialized => use the frequency directly
+	const uint64_t freq = get_tsc_frequency();
+	if (BUILTIN_EXPECT(!freq, 0))
+		return 0;
+
+	return (cycles / freq) * NSEC_PER_SEC + ((cycles % freq) * NSEC_PER_SEC) / freq;
+}
+
+uint64_t ns_to_cycles(uint64_t ns)
+{
+	const uint64_t freq = get_tsc_frequency();
+
+	return (ns / NSEC_PER_SEC) * freq + ((ns % NSEC_PER_SEC) * freq + NSEC_PER_SEC - 1) / NSEC_PER_SEC;
+}
+
+uint64_t get_uptime_ns(void)
+{
+	return cycles_to_ns(get_rdtsc() - get_boot_tsc());
+}
+
+static inline void time_page_write_begin(time_page_t* tp)
+{
+	tp->seq++;
+	mb();
+}
+
+static inline void time_page_write_end(time_page_t* tp)
+{
+	mb();
+	tp->seq++;
+}
+
+int time_init(void)
+{
+	time_page_t* tp = &time_data.tp;
+	const uint64_t freq = get_tsc_frequency();
+	uint64_t wallclock, uptime;
+	size_t viraddr;
+	int ret;
+
+	if (BUILTIN_EXPECT(!freq, 0)) {
+		LOG_ERROR("Time: frequency of the cycle counter is unknown\n");
+		return -EINVAL;
+	}
+
+	wallclock = get_wallclock();
+	if (!wallclock)
+		LOG_WARNING("Time: wall-clock time isn't available, CLOCK_REALTIME starts at the epoch\n");
+
+	spinlock_irqsave_lock(&time_lock);
+
+	time_page_write_begin(tp);
+	tp->shift = TIME_PAGE_SHIFT;
+	tp->mult = (NSEC_PER_SEC << TIME_PAGE_SHIFT) / freq;
+	tp->freq = freq;
+	tp->tsc_base = get_boot_tsc();
+	uptime = time_page_cycles_to_ns(tp, get_rdtsc() - tp->tsc_base);
+	tp->realtime_base = (wallclock > uptime) ? wallclock - uptime : 0;
+	time_page_write_end(tp);
+
+	spinlock_irqsave_unlock(&time_lock);
+
+	viraddr = vma_alloc(PAGE_SIZE, VMA_READ|VMA_USER|VMA_CACHEABLE);
+	if (BUILTIN_EXPECT(!viraddr, 0))
+		return -ENOMEM;
+
+	ret = page_map(viraddr, virt_to_phys((size_t) tp), 1, PG_PRESENT|PG_USER|PG_GLOBAL|PG_NX);
+	if (BUILTIN_EXPECT(ret, 0)) {
+		vma_free(viraddr, viraddr+PAGE_SIZE);
+		return -ENOMEM;
+	}
+
+	user_time_page = (const time_page_t*) viraddr;
+
+	LOG_INFO("Time: map time page at %p, counter frequency %llu Hz\n", user_time_page, freq);
+
+	return 0;
+}
+
+/* time of CLOCK_MONOTONIC or CLOCK_REALTIME in nanoseconds */
+static uint64_t clock_read(int realtime)
+{
+	const time_page_t* tp = &time_data.tp;
+
+	if (BUILTIN_EXPECT(!tp->mult, 0))
+		return get_uptime_ns();
+
+	return time_page_read(tp, realtime);
+}
+
+const time_page_t* sys_get_time_page(void)
+{
+	return user_time_page;
+}
+
+int sys_clock_getres(int clock_id, struct timespec* res)
+{
+	uint64_t freq, ns;
+
+	switch(clock_id) {
+	case CLOCK_REALTIME:
+	case CLOCK_MONOTONIC:
+	case CLOCK_PROCESS_CPUTIME_ID:
+	case CLOCK_THREAD_CPUTIME_ID:
+		break;
+	default:
+		return -EINVAL;
+	}
+
+	if (!res)
+		return 0;
+
+	freq = get_tsc_frequency();
+	ns = freq ? (NSEC_PER_SEC + freq - 1) / freq : NSEC_PER_SEC / TIMER_FREQ;
+
+	res->tv_sec = ns / NSEC_PER_SEC;
+	res->tv_nsec = ns % NSEC_PER_SEC;
+
+	return 0;
+}
+
+int sys_sched_rr_get_interval(tid_t id, struct timespec* interval)
+{
+	int policy, ret;
+
+	if (BUILTIN_EXPECT(!interval, 0))
+		return -EFAULT;
+
+	ret = get_task_scheduler(id, &policy, NULL);
+	if (ret)
+		return ret;
+
+	// SCHED_FIFO and SCHED_DEADLINE tasks run without time slices
+	if ((policy == SCHED_FIFO) || (policy == SCHED_DEADLINE)) {
+		interval->tv_sec = 0;
+		interval->tv_nsec = 0;
+	} else {
+		interval->tv_sec = SCHED_RR_QUANTUM / 1000000;
+		interval->tv_nsec = (SCHED_RR_QUANTUM % 1000000) * 1000;
+	}
+
+	return 0;
+}
+
+int sys_clock_gettime(int clock_id, struct timespec* tp)
+{
+	uint64_t ns;
+
+	if (BUILTIN_EXPECT(!tp, 0))
+		return -EFAULT;
+
+	switch(clock_id) {
+	case CLOCK_REALTIME:
+		ns = clock_read(1);
+		break;
+	case CLOCK_MONOTONIC:
+		ns = clock_read(0);
+		break;
+	case CLOCK_PROCESS_CPUTIME_ID:
+		ns = cycles_to_ns(get_process_cycles());
+		break;
+	case CLOCK_THREAD_CPUTIME_ID:
+		ns = cycles_to_ns(get_task_cycles());
+		break;
+	default:
+		return -EINVAL;
+	}
+
+	tp->tv_sec = ns / NSEC_PER_SEC;
+	tp->tv_nsec = ns % NSEC_PER_SEC;
+
+	return 0;
+}
+
+int sys_clock_settime(int clock_id, const struct timespec* tp)
+{
+	time_page_t* page = &time_data.tp;
+	uint64_t ns, uptime;
+	int ret = 0;
+
+	if (BUILTIN_EXPECT(!tp, 0))
+		return -EFAULT;
+	if (BUILTIN_EXPECT(clock_id != CLOCK_REALTIME, 0))
+		return -EINVAL;
+	if (BUILTIN_EXPECT((tp->tv_sec < 0) || (tp->tv_nsec < 0) || (tp->tv_nsec >= (long) NSEC_PER_SEC), 0))
+		return -EINVAL;
+
+	ns = (uint64_t) tp->tv_sec * NSEC_PER_SEC + tp->tv_nsec;
+
+	spinlock_irqsave_lock(&time_lock);
+
+	if (BUILTIN_EXPECT(!page->mult, 0)) {
+		ret = -EAGAIN;
+		goto out;
+	}
+
+	uptime = time_page_cycles_to_ns(page, get_rdtsc() - page->tsc_base);
+	if (BUILTIN_EXPECT(ns < uptime, 0)) {
+		// the time would be before the boot time
+		ret = -EINVAL;
+		goto out;
+	}
+
+	time_page_write_begin(page);
+	page->realtime_base = ns - uptime;
+	time_page_write_end(page);
+
+out:
+	spinlock_irqsave_unlock(&time_lock);
+
+	return ret;
+}
+
+int sys_gettimeofday(struct timeval* tv, struct timezone* tz)
+{
+	if (tv) {
+		const uint64_t ns = clock_read(1);
+
+		tv->tv_sec = ns / NSEC_PER_SEC;
+		tv->tv_usec = (ns % NSEC_PER_SEC) / NSEC_PER_USEC;
+	}
+
+	// HermitCore doesn't know time zones => always UTC
+	if (tz) {
+		tz->tz_minuteswest = 0;
+		tz->tz_dsttime = 0;
+	}
+
+	return 0;
+}
+
+int timer_wait_cycles(uint64_t deadline, int interruptible)
+{
+	task_t* curr_task = per_core(current_task);
+	const uint32_t signal_count = curr_task->signal_count;
+	const uint64_t spin = ns_to_cycles(SLEEP_SPIN_NS);
+	uint64_t now;
+	uint8_t flags;
+
+	while ((now = get_rdtsc()) < deadline) {
+		if (interruptible && (curr_task->signal_count != signal_count))
+			return -EINTR;
+
+		// the idle task isn't able to block
+		if (curr_task->status == TASK_IDLE) {
+			check_workqueues();
+			PAUSE;
+			continue;
+		}
+
+		if (deadline - now < spin) {
+			PAUSE;
+			continue;
+		}
+
+		/*
+		 * A signal IPI is handled after blocking the task and wakes it
+		 * up again. Without disabling the interrupts, a signal between
+		 * the check and blocking the task would be missed.
+		 */
+		flags = irq_nested_disable();
+
+		if (interruptible) {
+			if (curr_task->signal_count != signal_count) {
+				irq_nested_enable(flags);
+				return -EINTR;
+			}
+
+			curr_task->flags |= TASK_INTERRUPTIBLE;
+		}
+
+		// a premature wakeup re-arms the timer in the next iteration
+		set_timer_cycles(deadline);
+		irq_nested_enable(flags);
+
+		reschedule();
+
+		curr_task->flags &= ~TASK_INTERRUPTIBLE;
+	}
+
+	return 0;
+}
+
+static inline int timespec_valid(const struct timespec* ts)
+{
+	return (ts->tv_sec >= 0) && (ts->tv_nsec >= 0) && (ts->tv_nsec < (long) NSEC_PER_SEC);
+}
+
+/* sleep until deadline, stores the remaining time in rem, if the sleep is interrupted */
+static int do_nanosleep(uint64_t deadline, struct timespec* rem)
+{
+	int ret = timer_wait_cycles(deadline, 1);
+
+	if ((ret == -EINTR) && rem) {
+		const uint64_t now = get_rdtsc();
+		const uint64_t ns = (deadline > now) ? cycles_to_ns(deadline - now) : 0;
+
+		rem->tv_sec = ns / NSEC_PER_SEC;
+		rem->tv_nsec = ns % NSEC_PER_SEC;
+	}
+
+	return ret;
+}
+
+int sys_nanosleep(const struct timespec* req, struct timespec* rem)
+{
+	if (BUILTIN_EXPECT(!req, 0))
+		return -EFAULT;
+	if (BUILTIN_EXPECT(!timespec_valid(req), 0))
+		return -EINVAL;
+
+	const uint64_t ns = (uint64_t) req->tv_sec * NSEC_PER_SEC + req->tv_nsec;
+
+	return do_nanosleep(get_rdtsc() + ns_to_cycles(ns), rem);
+}
+
+int sys_clock_nanosleep(int clock_id, int flags, const struct timespec* req, struct timespec* rem)
+{
+	uint64_t ns, deadline;
+
+	if (BUILTIN_EXPECT(!req, 0))
+		return -EFAULT;
+	if (BUILTIN_EXPECT(!timespec_valid(req), 0))
+		return -EINVAL;
+	// sleeping on CPU time clocks isn't supported
+	if (BUILTIN_EXPECT((clock_id != CLOCK_REALTIME) && (clock_id != CLOCK_MONOTONIC), 0))
+		return -EINVAL;
+
+	ns = (uint64_t) req->tv_sec * NSEC_PER_SEC + req->tv_nsec;
+
+	if (!(flags & TIMER_ABSTIME))
+		return do_nanosleep(get_rdtsc() + ns_to_cycles(ns), rem);
+
+	// convert the absolute time into a value of the cycle counter
+	if (clock_id == CLOCK_REALTIME) {
+		const uint64_t realtime_base = time_data.tp.realtime_base;
+
+		if (ns <= realtime_base)
+			return 0;
+		ns -= realtime_base;
+	}
+
+	deadline = get_boot_tsc() + ns_to_cycles(ns);
+
+	// the remaining time isn't reported for absolute sleeps
+	return timer_wait_cycles(deadline, 1);
+}
@@ -26,15 +26,98 @@
  */
 
 #include <hermit/stdlib.h>
+#include <hermit/errno.h>
+#include <hermit/time.h>
+#include <hermit/tasks.h>
+#include <hermit/syscall.h>
+#include <sys/time.h>
 
-struct itimerval;
+/* converts a timeval into cycles, rounded up */
+static uint64_t timeval_to_cycles(const struct timeval* tv)
+{
+	const uint64_t freq = get_tsc_frequency();
+	uint64_t res;
+
+	res = (uint64_t) tv->tv_sec * freq
+		+ ((uint64_t) tv->tv_usec * freq + 999999ULL) / 1000000ULL;
+
+	// a nonzero time must not disarm the timer
+	if (!res && (tv->tv_sec || tv->tv_usec))
+		res = 1;
+
+	return res;
+}
+
+static void cycles_to_timeval(uint64_t cycles, struct timeval* tv)
+{
+	const uint64_t ns = cycles_to_ns(cycles);
+
+	tv->tv_sec = ns / NSEC_PER_SEC;
+	tv->tv_usec = (ns % NSEC_PER_SEC) / NSEC_PER_USEC;
+}
+
+static inline int timeval_valid(const struct timeval* tv)
+{
+	return (tv->tv_sec >= 0) && (tv->tv_usec >= 0) && (tv->tv_usec < 1000000);
+}
+
+int sys_getitimer(int which, struct itimerval* value)
+{
+	uint64_t val, interval;
+	int ret;
+
+	if (BUILTIN_EXPECT(!value, 0))
+		return -EFAULT;
+
+	ret = get_itimer(which, &val, &interval);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	cycles_to_timeval(val, &value->it_value);
+	cycles_to_timeval(interval, &value->it_interval);
+
+	return 0;
+}
+
+int sys_setitimer(int which, const struct itimerval* value, struct itimerval* ovalue)
+{
+	uint64_t val, interval, oval, ointerval;
+	int ret;
+
+	if (BUILTIN_EXPECT((which < ITIMER_REAL) || (which > ITIMER_PROF), 0))
+		return -EINVAL;
+	if (BUILTIN_EXPECT(!value, 0))
+		return -EFAULT;
+	if (BUILTIN_EXPECT(!timeval_valid(&value->it_value) || !timeval_valid(&value->it_interval), 0))
+		return -EINVAL;
+
+	val = timeval_to_cycles(&value->it_value);
+	interval = timeval_to_cycles(&value->it_interval);
+
+	ret = set_itimer(which, val, interval, &oval, &ointerval);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	if (ovalue) {
+		cycles_to_timeval(oval, &ovalue->it_value);
+		cycles_to_timeval(ointerval, &ovalue->it_interval);
+	}
+
+	return 0;
+}
 
 int getitimer(int which, struct itimerval *value)
 {
+	if (BUILTIN_EXPECT(sys_getitimer(which, value) < 0, 0))
+		return -1;
+
 	return 0;
 }
 
 int setitimer(int which, const struct itimerval *restrict value, struct itimerval * ovalue)
 {
+	if (BUILTIN_EXPECT(sys_setitimer(which, value, ovalue) < 0, 0))
+		return -1;
+
 	return 0;
 }
@@ -0,0 +1,787 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/tmpfs.c
+ * @brief RAM-backed file system
+ *
+ * Small files are stored in memory of the buddy system, larger
+ * files in page-aligned regions of page_alloc().
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdlib.h>
+#include <hermit/string.h>
+#include <hermit/time.h>
+#include <hermit/spinlock.h>
+#include <hermit/errno.h>
+#include <hermit/vma.h>
+#include <hermit/vfs.h>
+#include <asm/page.h>
+#include <sys/poll.h>
+#include <sys/stat.h>
+#include <sys/dirent.h>
+
+typedef struct tmpfs_node {
+	/// name of the directory entry
+	char name[MAX_FNAME];
+	/// file type and permissions
+	uint32_t mode;
+	/// inode number
+	uint64_t ino;
+	/// file size in bytes
+	size_t size;
+	/// size of the data buffer
+	size_t capacity;
+	/// file content
+	char* data;
+	/// number of open files
+	uint32_t opened;
+	/// is the node still part of a directory?
+	uint32_t linked;
+	/// time of last access, modification and status change (in seconds)
+	int64_t atime, mtime, ctime;
+	/// file system of the node
+	struct tmpfs* fs;
+	/// parent directory
+	struct tmpfs_node* parent;
+	/// first entry of a directory
+	struct tmpfs_node* children;
+	/// next entry in the parent directory
+	struct tmpfs_node* next;
+} tmpfs_node_t;
+
+typedef struct tmpfs {
+	/// lock for the whole file system
+	spinlock_t lock;
+	/// root directory
+	tmpfs_node_t root;
+	/// next free inode number
+	uint64_t next_ino;
+} tmpfs_t;
+
+static const file_ops_t tmpfs_file_ops;
+
+static inline int64_t tmpfs_now(void)
+{
+	return get_clock_tick() / TIMER_FREQ;
+}
+
+static inline int tmpfs_isdir(tmpfs_node_t* node)
+{
+	return S_ISDIR(node->mode);
+}
+
+static void tmpfs_free_data(char* data, size_t capacity)
+{
+	if (!data)
+		return;
+
+	if (capacity < PAGE_SIZE)
+		kfree(data);
+	else
+		page_free(data, capacity);
+}
+
+static void tmpfs_free_node(tmpfs_node_t* node)
+{
+	tmpfs_free_data(node->data, node->capacity);
+	kfree(node);
+}
+
+/*
+ * Increase the buffer of a file. The capacity is at least doubled to
+ * reduce the number of copies, if an application appends small pieces.
+ */
+static int tmpfs_reserve(tmpfs_node_t* node, size_t size)
+{
+	size_t capacity;
+	char* data;
+
+	if (size <= node->capacity)
+		return 0;
+
+	capacity = node->capacity * 2;
+	if (capacity < size)
+		capacity = size;
+
+	if (capacity < PAGE_SIZE) {
+		data = kmalloc(capacity);
+	} else {
+		capacity = PAGE_CEIL(capacity);
+		data = page_alloc(capacity, VMA_READ|VMA_WRITE);
+	}
+
+	if (BUILTIN_EXPECT(!data, 0))
+		return -ENOSPC;
+
+	if (node->size)
+		memcpy(data, node->data, node->size);
+	tmpfs_free_data(node->data, node->capacity);
+
+	node->data = data;
+	node->capacity = capacity;
+
+	return 0;
+}
+
+static int tmpfs_resize(tmpfs_node_t* node, size_t size)
+{
+	int ret;
+
+	if (size > node->size) {
+		ret = tmpfs_reserve(node, size);
+		if (BUILTIN_EXPECT(ret, 0))
+			return ret;
+
+		memset(node->data + node->size, 0x00, size - node->size);
+	}
+
+	node->size = size;
+	node->mtime = node->ctime = tmpfs_now();
+
+	return 0;
+}
+
+static tmpfs_node_t* tmpfs_find(tmpfs_node_t* dir, const char* name, size_t len)
+{
+	tmpfs_node_t* node;
+
+	if ((len == 1) && (name[0] == '.'))
+		return dir;
+	if ((len == 2) && (name[0] == '.') && (name[1] == '.'))
+		return dir->parent ? dir->parent : dir;
+
+	for(node=dir->children; node; node=node->next) {
+		if ((strlen(node->name) == len) && !strncmp(node->name, name, len))
+			return node;
+	}
+
+	return NULL;
+}
+
+/*
+ * Walk to the directory, which contains the last component of the path.
+ * If the path refers to the root directory, the length of the last
+ * component is zero.
+ */
+static int tmpfs_walk(tmpfs_t* fs, const char* path, tmpfs_node_t** dir, const char** last, size_t* len)
+{
+	tmpfs_node_t* curr = &fs->root;
+	const char* name;
+	size_t n;
+
+	while(*path == '/')
+		path++;
+
+	while(1) {
+		name = path;
+		for(n=0; name[n] && (name[n] != '/'); n++)
+			;
+
+		path = name + n;
+		while(*path == '/')
+			path++;
+
+		if (!*path)
+			break;
+
+		curr = tmpfs_find(curr, name, n);
+		if (!curr)
+			return -ENOENT;
+		if (!tmpfs_isdir(curr))
+			return -ENOTDIR;
+	}
+
+	if (n >= MAX_FNAME)
+		return -ENAMETOOLONG;
+
+	*dir = curr;
+	*last = name;
+	*len = n;
+
+	return 0;
+}
+
+static int tmpfs_lookup(tmpfs_t* fs, const char* path, tmpfs_node_t** node)
+{
+	tmpfs_node_t* dir;
+	const char* name;
+	size_t len;
+	int ret;
+
+	ret = tmpfs_walk(fs, path, &dir, &name, &len);
+	if (ret)
+		return ret;
+
+	if (!len) {
+		*node = dir;
+		return 0;
+	}
+
+	*node = tmpfs_find(dir, name, len);
+	if (!*node)
+		return -ENOENT;
+
+	return 0;
+}
+
+static tmpfs_node_t* tmpfs_create_node(tmpfs_t* fs, tmpfs_node_t* dir, const char* name, size_t len, uint32_t mode)
+{
+	tmpfs_node_t* node = kmalloc(sizeof(tmpfs_node_t));
+
+	if (BUILTIN_EXPECT(!node, 0))
+		return NULL;
+
+	memset(node, 0x00, sizeof(tmpfs_node_t));
+	memcpy(node->name, name, len);
+	node->name[len] = '\0';
+	node->mode = mode;
+	node->ino = fs->next_ino++;
+	node->linked = 1;
+	node->atime = node->mtime = node->ctime = tmpfs_now();
+	node->fs = fs;
+	node->parent = dir;
+	node->next = dir->children;
+	dir->children = node;
+	dir->mtime = dir->ctime = node->ctime;
+
+	return node;
+}
+
+static void tmpfs_detach(tmpfs_node_t* node)
+{
+	tmpfs_node_t** prev = &node->parent->children;
+
+	while(*prev && (*prev != node))
+		prev = &(*prev)->next;
+	if (*prev)
+		*prev = node->next;
+
+	node->parent->mtime = node->parent->ctime = tmpfs_now();
+	node->next = NULL;
+}
+
+/* Remove a node from its directory and release it, if the node isn't open */
+static void tmpfs_remove(tmpfs_node_t* node)
+{
+	tmpfs_detach(node);
+	node->linked = 0;
+
+	if (!node->opened)
+		tmpfs_free_node(node);
+}
+
+static void tmpfs_fill_stat(tmpfs_node_t* node, struct stat* st)
+{
+	memset(st, 0x00, sizeof(struct stat));
+	st->st_ino = node->ino;
+	st->st_mode = node->mode;
+	st->st_nlink = node->linked;
+	st->st_size = node->size;
+	st->st_atime = node->atime;
+	st->st_mtime = node->mtime;
+	st->st_ctime = node->ctime;
+	st->st_blksize = PAGE_SIZE;
+	st->st_blocks = (node->capacity + 511) / 512;
+}
+
+static ssize_t tmpfs_read(file_t* file, char* buf, size_t len)
+{
+	tmpfs_node_t* node = (tmpfs_node_t*) file->priv;
+	ssize_t ret = 0;
+
+	if (BUILTIN_EXPECT(tmpfs_isdir(node), 0))
+		return -EISDIR;
+	if (BUILTIN_EXPECT((file->flags & O_ACCMODE) == O_WRONLY, 0))
+		return -EBADF;
+
+	spinlock_lock(&node->fs->lock);
+
+	if (file->offset < node->size) {
+		ret = node->size - file->offset;
+		if (ret > len)
+			ret = len;
+
+		memcpy(buf, node->data + file->offset, ret);
+		file->offset += ret;
+	}
+	node->atime = tmpfs_now();
+
+	spinlock_unlock(&node->fs->lock);
+
+	return ret;
+}
+
+static ssize_t tmpfs_write(file_t* file, const char* buf, size_t len)
+{
+	tmpfs_node_t* node = (tmpfs_node_t*) file->priv;
+	ssize_t ret;
+
+	if (BUILTIN_EXPECT((file->flags & O_ACCMODE) == O_RDONLY, 0))
+		return -EBADF;
+
+	spinlock_lock(&node->fs->lock);
+
+	if (file->flags & O_APPEND)
+		file->offset = node->size;
+
+	if (BUILTIN_EXPECT(file->offset + len < file->offset, 0)) {
+		ret = -EFBIG;
+		goto out;
+	}
+
+	if (file->offset + len > node->size) {
+		ret = tmpfs_resize(node, file->offset + len);
+		if (BUILTIN_EXPECT(ret, 0))
+			goto out;
+	}
+
+	memcpy(node->data + file->offset, buf, len);
+	file->offset += len;
+	node->mtime = node->ctime = tmpfs_now();
+	ret = len;
+
+out:
+	spinlock_unlock(&node->fs->lock);
+
+	return ret;
+}
+
+static off_t tmpfs_lseek(file_t* file, off_t offset, int whence)
+{
+	tmpfs_node_t* node = (tmpfs_node_t*) file->priv;
+	off_t ret;
+
+	spinlock_lock(&node->fs->lock);
+
+	switch(whence) {
+	case SEEK_SET:
+		ret = offset;
+		break;
+	case SEEK_CUR:
+		ret = file->offset + offset;
+		break;
+	case SEEK_END:
+		ret = node->size + offset;
+		break;
+	default:
+		ret = -EINVAL;
+		goto out;
+	}
+
+	if (ret < 0) {
+		ret = -EINVAL;
+		goto out;
+	}
+
+	file->offset = ret;
+
+out:
+	spinlock_unlock(&node->fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_fstat(file_t* file, struct stat* st)
+{
+	tmpfs_node_t* node = (tmpfs_node_t*) file->priv;
+
+	spinlock_lock(&node->fs->lock);
+	tmpfs_fill_stat(node, st);
+	spinlock_unlock(&node->fs->lock);
+
+	return 0;
+}
+
+static int tmpfs_poll(file_t* file, int events)
+{
+	// memory never blocks
+	return events & (POLLIN|POLLRDNORM|POLLOUT|POLLWRNORM);
+}
+
+static int tmpfs_truncate(file_t* file, off_t length)
+{
+	tmpfs_node_t* node = (tmpfs_node_t*) file->priv;
+	int ret;
+
+	if (BUILTIN_EXPECT(tmpfs_isdir(node), 0))
+		return -EISDIR;
+
+	spinlock_lock(&node->fs->lock);
+	ret = tmpfs_resize(node, length);
+	spinlock_unlock(&node->fs->lock);
+
+	return ret;
+}
+
+/* The file offset is the index of the next entry, "." and ".." come first */
+static int tmpfs_readdir(file_t* file, struct dirent* entry)
+{
+	tmpfs_node_t* node = (tmpfs_node_t*) file->priv;
+	tmpfs_node_t* child;
+	int ret = 1;
+
+	if (BUILTIN_EXPECT(!tmpfs_isdir(node), 0))
+		return -ENOTDIR;
+
+	spinlock_lock(&node->fs->lock);
+
+	memset(entry, 0x00, sizeof(struct dirent));
+	if (file->offset == 0) {
+		entry->d_ino = node->ino;
+		entry->d_type = DT_DIR;
+		strcpy(entry->d_name, ".");
+	} else if (file->offset == 1) {
+		entry->d_ino = node->parent ? node->parent->ino : node->ino;
+		entry->d_type = DT_DIR;
+		strcpy(entry->d_name, "..");
+	} else {
+		child = node->children;
+		for(off_t i=2; child && (i<file->offset); i++)
+			child = child->next;
+
+		if (!child) {
+			ret = 0;
+			goto out;
+		}
+
+		entry->d_ino = child->ino;
+		entry->d_type = tmpfs_isdir(child) ? DT_DIR : DT_REG;
+		strncpy(entry->d_name, child->name, NAME_MAX);
+	}
+
+	file->offset++;
+
+out:
+	spinlock_unlock(&node->fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_close(file_t* file)
+{
+	tmpfs_node_t* node = (tmpfs_node_t*) file->priv;
+	tmpfs_t* fs = node->fs;
+
+	spinlock_lock(&fs->lock);
+
+	node->opened--;
+	// release an unlinked file with the last close
+	if (!node->opened && !node->linked)
+		tmpfs_free_node(node);
+
+	spinlock_unlock(&fs->lock);
+
+	return 0;
+}
+
+static const file_ops_t tmpfs_file_ops = {
+	.read = tmpfs_read,
+	.write = tmpfs_write,
+	.lseek = tmpfs_lseek,
+	.fstat = tmpfs_fstat,
+	.poll = tmpfs_poll,
+	.truncate = tmpfs_truncate,
+	.readdir = tmpfs_readdir,
+	.close = tmpfs_close,
+};
+
+static int tmpfs_open(void* data, const char* path, int flags, int mode, file_t* file)
+{
+	tmpfs_t* fs = (tmpfs_t*) data;
+	tmpfs_node_t* dir;
+	tmpfs_node_t* node;
+	const char* name;
+	size_t len;
+	int ret;
+
+	spinlock_lock(&fs->lock);
+
+	ret = tmpfs_walk(fs, path, &dir, &name, &len);
+	if (ret)
+		goto out;
+
+	node = len ? tmpfs_find(dir, name, len) : dir;
+	if (!node) {
+		if (!(flags & O_CREAT)) {
+			ret = -ENOENT;
+			goto out;
+		}
+
+		node = tmpfs_create_node(fs, dir, name, len, S_IFREG | (mode & 0777));
+		if (BUILTIN_EXPECT(!node, 0)) {
+			ret = -ENOMEM;
+			goto out;
+		}
+	} else if ((flags & O_CREAT) && (flags & O_EXCL)) {
+		ret = -EEXIST;
+		goto out;
+	} else if (tmpfs_isdir(node)) {
+		if ((flags & O_ACCMODE) != O_RDONLY) {
+			ret = -EISDIR;
+			goto out;
+		}
+	} else if ((flags & O_TRUNC) && ((flags & O_ACCMODE) != O_RDONLY)) {
+		ret = tmpfs_resize(node, 0);
+		if (BUILTIN_EXPECT(ret, 0))
+			goto out;
+	}
+
+	node->opened++;
+	file->ops = &tmpfs_file_ops;
+	file->priv = node;
+	file->offset = 0;
+	ret = 0;
+
+out:
+	spinlock_unlock(&fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_stat(void* data, const char* path, struct stat* st, int follow)
+{
+	tmpfs_t* fs = (tmpfs_t*) data;
+	tmpfs_node_t* node;
+	int ret;
+
+	spinlock_lock(&fs->lock);
+
+	ret = tmpfs_lookup(fs, path, &node);
+	if (!ret)
+		tmpfs_fill_stat(node, st);
+
+	spinlock_unlock(&fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_mkdir(void* data, const char* path, int mode)
+{
+	tmpfs_t* fs = (tmpfs_t*) data;
+	tmpfs_node_t* dir;
+	const char* name;
+	size_t len;
+	int ret;
+
+	spinlock_lock(&fs->lock);
+
+	ret = tmpfs_walk(fs, path, &dir, &name, &len);
+	if (ret)
+		goto out;
+
+	if (!len || tmpfs_find(dir, name, len)) {
+		ret = -EEXIST;
+		goto out;
+	}
+
+	if (BUILTIN_EXPECT(!tmpfs_create_node(fs, dir, name, len, S_IFDIR | (mode & 0777)), 0))
+		ret = -ENOMEM;
+
+out:
+	spinlock_unlock(&fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_rmdir(void* data, const char* path)
+{
+	tmpfs_t* fs = (tmpfs_t*) data;
+	tmpfs_node_t* node;
+	int ret;
+
+	spinlock_lock(&fs->lock);
+
+	ret = tmpfs_lookup(fs, path, &node);
+	if (ret)
+		goto out;
+
+	if (!tmpfs_isdir(node))
+		ret = -ENOTDIR;
+	else if (node == &fs->root)
+		ret = -EBUSY;
+	else if (node->children)
+		ret = -ENOTEMPTY;
+	else
+		tmpfs_remove(node);
+
+out:
+	spinlock_unlock(&fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_unlink(void* data, const char* path)
+{
+	tmpfs_t* fs = (tmpfs_t*) data;
+	tmpfs_node_t* node;
+	int ret;
+
+	spinlock_lock(&fs->lock);
+
+	ret = tmpfs_lookup(fs, path, &node);
+	if (ret)
+		goto out;
+
+	if (tmpfs_isdir(node))
+		ret = -EISDIR;
+	else
+		tmpfs_remove(node);
+
+out:
+	spinlock_unlock(&fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_rename(void* data, const char* oldpath, const char* newpath)
+{
+	tmpfs_t* fs = (tmpfs_t*) data;
+	tmpfs_node_t* node;
+	tmpfs_node_t* dir;
+	tmpfs_node_t* target;
+	tmpfs_node_t* p;
+	const char* name;
+	size_t len;
+	int ret;
+
+	spinlock_lock(&fs->lock);
+
+	ret = tmpfs_lookup(fs, oldpath, &node);
+	if (ret)
+		goto out;
+
+	ret = tmpfs_walk(fs, newpath, &dir, &name, &len);
+	if (ret)
+		goto out;
+
+	if ((node == &fs->root) || !len) {
+		ret = -EBUSY;
+		goto out;
+	}
+
+	// a directory cannot become a subdirectory of itself
+	for(p=dir; p; p=p->parent) {
+		if (p == node) {
+			ret = -EINVAL;
+			goto out;
+		}
+	}
+
+	target = tmpfs_find(dir, name, len);
+	if (target == node)
+		goto out;
+
+	if (target) {
+		if (tmpfs_isdir(node) && !tmpfs_isdir(target)) {
+			ret = -ENOTDIR;
+			goto out;
+		}
+		if (!tmpfs_isdir(node) && tmpfs_isdir(target)) {
+			ret = -EISDIR;
+			goto out;
+		}
+		if (target->children) {
+			ret = -ENOTEMPTY;
+			goto out;
+		}
+
+		tmpfs_remove(target);
+	}
+
+	tmpfs_detach(node);
+	memcpy(node->name, name, len);
+	node->name[len] = '\0';
+	node->parent = dir;
+	node->next = dir->children;
+	dir->children = node;
+	node->ctime = dir->mtime = dir->ctime = tmpfs_now();
+
+out:
+	spinlock_unlock(&fs->lock);
+
+	return ret;
+}
+
+static int tmpfs_opendir(void* data, const char* path, file_t* file)
+{
+	tmpfs_t* fs = (tmpfs_t*) data;
+	tmpfs_node_t* node;
+	int ret;
+
+	spinlock_lock(&fs->lock);
+
+	ret = tmpfs_lookup(fs, path, &node);
+	if (ret)
+		goto out;
+
+	if (!tmpfs_isdir(node)) {
+		ret = -ENOTDIR;
+		goto out;
+	}
+
+	node->opened++;
+	file->ops = &tmpfs_file_ops;
+	file->priv = node;
+	file->offset = 0;
+
+out:
+	spinlock_unlock(&fs->lock);
+
+	return ret;
+}
+
+const fs_ops_t tmpfs_ops = {
+	.open = tmpfs_open,
+	.stat = tmpfs_stat,
+	.mkdir = tmpfs_mkdir,
+	.rmdir = tmpfs_rmdir,
+	.unlink = tmpfs_unlink,
+	.rename = tmpfs_rename,
+	.opendir = tmpfs_opendir,
+};
+
+void* tmpfs_create(void)
+{
+	tmpfs_t* fs = kmalloc(sizeof(tmpfs_t));
+
+	if (BUILTIN_EXPECT(!fs, 0))
+		return NULL;
+
+	memset(fs, 0x00, sizeof(tmpfs_t));
+	spinlock_init(&fs->lock);
+	fs->root.mode = S_IFDIR|0777;
+	fs->root.ino = 1;
+	fs->root.linked = 1;
+	fs->root.atime = fs->root.mtime = fs->root.ctime = tmpfs_now();
+	fs->root.fs = fs;
+	fs->next_ino = 2;
+
+	return fs;
+}
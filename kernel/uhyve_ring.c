@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/**
+ * @file kernel/uhyve_ring.c
+ * @brief Shared-memory hypercall ring of uhyve
+ */
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/stdlib.h>
+#include <hermit/string.h>
+#include <hermit/errno.h>
+#include <hermit/tasks.h>
+#include <hermit/spinlock.h>
+#include <hermit/memory.h>
+#include <hermit/vma.h>
+#include <hermit/logging.h>
+#include <hermit/uhyve_ring.h>
+#include <asm/irq.h>
+#include <asm/irqflags.h>
+#include <asm/processor.h>
+#include <asm/page.h>
+#include <asm/uhyve.h>
+
+/** @brief Submitted hypercall, which is located on the stack of the caller */
+typedef struct uhyve_request {
+	/// Set by the interrupt handler
+	volatile uint8_t done;
+	/// Blocked task
+	tid_t waiter;
+} uhyve_request_t;
+
+static uhyve_ring_t* ring = NULL;
+static spinlock_irqsave_t ring_lock = SPINLOCK_IRQSAVE_INIT;
+
+static void uhyve_ring_handler(struct state* s)
+{
+	uhyve_request_t* req;
+	uint64_t id;
+
+	spinlock_irqsave_lock(&ring_lock);
+
+	while(uhyve_ring_complete(ring, &id)) {
+		req = (uhyve_request_t*) (size_t) id;
+
+		req->done = 1;
+		wakeup_task(req->waiter);
+	}
+
+	spinlock_irqsave_unlock(&ring_lock);
+}
+
+int uhyve_ring_init(void)
+{
+	uhyve_ring_setup_t setup;
+	uhyve_ring_t* r;
+
+	if (!is_uhyve() || !(get_uhyve_features() & UHYVE_FEATURE_RING))
+		return -ENOSYS;
+
+	r = page_alloc(PAGE_SIZE, VMA_READ|VMA_WRITE|VMA_CACHEABLE);
+	if (BUILTIN_EXPECT(!r, 0))
+		return -ENOMEM;
+
+	memset(r, 0x00, PAGE_SIZE);
+
+	setup.ring = virt_to_phys((size_t) r);
+	setup.entries = UHYVE_RING_ENTRIES;
+	setup.irq = UHYVE_IRQ_RING;
+	setup.ret = -ENOSYS;
+
+	irq_install_handler(32+UHYVE_IRQ_RING, uhyve_ring_handler);
+
+	uhyve_send(UHYVE_PORT_RING, (unsigned)virt_to_phys((size_t) &setup));
+
+	if (setup.ret) {
+		LOG_INFO("uhyve doesn't use the hypercall ring: %d\n", setup.ret);
+		irq_uninstall_handler(32+UHYVE_IRQ_RING);
+		page_free(r, PAGE_SIZE);
+		return -ENOSYS;
+	}
+
+	ring = r;
+
+	LOG_INFO("uhyve uses the hypercall ring at %p (irq %d)\n", ring, UHYVE_IRQ_RING);
+
+	return 0;
+}
+
+void uhyve_call(unsigned short port, unsigned int data)
+{
+	uhyve_request_t req;
+	int ret;
+
+	// interrupt handlers and code with disabled interrupts are not able to block
+	if (!ring || !is_irq_enabled()) {
+		uhyve_send(port, data);
+		return;
+	}
+
+	req.done = 0;
+	req.waiter = per_core(current_task)->id;
+
+	spinlock_irqsave_lock(&ring_lock);
+	ret = uhyve_ring_submit(ring, (size_t) &req, port, data);
+	spinlock_irqsave_unlock(&ring_lock);
+
+	// the submission queue is full
+	if (ret < 0) {
+		uhyve_send(port, data);
+		return;
+	}
+
+	// a busy host polls the ring => no exit is required
+	if (ret > 0)
+		uhyve_send(UHYVE_PORT_RING_NOTIFY, 0);
+
+	spinlock_irqsave_lock(&ring_lock);
+
+	while(!req.done) {
+		block_current_task();
+		spinlock_irqsave_unlock(&ring_lock);
+		reschedule();
+		spinlock_irqsave_lock(&ring_lock);
+	}
+
+	spinlock_irqsave_unlock(&ring_lock);
+}
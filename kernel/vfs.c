@@ -0,0 +1,488 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#include <hermit/stddef.h>
+#include <hermit/stdio.h>
+#include <hermit/stdlib.h>
+#include <hermit/string.h>
+#include <hermit/tasks.h>
+#include <hermit/spinlock.h>
+#include <hermit/errno.h>
+#include <hermit/vfs.h>
+#include <hermit/logging.h>
+
+#include <lwip/sockets.h>
+
+#define MAX_MOUNTS	8
+
+typedef struct mount {
+	/// mount point
+	char path[MAX_FNAME];
+	/// length of the mount point
+	size_t len;
+	/// operations of the file system
+	const fs_ops_t* ops;
+	/// private data of the file system
+	void* fs;
+} mount_t;
+
+static mount_t mounts[MAX_MOUNTS];
+static spinlock_irqsave_t mount_lock = SPINLOCK_IRQSAVE_INIT;
+
+int vfs_init(void)
+{
+	void* tmp;
+	int ret;
+
+	ret = vfs_mount("/", &host_fs_ops, NULL);
+	if (BUILTIN_EXPECT(ret, 0))
+		return ret;
+
+	tmp = tmpfs_create();
+	if (BUILTIN_EXPECT(!tmp, 0))
+		return -ENOMEM;
+
+	return vfs_mount(TMPFS_PATH, &tmpfs_ops, tmp);
+}
+
+int vfs_mount(const char* path, const fs_ops_t* ops, void* fs)
+{
+	size_t len;
+	int i, ret = -ENOMEM;
+
+	if (BUILTIN_EXPECT(!path || !ops || !ops->open || (path[0] != '/'), 0))
+		return -EINVAL;
+
+	len = strlen(path);
+	if (BUILTIN_EXPECT(len >= MAX_FNAME, 0))
+		return -EINVAL;
+
+	// ignore trailing slashes, only the root keeps its slash
+	while((len > 1) && (path[len-1] == '/'))
+		len--;
+
+	spinlock_irqsave_lock(&mount_lock);
+
+	for(i=0; i<MAX_MOUNTS; i++) {
+		// replace an existing mount point
+		if (mounts[i].ops && (mounts[i].len == len) && !strncmp(mounts[i].path, path, len))
+			break;
+	}
+
+	if (i >= MAX_MOUNTS) {
+		for(i=0; i<MAX_MOUNTS; i++) {
+			if (!mounts[i].ops)
+				break;
+		}
+	}
+
+	if (i < MAX_MOUNTS) {
+		memcpy(mounts[i].path, path, len);
+		mounts[i].path[len] = '\0';
+		mounts[i].len = len;
+		mounts[i].ops = ops;
+		mounts[i].fs = fs;
+		ret = 0;
+
+		LOG_INFO("Mount file system at %s\n", mounts[i].path);
+	}
+
+	spinlock_irqsave_unlock(&mount_lock);
+
+	return ret;
+}
+
+/*
+ * Find the mount point with the longest matching prefix. Relative paths
+ * are always resolved by the root file system.
+ */
+static mount_t* vfs_lookup(const char* path, const char** rel)
+{
+	mount_t* found = NULL;
+
+	spinlock_irqsave_lock(&mount_lock);
+
+	for(int i=0; i<MAX_MOUNTS; i++) {
+		size_t len = mounts[i].len;
+
+		if (!mounts[i].ops)
+			continue;
+
+		if (len == 1) {
+			// root file system
+			if (!found) {
+				found = mounts+i;
+				*rel = path;
+			}
+			continue;
+		}
+
+		if (strncmp(mounts[i].path, path, len))
+			continue;
+		if ((path[len] != '\0') && (path[len] != '/'))
+			continue;
+
+		if (!found || (found->len < len)) {
+			found = mounts+i;
+			*rel = path + len;
+			while(**rel == '/')
+				(*rel)++;
+		}
+	}
+
+	spinlock_irqsave_unlock(&mount_lock);
+
+	return found;
+}
+
+int fd_table_init(void)
+{
+	task_t* curr_task = per_core(current_task);
+	fd_table_t* table;
+
+	if (curr_task->fd_table)
+		return 0;
+
+	table = kmalloc(sizeof(fd_table_t));
+	if (BUILTIN_EXPECT(!table, 0))
+		return -ENOMEM;
+
+	memset(table, 0x00, sizeof(fd_table_t));
+	atomic_int32_set(&table->refs, 1);
+	spinlock_irqsave_init(&table->lock);
+
+	// standard streams are forwarded to the host
+	for(int i=0; i<3; i++) {
+		table->files[i] = host_file_alloc(i, (i == 0) ? O_RDONLY : O_WRONLY);
+		if (BUILTIN_EXPECT(!table->files[i], 0))
+			goto oom;
+	}
+
+	curr_task->fd_table = table;
+
+	return 0;
+
+oom:
+	for(int i=0; i<3; i++) {
+		if (table->files[i])
+			kfree(table->files[i]);
+	}
+	kfree(table);
+
+	return -ENOMEM;
+}
+
+void fd_table_get(fd_table_t* table)
+{
+	if (table)
+		atomic_int32_inc(&table->refs);
+}
+
+void fd_table_put(void)
+{
+	task_t* curr_task = per_core(current_task);
+	fd_table_t* table = curr_task->fd_table;
+
+	if (!table)
+		return;
+
+	// the last task, which uses the table, closes all files
+	if (atomic_int32_dec(&table->refs) == 0) {
+		for(int i=0; i<MAX_FILES; i++) {
+			if (table->files[i])
+				fd_close(i);
+		}
+
+		kfree(table);
+	}
+
+	curr_task->fd_table = NULL;
+}
+
+file_t* file_alloc(const file_ops_t* ops, int flags)
+{
+	file_t* file = kmalloc(sizeof(file_t));
+
+	if (BUILTIN_EXPECT(!file, 0))
+		return NULL;
+
+	memset(file, 0x00, sizeof(file_t));
+	file->ops = ops;
+	file->flags = flags;
+	file->handle = -1;
+	atomic_int32_set(&file->refs, 1);
+
+	return file;
+}
+
+int fd_install(file_t* file)
+{
+	fd_table_t* table = per_core(current_task)->fd_table;
+	int fd;
+
+	if (BUILTIN_EXPECT(!table || !file, 0))
+		return -EINVAL;
+
+	spinlock_irqsave_lock(&table->lock);
+
+	for(fd=0; fd<MAX_FILES; fd++) {
+		if (!table->files[fd]) {
+			table->files[fd] = file;
+			break;
+		}
+	}
+
+	spinlock_irqsave_unlock(&table->lock);
+
+	if (fd >= MAX_FILES)
+		return -EMFILE;
+
+	return fd;
+}
+
+int fd_get(int fd, file_t** file)
+{
+	fd_table_t* table = per_core(current_task)->fd_table;
+	file_t* f = NULL;
+
+	// LwIP descriptors aren't part of the table
+	if (fd & LWIP_FD_BIT)
+		return socket_get_file(fd, file);
+
+	if (BUILTIN_EXPECT(!table || (fd < 0) || (fd >= MAX_FILES), 0))
+		return -EBADF;
+
+	spinlock_irqsave_lock(&table->lock);
+	f = table->files[fd];
+	if (f)
+		atomic_int32_inc(&f->refs);
+	spinlock_irqsave_unlock(&table->lock);
+
+	if (!f)
+		return -EBADF;
+
+	*file = f;
+
+	return 0;
+}
+
+void fd_put(file_t* file)
+{
+	if (BUILTIN_EXPECT(!file, 0))
+		return;
+
+	if (atomic_int32_dec(&file->refs) == 0) {
+		if (file->ops->close)
+			file->ops->close(file);
+		kfree(file);
+	}
+}
+
+int fd_close(int fd)
+{
+	fd_table_t* table = per_core(current_task)->fd_table;
+	file_t* file;
+
+	// LwIP owns the descriptors of the sockets
+	if (fd & LWIP_FD_BIT)
+		return socket_close(fd);
+
+	if (BUILTIN_EXPECT(!table || (fd < 0) || (fd >= MAX_FILES), 0))
+		return -EBADF;
+
+	spinlock_irqsave_lock(&table->lock);
+	file = table->files[fd];
+	table->files[fd] = NULL;
+	spinlock_irqsave_unlock(&table->lock);
+
+	if (!file)
+		return -EBADF;
+
+	fd_put(file);
+
+	return 0;
+}
+
+int vfs_open(const char* path, int flags, int mode)
+{
+	const char* rel = NULL;
+	mount_t* mnt;
+	file_t* file;
+	int ret;
+
+	if (BUILTIN_EXPECT(!path || !path[0], 0))
+		return -ENOENT;
+
+	mnt = vfs_lookup(path, &rel);
+	if (BUILTIN_EXPECT(!mnt, 0))
+		return -ENOENT;
+
+	file = file_alloc(NULL, flags);
+	if (BUILTIN_EXPECT(!file, 0))
+		return -ENOMEM;
+
+	ret = mnt->ops->open(mnt->fs, rel, flags, mode, file);
+	if (ret) {
+		kfree(file);
+		return ret;
+	}
+
+	ret = fd_install(file);
+	if (ret < 0)
+		fd_put(file);
+
+	return ret;
+}
+
+int vfs_opendir(const char* path)
+{
+	const char* rel = NULL;
+	mount_t* mnt;
+	file_t* file;
+	int ret;
+
+	if (BUILTIN_EXPECT(!path || !path[0], 0))
+		return -ENOENT;
+
+	mnt = vfs_lookup(path, &rel);
+	if (BUILTIN_EXPECT(!mnt, 0))
+		return -ENOENT;
+
+	if (!mnt->ops->opendir)
+		return -ENOSYS;
+
+	file = file_alloc(NULL, O_RDONLY);
+	if (BUILTIN_EXPECT(!file, 0))
+		return -ENOMEM;
+
+	ret = mnt->ops->opendir(mnt->fs, rel, file);
+	if (ret) {
+		kfree(file);
+		return ret;
+	}
+
+	ret = fd_install(file);
+	if (ret < 0)
+		fd_put(file);
+
+	return ret;
+}
+
+int vfs_stat(const char* path, struct stat* st, int follow)
+{
+	const char* rel = NULL;
+	mount_t* mnt;
+
+	if (BUILTIN_EXPECT(!path || !path[0], 0))
+		return -ENOENT;
+
+	mnt = vfs_lookup(path, &rel);
+	if (BUILTIN_EXPECT(!mnt, 0))
+		return -ENOENT;
+
+	if (!mnt->ops->stat)
+		return -ENOSYS;
+
+	return mnt->ops->stat(mnt->fs, rel, st, follow);
+}
+
+int vfs_mkdir(const char* path, int mode)
+{
+	const char* rel = NULL;
+	mount_t* mnt;
+
+	if (BUILTIN_EXPECT(!path || !path[0], 0))
+		return -ENOENT;
+
+	mnt = vfs_lookup(path, &rel);
+	if (BUILTIN_EXPECT(!mnt, 0))
+		return -ENOENT;
+
+	if (!mnt->ops->mkdir)
+		return -ENOSYS;
+
+	return mnt->ops->mkdir(mnt->fs, rel, mode);
+}
+
+int vfs_rmdir(const char* path)
+{
+	const char* rel = NULL;
+	mount_t* mnt;
+
+	if (BUILTIN_EXPECT(!path || !path[0], 0))
+		return -ENOENT;
+
+	mnt = vfs_lookup(path, &rel);
+	if (BUILTIN_EXPECT(!mnt, 0))
+		return -ENOENT;
+
+	if (!mnt->ops->rmdir)
+		return -ENOSYS;
+
+	return mnt->ops->rmdir(mnt->fs, rel);
+}
+
+int vfs_unlink(const char* path)
+{
+	const char* rel = NULL;
+	mount_t* mnt;
+
+	if (BUILTIN_EXPECT(!path || !path[0], 0))
+		return -ENOENT;
+
+	mnt = vfs_lookup(path, &rel);
+	if (BUILTIN_EXPECT(!mnt, 0))
+		return -ENOENT;
+
+	if (!mnt->ops->unlink)
+		return -ENOSYS;
+
+	return mnt->ops->unlink(mnt->fs, rel);
+}
+
+int vfs_rename(const char* oldpath, const char* newpath)
+{
+	const char* oldrel = NULL;
+	const char* newrel = NULL;
+	mount_t* mnt;
+
+	if (BUILTIN_EXPECT(!oldpath || !oldpath[0] || !newpath || !newpath[0], 0))
+		return -ENOENT;
+
+	mnt = vfs_lookup(oldpath, &oldrel);
+	if (BUILTIN_EXPECT(!mnt, 0))
+		return -ENOENT;
+
+	if (vfs_lookup(newpath, &newrel) != mnt)
+		return -EXDEV;
+
+	if (!mnt->ops->rename)
+		return -ENOSYS;
+
+	return mnt->ops->rename(mnt->fs, oldrel, newrel);
+}
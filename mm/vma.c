@@ -221,6 +221,127 @@ int vma_free(size_t start, size_t end)
 	return 0;
 }
 
+int vma_get_flags(size_t addr, uint32_t* flags)
+{
+	vma_t* vma;
+	int ret = -EINVAL;
+
+	spinlock_irqsave_lock(&hermit_mm_lock);
+
+	for(vma=vma_list; vma; vma=vma->next) {
+		if ((addr >= vma->start) && (addr < vma->end)) {
+			*flags = vma->flags;
+			ret = 0;
+			break;
+		}
+	}
+
+	spinlock_irqsave_unlock(&hermit_mm_lock);
+
+	return ret;
+}
+
+int vma_check(size_t start, size_t end, uint32_t flags)
+{
+	vma_t* vma;
+	int ret = 0;
+
+	if (BUILTIN_EXPECT(start >= end, 0))
+		return 0;
+
+	spinlock_irqsave_lock(&hermit_mm_lock);
+
+	// the list is sorted => walk through the range without gaps
+	for(vma=vma_list; vma && (start < end); vma=vma->next) {
+		if (vma->end <= start)
+			continue;
+		if ((vma->start > start) || ((vma->flags & flags) != flags))
+			break;
+		start = vma->end;
+	}
+
+	if (start >= end)
+		ret = 1;
+
+	spinlock_irqsave_unlock(&hermit_mm_lock);
+
+	return ret;
+}
+
+/* Split a VMA at addr, the new VMA starts at addr */
+static int vma_split(vma_t* vma, size_t addr)
+{
+	vma_t* new;
+
+	if ((addr <= vma->start) || (addr >= vma->end))
+		return 0;
+
+	new = kmalloc(sizeof(vma_t));
+	if (BUILTIN_EXPECT(!new, 0))
+		return -ENOMEM;
+
+	new->start = addr;
+	new->end = vma->end;
+	new->flags = vma->flags;
+	new->prev = vma;
+	new->next = vma->next;
+	if (vma->next)
+		vma->next->prev = new;
+	vma->next = new;
+	vma->end = addr;
+
+	return 0;
+}
+
+int vma_set_flags(size_t start, size_t end, uint32_t flags)
+{
+	vma_t* vma;
+	int ret = 0;
+
+	if (BUILTIN_EXPECT(start >= end, 0))
+		return -EINVAL;
+
+	if (!vma_check(start, end, 0))
+		return -ENOMEM;
+
+	spinlock_irqsave_lock(&hermit_mm_lock);
+
+	for(vma=vma_list; vma && (vma->start < end); vma=vma->next) {
+		if (vma->end <= start)
+			continue;
+
+		ret = vma_split(vma, start);
+		if (BUILTIN_EXPECT(ret, 0))
+			break;
+		if (vma->end <= start)
+			continue; // the next iteration handles the new VMA
+
+		ret = vma_split(vma, end);
+		if (BUILTIN_EXPECT(ret, 0))
+			break;
+
+		vma->flags = flags;
+	}
+
+	// merge the VMAs of the range, which are split by previous calls
+	for(vma=vma_list; vma && (vma->start < end); ) {
+		vma_t* next = vma->next;
+
+		if ((vma->start >= start) && next && (next->start < end)
+		    && (vma->end == next->start) && (vma->flags == next->flags)) {
+			vma->end = next->end;
+			vma->next = next->next;
+			if (next->next)
+				next->next->prev = vma;
+			kfree(next);
+		} else vma = next;
+	}
+
+	spinlock_irqsave_unlock(&hermit_mm_lock);
+
+	return ret;
+}
+
 int vma_add(size_t start, size_t end, uint32_t flags)
 {
 	spinlock_irqsave_t* lock = &hermit_mm_lock;
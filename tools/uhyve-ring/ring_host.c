@@ -0,0 +1,302 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+/*
+ * Host-side stand-in for the hypercall ring of uhyve (see
+ * include/hermit/uhyve_ring.h). It contains the consumer of the ring,
+ * which a hypervisor would embed, and a test, which emulates concurrent
+ * guest tasks by threads. The guest side uses the producer of the kernel
+ * (uhyve_ring_submit) and falls back to a port exit as uhyve_call() does,
+ * if the submission queue is full. Guest physical addresses are offsets
+ * in the emulated guest memory.
+ *
+ * The tool is built as part of HermitCore. Build and run it manually on Linux:
+ *   gcc -O2 -Wall -pthread -idirafter ../../include -o ring_host ring_host.c
+ *   ./ring_host
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdint.h>
+#include <string.h>
+#include <unistd.h>
+#include <pthread.h>
+#include <hermit/uhyve_ring.h>
+
+#define UHYVE_PORT_WRITE	0x400
+
+#define NUM_TASKS		8
+#define NUM_CALLS		10000
+/// Number of calls, which don't fit into a full submission queue
+#define NUM_OVERFLOW		8
+
+typedef struct {
+	int fd;
+	const char* buf;
+	size_t len;
+} __attribute__((packed)) uhyve_write_t;
+
+/* emulated guest memory */
+static uint8_t guest_mem[4*1024*1024] __attribute__((aligned(4096)));
+static uhyve_ring_t* ring = (uhyve_ring_t*) guest_mem;
+static size_t next_phys = 4096;
+
+static pthread_mutex_t guest_lock = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t guest_irq = PTHREAD_COND_INITIALIZER;
+static pthread_mutex_t host_lock = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t host_notify = PTHREAD_COND_INITIALIZER;
+static volatile int shutdown_host = 0;
+/// The host doesn't process the ring, e.g. because it's busy
+static volatile int stall_host = 0;
+
+static unsigned long exits = 0;
+static unsigned long irqs = 0;
+static unsigned long fallbacks = 0;
+static unsigned long long written = 0;
+
+/* Handles one hypercall, as uhyve does for an I/O exit */
+static void host_hypercall(uint32_t port, uint64_t data)
+{
+	switch(port) {
+	case UHYVE_PORT_WRITE: {
+		uhyve_write_t* args = (uhyve_write_t*) (guest_mem + data);
+
+		// the fallback runs concurrently to the host thread
+		__sync_fetch_and_add(&written, args->len);
+		break;
+	}
+	default:
+		fprintf(stderr, "unknown port 0x%x\n", port);
+		exit(1);
+	}
+}
+
+/*
+ * Consumer of the hypervisor: processes all pending submissions and
+ * returns the number of completions.
+ */
+static unsigned uhyve_ring_service(uhyve_ring_t* r)
+{
+	unsigned n = 0;
+
+	while(r->sq_head != r->sq_tail) {
+		uhyve_sqe_t* sqe = r->sq + (r->sq_head % UHYVE_RING_ENTRIES);
+
+		__sync_synchronize();
+		host_hypercall(sqe->port, sqe->data);
+
+		// the completion queue can't overflow: the guest has at most
+		// UHYVE_RING_ENTRIES requests in flight
+		r->cq[r->cq_tail % UHYVE_RING_ENTRIES].id = sqe->id;
+		__sync_synchronize();
+		r->cq_tail++;
+		r->sq_head++;
+		n++;
+	}
+
+	return n;
+}
+
+static void* host_thread(void* arg)
+{
+	while(!shutdown_host) {
+		pthread_mutex_lock(&host_lock);
+		while(stall_host && !shutdown_host)
+			pthread_cond_wait(&host_notify, &host_lock);
+		pthread_mutex_unlock(&host_lock);
+
+		unsigned n = uhyve_ring_service(ring);
+
+		if (n) {
+			// inject UHYVE_IRQ_RING
+			pthread_mutex_lock(&guest_lock);
+			irqs++;
+			pthread_cond_broadcast(&guest_irq);
+			pthread_mutex_unlock(&guest_lock);
+			continue;
+		}
+
+		// poll for a while, before the guest has to notify us
+		for(int i=0; (i<10000) && (ring->sq_head == ring->sq_tail); i++)
+			__builtin_ia32_pause();
+		if (ring->sq_head != ring->sq_tail)
+			continue;
+
+		// idle => ask the guest for a notification
+		pthread_mutex_lock(&host_lock);
+		ring->flags |= UHYVE_RING_NEED_NOTIFY;
+		__sync_synchronize();
+		while((ring->sq_head == ring->sq_tail) && !shutdown_host)
+			pthread_cond_wait(&host_notify, &host_lock);
+		ring->flags &= ~UHYVE_RING_NEED_NOTIFY;
+		pthread_mutex_unlock(&host_lock);
+	}
+
+	return NULL;
+}
+
+/* Guest side: uhyve_ring_handler() and uhyve_call() of kernel/uhyve_ring.c */
+typedef struct {
+	volatile int done;
+} request_t;
+
+static void guest_irq_handler(void)
+{
+	uint64_t id;
+
+	while(uhyve_ring_complete(ring, &id)) {
+		request_t* req = (request_t*) (size_t) id;
+
+		req->done = 1;
+	}
+}
+
+static void guest_call(uint32_t port, uint64_t data)
+{
+	request_t req = { 0 };
+	int ret;
+
+	pthread_mutex_lock(&guest_lock);
+
+	ret = uhyve_ring_submit(ring, (size_t) &req, port, data);
+
+	// the submission queue is full => emulates the port exit
+	if (ret < 0) {
+		fallbacks++;
+		pthread_mutex_unlock(&guest_lock);
+		host_hypercall(port, data);
+		return;
+	}
+
+	if (ret > 0) {
+		// emulates the exit on UHYVE_PORT_RING_NOTIFY
+		pthread_mutex_lock(&host_lock);
+		exits++;
+		pthread_cond_signal(&host_notify);
+		pthread_mutex_unlock(&host_lock);
+	}
+
+	while(!req.done) {
+		pthread_cond_wait(&guest_irq, &guest_lock);
+		guest_irq_handler();
+	}
+
+	pthread_mutex_unlock(&guest_lock);
+}
+
+static uhyve_write_t* guest_args(size_t* phys)
+{
+	pthread_mutex_lock(&guest_lock);
+	*phys = next_phys;
+	next_phys += 4096;
+	pthread_mutex_unlock(&guest_lock);
+
+	return (uhyve_write_t*) (guest_mem + *phys);
+}
+
+static void* guest_task(void* arg)
+{
+	int calls = (int) (size_t) arg;
+	uhyve_write_t* args;
+	size_t phys;
+
+	args = guest_args(&phys);
+
+	for(int i=0; i<calls; i++) {
+		args->fd = 1;
+		args->buf = NULL;
+		args->len = 1;
+
+		guest_call(UHYVE_PORT_WRITE, phys);
+	}
+
+	return NULL;
+}
+
+static void set_stall(int stall)
+{
+	pthread_mutex_lock(&host_lock);
+	stall_host = stall;
+	pthread_cond_signal(&host_notify);
+	pthread_mutex_unlock(&host_lock);
+}
+
+int main(int argc, char** argv)
+{
+	pthread_t host, tasks[UHYVE_RING_ENTRIES+NUM_OVERFLOW];
+	unsigned long long expected;
+	unsigned long overflow = 0;
+	int full = 0;
+
+	memset(guest_mem, 0x00, sizeof(guest_mem));
+
+	pthread_create(&host, NULL, host_thread, NULL);
+
+	// concurrent hypercalls
+	for(int i=0; i<NUM_TASKS; i++)
+		pthread_create(tasks+i, NULL, guest_task, (void*) (size_t) NUM_CALLS);
+	for(int i=0; i<NUM_TASKS; i++)
+		pthread_join(tasks[i], NULL);
+
+	// more pending hypercalls than entries of the submission queue
+	set_stall(1);
+	for(int i=0; i<UHYVE_RING_ENTRIES+NUM_OVERFLOW; i++)
+		pthread_create(tasks+i, NULL, guest_task, (void*) (size_t) 1);
+
+	for(int i=0; (i<10000) && !full; i++) {
+		usleep(1000);
+
+		pthread_mutex_lock(&guest_lock);
+		overflow = fallbacks;
+		full = (ring->sq_tail - ring->sq_head == UHYVE_RING_ENTRIES) && (overflow == NUM_OVERFLOW);
+		pthread_mutex_unlock(&guest_lock);
+	}
+
+	set_stall(0);
+	for(int i=0; i<UHYVE_RING_ENTRIES+NUM_OVERFLOW; i++)
+		pthread_join(tasks[i], NULL);
+
+	pthread_mutex_lock(&host_lock);
+	shutdown_host = 1;
+	pthread_cond_signal(&host_notify);
+	pthread_mutex_unlock(&host_lock);
+	pthread_join(host, NULL);
+
+	expected = (unsigned long long) NUM_TASKS*NUM_CALLS + UHYVE_RING_ENTRIES + NUM_OVERFLOW;
+
+	printf("hypercalls %llu, bytes %llu, notifications %lu, interrupts %lu, fallbacks %lu\n",
+		expected, written, exits, irqs, fallbacks);
+
+	if ((written != expected) || !full || (fallbacks != NUM_OVERFLOW)) {
+		printf("FAILED\n");
+		return 1;
+	}
+
+	printf("OK\n");
+
+	return 0;
+}
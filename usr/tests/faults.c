@@ -0,0 +1,156 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+#include <stdint.h>
+#include <errno.h>
+#include <hermit/syscall.h>
+
+#include "tests.h"
+
+static volatile int segv_count = 0;
+static volatile int segv_code = 0;
+static void* volatile segv_addr = NULL;
+
+static volatile int altstack_used = 0;
+static unsigned char altstack[4 * PAGE_SIZE];
+
+static volatile int fpe_count = 0;
+static volatile int fpe_code = 0;
+
+static void segv_handler(int sig, ksiginfo_t* info, void* ctx)
+{
+	segv_count++;
+	segv_code = info->si_code;
+	segv_addr = info->si_addr;
+
+	// the handler runs on the alternate stack, if it's registered with SA_ONSTACK
+	if (((size_t) &sig > (size_t) altstack) && ((size_t) &sig <= (size_t) altstack + sizeof(altstack)))
+		altstack_used++;
+
+	// grant the access => the faulting instruction is repeated
+	sys_mprotect((void*) ((size_t) info->si_addr & ~(PAGE_SIZE-1)), PAGE_SIZE, PROT_READ|PROT_WRITE);
+}
+
+#ifdef __x86_64__
+static void fpe_handler(int sig, ksiginfo_t* info, void* ctx)
+{
+	kmregs_t* regs = (kmregs_t*) ctx;
+
+	fpe_count++;
+	fpe_code = info->si_code;
+
+	// skip the faulting "divl %ecx" (2 bytes)
+	regs->rip += 2;
+}
+#endif
+
+int main(int argc, char **argv)
+{
+	struct sigaction act;
+	volatile unsigned char* p;
+
+	memset(&act, 0x00, sizeof(act));
+	act.sa_sigaction = segv_handler;
+	act.sa_flags = SA_SIGINFO;
+	CHECK(sys_sigaction(SIGSEGV, &act, NULL) == 0);
+
+	p = sys_mmap(NULL, 2 * PAGE_SIZE, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+	CHECK((long) p > 0);
+	p[PAGE_SIZE] = 1;
+
+	// a write to a read-only page is caught by the handler
+	CHECK(sys_mprotect((void*) (p + PAGE_SIZE), PAGE_SIZE, PROT_READ) == 0);
+	p[PAGE_SIZE + 8] = 42;
+	CHECK(segv_count == 1);
+	CHECK(segv_code == SEGV_ACCERR);
+	CHECK(segv_addr == p + PAGE_SIZE + 8);
+	CHECK(p[PAGE_SIZE + 8] == 42);
+
+	// the handler is able to handle the same fault again
+	CHECK(sys_mprotect((void*) p, PAGE_SIZE, PROT_READ) == 0);
+	p[0] = 7;
+	CHECK(segv_count == 2);
+	CHECK(segv_addr == p);
+	CHECK(p[0] == 7);
+
+	CHECK(altstack_used == 0);
+
+	// run the handler on an alternate stack
+	{
+		struct sigaltstack ss, old;
+
+		ss.ss_sp = altstack;
+		ss.ss_size = MINSIGSTKSZ - 1;
+		ss.ss_flags = 0;
+		CHECK(sys_sigaltstack(&ss, NULL) == -ENOMEM);
+		ss.ss_size = sizeof(altstack);
+		ss.ss_flags = SS_ONSTACK;
+		CHECK(sys_sigaltstack(&ss, NULL) == -EINVAL);
+		ss.ss_flags = 0;
+		CHECK(sys_sigaltstack(&ss, &old) == 0);
+		CHECK(old.ss_flags == SS_DISABLE);
+		CHECK(sys_sigaltstack(NULL, &old) == 0);
+		CHECK(old.ss_sp == altstack);
+		CHECK(old.ss_size == sizeof(altstack));
+		CHECK(old.ss_flags == 0);
+	}
+
+	act.sa_flags = SA_SIGINFO|SA_ONSTACK;
+	CHECK(sys_sigaction(SIGSEGV, &act, NULL) == 0);
+	CHECK(sys_mprotect((void*) p, PAGE_SIZE, PROT_READ) == 0);
+	p[1] = 8;
+	CHECK(segv_count == 3);
+	CHECK(altstack_used == 1);
+	CHECK(p[1] == 8);
+
+	CHECK(sys_munmap((void*) p, 2 * PAGE_SIZE) == 0);
+
+#ifdef __x86_64__
+	memset(&act, 0x00, sizeof(act));
+	act.sa_sigaction = fpe_handler;
+	act.sa_flags = SA_SIGINFO;
+	CHECK(sys_sigaction(SIGFPE, &act, NULL) == 0);
+
+	// an integer division by zero continues behind the instruction
+	{
+		uint32_t eax = 10, edx = 0, ecx = 0;
+
+		asm volatile("divl %%ecx" : "+a"(eax), "+d"(edx) : "c"(ecx) : "cc");
+		CHECK(fpe_count == 1);
+		CHECK(fpe_code == FPE_INTDIV);
+		CHECK(eax == 10);
+	}
+#endif
+
+	printf("fault signal test passed\n");
+
+	return 0;
+}
@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+#include <errno.h>
+#include <time.h>
+#include <hermit/syscall.h>
+#include <hermit/futex.h>
+
+#include "tests.h"
+
+static int word = 0;
+static volatile tid_t waiter_id = 0;
+static volatile int handled = 0;
+
+static void handler(int sig)
+{
+	handled++;
+}
+
+static int waiter(void* arg)
+{
+	waiter_id = sys_getpid();
+
+	return sys_futex(&word, FUTEX_WAIT|FUTEX_PRIVATE_FLAG, 0, NULL, NULL, 0);
+}
+
+/* wait until the waiter is blocked in the kernel */
+static void wait_for_waiter(void)
+{
+	while(!waiter_id)
+		sys_yield();
+	sys_msleep(50);
+}
+
+int main(int argc, char **argv)
+{
+	struct timespec timeout;
+	struct sigaction act;
+	tid_t id;
+	int status;
+
+	// the value has already changed
+	CHECK(sys_futex(&word, FUTEX_WAIT, 1, NULL, NULL, 0) == -EAGAIN);
+
+	// nobody waits
+	CHECK(sys_futex(&word, FUTEX_WAKE, 1, NULL, NULL, 0) == 0);
+
+	// a relative timeout
+	timeout.tv_sec = 0;
+	timeout.tv_nsec = 20000000;
+	CHECK(sys_futex(&word, FUTEX_WAIT, 0, &timeout, NULL, 0) == -KERNEL_ETIMEDOUT);
+	timeout.tv_nsec = 1000000000;
+	CHECK(sys_futex(&word, FUTEX_WAIT, 0, &timeout, NULL, 0) == -EINVAL);
+
+	// wake up a blocked task
+	CHECK(sys_clone_flags(&id, waiter, NULL, CLONE_JOINABLE) == 0);
+	wait_for_waiter();
+	__atomic_store_n(&word, 1, __ATOMIC_SEQ_CST);
+	CHECK(sys_futex(&word, FUTEX_WAKE, 1, NULL, NULL, 0) == 1);
+	CHECK(sys_join(id, &status) == 0);
+	CHECK(status == 0);
+
+	// a signal without SA_RESTART interrupts the wait
+	memset(&act, 0x00, sizeof(act));
+	act.sa_handler = handler;
+	CHECK(sys_sigaction(SIGUSR1, &act, NULL) == 0);
+
+	__atomic_store_n(&word, 0, __ATOMIC_SEQ_CST);
+	waiter_id = 0;
+	CHECK(sys_clone_flags(&id, waiter, NULL, CLONE_JOINABLE) == 0);
+	wait_for_waiter();
+	CHECK(sys_kill(waiter_id, SIGUSR1) == 0);
+	CHECK(sys_join(id, &status) == 0);
+	CHECK(status == -EINTR);
+	CHECK(handled == 1);
+	// the interrupted waiter has left the wait queue
+	CHECK(sys_futex(&word, FUTEX_WAKE, 1, NULL, NULL, 0) == 0);
+
+	printf("futex test passed\n");
+
+	return 0;
+}
@@ -0,0 +1,122 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#include <stdlib.h>
+#include <stdio.h>
+#include <errno.h>
+#include <hermit/syscall.h>
+
+#include "tests.h"
+
+/* more tasks than the kernel is able to hold at once */
+#define DETACHED_TASKS	256
+
+static volatile int finished = 0;
+
+static int sleeper(void* arg)
+{
+	sys_msleep(50);
+
+	return (int) (size_t) arg;
+}
+
+static int runner(void* arg)
+{
+	__atomic_add_fetch(&finished, 1, __ATOMIC_SEQ_CST);
+
+	return 0;
+}
+
+/* the slot of a detached task becomes available shortly after its termination */
+static int clone_retry(tid_t* id, void* ep, void* arg, unsigned int flags)
+{
+	int i, ret;
+
+	for(i=0; i<100; i++) {
+		ret = sys_clone_flags(id, ep, arg, flags);
+		if (!ret)
+			break;
+		sys_msleep(1);
+	}
+
+	return ret;
+}
+
+int main(int argc, char **argv)
+{
+	tid_t id;
+	int status, i;
+
+	// join a running task
+	CHECK(sys_clone_flags(&id, sleeper, (void*) 42, CLONE_JOINABLE) == 0);
+	status = 0;
+	CHECK(sys_join(id, &status) == 0);
+	CHECK(status == 42);
+	// the task has been reaped by the join
+	CHECK(sys_join(id, &status) < 0);
+
+	// join a task, which is already finished
+	CHECK(sys_clone_flags(&id, sleeper, (void*) 7, CLONE_JOINABLE) == 0);
+	sys_msleep(200);
+	status = 0;
+	CHECK(sys_join(id, &status) == 0);
+	CHECK(status == 7);
+
+	// a task can't join itself
+	CHECK(sys_join(sys_getpid(), NULL) < 0);
+	CHECK(sys_clone_flags(&id, sleeper, NULL, 0x80) == -EINVAL);
+
+	// cloned tasks are detached by default
+	CHECK(sys_clone(&id, sleeper, NULL) == 0);
+	CHECK(sys_join(id, NULL) == -EINVAL);
+	CHECK(sys_detach(id) == -EINVAL);
+
+	// a detached joinable task can't be joined anymore
+	CHECK(sys_clone_flags(&id, sleeper, NULL, CLONE_JOINABLE) == 0);
+	CHECK(sys_detach(id) == 0);
+	CHECK(sys_join(id, NULL) == -EINVAL);
+
+	// finished detached tasks don't occupy a slot of the task table
+	sys_msleep(100);
+	for(i=0; i<DETACHED_TASKS; i++) {
+		CHECK(clone_retry(&id, runner, NULL, 0) == 0);
+		while(finished <= i)
+			sys_yield();
+	}
+
+	// joined tasks release their slots as well
+	for(i=0; i<DETACHED_TASKS; i++) {
+		CHECK(clone_retry(&id, runner, NULL, CLONE_JOINABLE) == 0);
+		CHECK(sys_join(id, &status) == 0);
+		CHECK(status == 0);
+	}
+
+	printf("join test passed\n");
+
+	return 0;
+}
@@ -0,0 +1,153 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <hermit/syscall.h>
+
+#include "tests.h"
+
+/* sys_mmap reports errors as a negative errno value */
+#define MMAP_FAILED(p)	((long) (p) < 0 && (long) (p) > -4096)
+
+static void test_file(void)
+{
+	const char name[] = "mmap_test.dat";
+	const size_t len = 2 * PAGE_SIZE + 100;
+	unsigned char* p;
+	size_t i;
+	int fd;
+
+	// fill a host file with a known pattern
+	fd = sys_open(name, O_RDWR|O_CREAT|O_TRUNC, 0644);
+	CHECK(fd >= 0);
+	for(i=0; i<len; i++) {
+		unsigned char c = (unsigned char) (i * 7);
+		CHECK(sys_write(fd, (const char*) &c, 1) == 1);
+	}
+	CHECK(sys_close(fd) == 0);
+
+	fd = sys_open(name, O_RDONLY, 0);
+	CHECK(fd >= 0);
+
+	p = sys_mmap(NULL, len, PROT_READ, MAP_PRIVATE, fd, 0);
+	if ((long) p == -ENODEV) {
+		// file mappings are only supported by uhyve
+		printf("file mappings aren't supported, skip the test\n");
+		goto out;
+	}
+	CHECK(!MMAP_FAILED(p));
+
+	for(i=0; i<len; i++)
+		CHECK(p[i] == (unsigned char) (i * 7));
+	// the remainder of the last page is zero filled
+	for(i=len; i<3*PAGE_SIZE; i++)
+		CHECK(p[i] == 0);
+
+	// file mappings are read-only
+	CHECK(sys_mprotect(p, PAGE_SIZE, PROT_READ|PROT_WRITE) == -EACCES);
+	CHECK((long) sys_mmap(NULL, len, PROT_READ|PROT_WRITE, MAP_PRIVATE, fd, 0) == -EACCES);
+	CHECK(MMAP_FAILED(sys_mmap(NULL, len, PROT_READ, MAP_PRIVATE, fd, 1)));
+
+	// the mapping stays valid after closing the descriptor
+	CHECK(sys_close(fd) == 0);
+	fd = -1;
+	CHECK(p[PAGE_SIZE + 3] == (unsigned char) ((PAGE_SIZE + 3) * 7));
+
+	// map the file at an offset
+	CHECK(sys_munmap(p, len) == 0);
+	fd = sys_open(name, O_RDONLY, 0);
+	CHECK(fd >= 0);
+	p = sys_mmap(NULL, PAGE_SIZE, PROT_READ, MAP_PRIVATE, fd, PAGE_SIZE);
+	CHECK(!MMAP_FAILED(p));
+	for(i=0; i<PAGE_SIZE; i++)
+		CHECK(p[i] == (unsigned char) ((PAGE_SIZE + i) * 7));
+	CHECK(sys_munmap(p, PAGE_SIZE) == 0);
+
+out:
+	if (fd >= 0)
+		sys_close(fd);
+	sys_unlink(name);
+}
+
+int main(int argc, char **argv)
+{
+	const size_t len = 4 * PAGE_SIZE;
+	unsigned char* p;
+	unsigned char* q;
+	size_t i;
+
+	p = sys_mmap(NULL, len, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+	CHECK(!MMAP_FAILED(p));
+	CHECK(((size_t) p % PAGE_SIZE) == 0);
+
+	// anonymous memory is zero filled on demand
+	for(i=0; i<len; i++)
+		CHECK(p[i] == 0);
+
+	for(i=0; i<len; i++)
+		p[i] = (unsigned char) i;
+	for(i=0; i<len; i++)
+		CHECK(p[i] == (unsigned char) i);
+
+	// invalid arguments
+	CHECK(MMAP_FAILED(sys_mmap(NULL, 0, PROT_READ, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0)));
+	CHECK(MMAP_FAILED(sys_mmap(NULL, len, PROT_READ, MAP_ANONYMOUS, -1, 0)));
+	CHECK(MMAP_FAILED(sys_mmap(NULL, len, PROT_READ, MAP_SHARED|MAP_PRIVATE|MAP_ANONYMOUS, -1, 0)));
+	CHECK(sys_munmap(p + 1, PAGE_SIZE) < 0);
+	CHECK(sys_mprotect(p + 1, PAGE_SIZE, PROT_READ) < 0);
+
+	// drop the write permission of the second page and restore it again
+	CHECK(sys_mprotect(p + PAGE_SIZE, PAGE_SIZE, PROT_READ) == 0);
+	CHECK(p[PAGE_SIZE + 1] == 1);
+	CHECK(sys_mprotect(p + PAGE_SIZE, PAGE_SIZE, PROT_READ|PROT_WRITE) == 0);
+	p[PAGE_SIZE + 1] = 0xAA;
+	CHECK(p[PAGE_SIZE + 1] == 0xAA);
+
+	// unmap the pages in the middle and map them again at a fixed address
+	CHECK(sys_munmap(p + PAGE_SIZE, 2 * PAGE_SIZE) == 0);
+	CHECK(p[0] == 0);
+	CHECK(p[3 * PAGE_SIZE] == (unsigned char) (3 * PAGE_SIZE));
+
+	q = sys_mmap(p + PAGE_SIZE, 2 * PAGE_SIZE, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS|MAP_FIXED, -1, 0);
+	CHECK(q == p + PAGE_SIZE);
+	for(i=0; i<2*PAGE_SIZE; i++)
+		CHECK(q[i] == 0);
+
+	CHECK(sys_munmap(p, len) == 0);
+	CHECK(sys_munmap(p, len) < 0);
+
+	test_file();
+
+	printf("mmap test passed\n");
+
+	return 0;
+}
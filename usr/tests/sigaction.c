@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+#include <errno.h>
+#include <hermit/syscall.h>
+
+#include "tests.h"
+
+static volatile int received[32];
+static volatile int info_code = 0;
+static volatile int info_pid = 0;
+static volatile unsigned long handler_mask = 0;
+
+static void handler(int sig)
+{
+	received[sig]++;
+	sys_sigprocmask(SIG_BLOCK, NULL, (unsigned long*) &handler_mask);
+}
+
+static void info_handler(int sig, ksiginfo_t* info, void* ctx)
+{
+	received[sig]++;
+	info_code = info->si_code;
+	info_pid = info->si_pid;
+}
+
+#ifdef __x86_64__
+#define MXCSR_RC_UP	0x5f80
+#define MXCSR_RC_ZERO	0x7f80
+
+static inline unsigned int get_mxcsr(void)
+{
+	unsigned int mxcsr;
+
+	asm volatile ("stmxcsr %0" : "=m"(mxcsr));
+
+	return mxcsr;
+}
+
+static inline void set_mxcsr(unsigned int mxcsr)
+{
+	asm volatile ("ldmxcsr %0" :: "m"(mxcsr));
+}
+
+static volatile double fpu_result = 0.0;
+
+/* the handler changes the FPU state of the interrupted context */
+static void fpu_handler(int sig)
+{
+	volatile double x = 1.0;
+
+	set_mxcsr(MXCSR_RC_UP);
+	fpu_result = x / 3.0;
+	received[sig]++;
+}
+#endif
+
+/* the signal is delivered asynchronously by an IPI */
+static void wait_for(int sig, int count)
+{
+	int i;
+
+	for(i=0; (i<100) && (received[sig] < count); i++)
+		sys_msleep(1);
+}
+
+int main(int argc, char **argv)
+{
+	const tid_t self = sys_getpid();
+	struct sigaction act, old;
+	unsigned long set;
+
+	memset(&act, 0x00, sizeof(act));
+	act.sa_handler = handler;
+	act.sa_mask = sigmask(SIGUSR2);
+	CHECK(sys_sigaction(SIGUSR1, &act, NULL) == 0);
+	CHECK(sys_sigaction(SIGUSR1, NULL, &old) == 0);
+	CHECK(old.sa_handler == handler);
+	CHECK(sys_sigaction(0, &act, NULL) == -EINVAL);
+	CHECK(sys_sigaction(32, &act, NULL) == -EINVAL);
+
+	// delivery with the signal and sa_mask blocked during the handler
+	CHECK(sys_kill(self, SIGUSR1) == 0);
+	wait_for(SIGUSR1, 1);
+	CHECK(received[SIGUSR1] == 1);
+	CHECK(handler_mask & sigmask(SIGUSR1));
+	CHECK(handler_mask & sigmask(SIGUSR2));
+	CHECK(sys_sigprocmask(SIG_BLOCK, NULL, &set) == 0);
+	CHECK(!(set & sigmask(SIGUSR1)));
+
+	// a blocked signal stays pending until it's unblocked
+	set = sigmask(SIGUSR1);
+	CHECK(sys_sigprocmask(SIG_BLOCK, &set, NULL) == 0);
+	CHECK(sys_kill(self, SIGUSR1) == 0);
+	CHECK(sys_kill(self, SIGUSR1) == 0);
+	sys_msleep(10);
+	CHECK(received[SIGUSR1] == 1);
+	CHECK(sys_sigpending(&set) == 0);
+	CHECK(set & sigmask(SIGUSR1));
+	set = sigmask(SIGUSR1);
+	CHECK(sys_sigprocmask(SIG_UNBLOCK, &set, NULL) == 0);
+	wait_for(SIGUSR1, 2);
+	// standard signals aren't queued
+	sys_msleep(10);
+	CHECK(received[SIGUSR1] == 2);
+	CHECK(sys_sigpending(&set) == 0);
+	CHECK(!(set & sigmask(SIGUSR1)));
+	CHECK(sys_sigprocmask(42, &set, NULL) == -EINVAL);
+
+	// ignored signals are discarded
+	act.sa_handler = SIG_IGN;
+	CHECK(sys_sigaction(SIGUSR1, &act, NULL) == 0);
+	CHECK(sys_kill(self, SIGUSR1) == 0);
+	sys_msleep(10);
+	CHECK(received[SIGUSR1] == 2);
+
+	// siginfo and a one-shot handler
+	memset(&act, 0x00, sizeof(act));
+	act.sa_sigaction = info_handler;
+	act.sa_flags = SA_SIGINFO|SA_RESETHAND;
+	CHECK(sys_sigaction(SIGUSR2, &act, NULL) == 0);
+	CHECK(sys_kill(self, SIGUSR2) == 0);
+	wait_for(SIGUSR2, 1);
+	CHECK(received[SIGUSR2] == 1);
+	CHECK(info_code == SI_USER);
+	CHECK(info_pid == self);
+	CHECK(sys_sigaction(SIGUSR2, NULL, &old) == 0);
+	CHECK(old.sa_handler == SIG_DFL);
+
+	// sigsuspend waits with a temporary mask for a blocked signal
+	act.sa_handler = handler;
+	act.sa_flags = 0;
+	CHECK(sys_sigaction(SIGUSR2, &act, NULL) == 0);
+	set = sigmask(SIGUSR2);
+	CHECK(sys_sigprocmask(SIG_BLOCK, &set, NULL) == 0);
+	CHECK(sys_kill(self, SIGUSR2) == 0);
+	set = 0;
+	CHECK(sys_sigsuspend(&set) == -EINTR);
+	CHECK(received[SIGUSR2] == 2);
+	CHECK(sys_sigprocmask(SIG_BLOCK, NULL, &set) == 0);
+	CHECK(set & sigmask(SIGUSR2));
+
+#ifdef __x86_64__
+	// the FPU state is restored after the handler
+	const unsigned int mxcsr = get_mxcsr();
+	set = sigmask(SIGUSR1);
+	CHECK(sys_sigprocmask(SIG_UNBLOCK, &set, NULL) == 0);
+	memset(&act, 0x00, sizeof(act));
+	act.sa_handler = fpu_handler;
+	CHECK(sys_sigaction(SIGUSR1, &act, NULL) == 0);
+	set_mxcsr(MXCSR_RC_ZERO);
+	CHECK(sys_kill(self, SIGUSR1) == 0);
+	wait_for(SIGUSR1, 3);
+	CHECK(received[SIGUSR1] == 3);
+	CHECK(get_mxcsr() == MXCSR_RC_ZERO);
+	set_mxcsr(mxcsr);
+#endif
+
+	printf("sigaction test passed\n");
+
+	return 0;
+}
@@ -0,0 +1,125 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+/*
+ * Common definitions of the tests. The kernel headers sys/signal.h and
+ * sys/mman.h clash with newlib and aren't installed. Hence, this header
+ * provides the parts of the kernel ABI, which are used by the tests of
+ * the system calls.
+ */
+
+#ifndef __TESTS_H__
+#define __TESTS_H__
+
+#include <stdlib.h>
+#include <stdio.h>
+#include <stdint.h>
+
+#define CHECK(exp) \
+	if (!(exp)) { \
+		fprintf(stderr, "ERROR: %s failed (line %d)\n", #exp, __LINE__); \
+		exit(-1); \
+	}
+
+#define PAGE_SIZE	4096
+
+/* error numbers of the kernel, which differ from newlib */
+#define KERNEL_ETIMEDOUT	110
+
+/* memory mappings (see include/sys/mman.h) */
+#ifndef PROT_READ
+#define PROT_NONE	0x0
+#define PROT_READ	0x1
+#define PROT_WRITE	0x2
+#define PROT_EXEC	0x4
+#define MAP_SHARED	0x01
+#define MAP_PRIVATE	0x02
+#define MAP_FIXED	0x10
+#define MAP_ANONYMOUS	0x20
+#endif
+
+/* signals (see include/sys/signal.h) */
+#define SIGFPE		8
+#define SIGSEGV		11
+#define SIGUSR1		30
+#define SIGUSR2		31
+
+#define sigmask(sig)	(1UL << (sig))
+
+#define SIG_SETMASK	0
+#define SIG_BLOCK	1
+#define SIG_UNBLOCK	2
+
+#define SA_SIGINFO	0x00000002
+#define SA_ONSTACK	0x08000000
+#define SA_RESETHAND	0x80000000
+
+#define SS_ONSTACK	1
+#define SS_DISABLE	2
+#define MINSIGSTKSZ	2048
+
+#define SI_USER		1
+#define FPE_INTDIV	1
+#define SEGV_ACCERR	2
+
+typedef struct {
+	int		si_signo;
+	int		si_errno;
+	int		si_code;
+	int		si_pid;
+	void*		si_addr;
+	int		si_status;
+	void*		si_value;
+} ksiginfo_t;
+
+struct sigaction {
+	union {
+		void	(*sa_handler)(int);
+		void	(*sa_sigaction)(int, ksiginfo_t*, void*);
+	};
+	unsigned long	sa_mask;
+	int		sa_flags;
+};
+
+#define SIG_DFL		((void (*)(int)) 0)
+#define SIG_IGN		((void (*)(int)) 1)
+
+struct sigaltstack {
+	void*		ss_sp;
+	int		ss_flags;
+	size_t		ss_size;
+};
+
+#ifdef __x86_64__
+/* leading registers of the ucontext (see mregs_t in arch/x86_64/include/asm/stddef.h) */
+typedef struct {
+	uint64_t r15, r14, r13, r12, r9, r8, rdi, rsi, rbp, rbx, rdx, rcx, rsp, rip;
+} kmregs_t;
+#endif
+
+#endif
@@ -0,0 +1,73 @@
+/*
+ * Copyright (c) 2017, Stefan Lankes, RWTH Aachen University
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions are met:
+ *    * Redistributions of source code must retain the above copyright
+ *      notice, this list of conditions and the following disclaimer.
+ *    * Redistributions in binary form must reproduce the above copyright
+ *      notice, this list of conditions and the following disclaimer in the
+ *      documentation and/or other materials provided with the distribution.
+ *    * Neither the name of the University nor the names of its contributors
+ *      may be used to endorse or promote products derived from this
+ *      software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ * ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+ * WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * DISCLAIMED. IN NO EVENT SHALL THE REGENTS OR CONTRIBUTORS BE LIABLE FOR ANY
+ * DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+ * (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+ * LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+ * ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+ * SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+#include <fcntl.h>
+#include <unistd.h>
+#include <sys/stat.h>
+#include <hermit/syscall.h>
+
+#include "tests.h"
+
+int main(int argc, char **argv)
+{
+	const char msg[] = "Hello from tmpfs!";
+	char buf[64];
+	struct stat st;
+	int fd;
+
+	CHECK(sys_mkdir("/tmp/test", 0755) == 0);
+	CHECK(sys_mkdir("/tmp/test", 0755) < 0);
+
+	fd = sys_open("/tmp/test/file", O_RDWR|O_CREAT|O_TRUNC, 0644);
+	CHECK(fd >= 0);
+	CHECK(sys_write(fd, msg, sizeof(msg)) == sizeof(msg));
+	CHECK(sys_lseek(fd, 0, SEEK_SET) == 0);
+	memset(buf, 0x00, sizeof(buf));
+	CHECK(sys_read(fd, buf, sizeof(buf)) == sizeof(msg));
+	CHECK(strcmp(buf, msg) == 0);
+	CHECK(sys_ftruncate(fd, 5) == 0);
+	CHECK(sys_fstat(fd, &st) == 0);
+	CHECK(st.st_size == 5);
+	CHECK(sys_close(fd) == 0);
+
+	CHECK(sys_rename("/tmp/test/file", "/tmp/test/renamed") == 0);
+	CHECK(sys_stat("/tmp/test/file", &st) < 0);
+	CHECK(sys_stat("/tmp/test/renamed", &st) == 0);
+	CHECK(S_ISREG(st.st_mode) && (st.st_size == 5));
+
+	CHECK(sys_rmdir("/tmp/test") < 0);
+	CHECK(sys_unlink("/tmp/test/renamed") == 0);
+	CHECK(sys_rmdir("/tmp/test") == 0);
+
+	printf("tmpfs test passed\n");
+
+	return 0;
+}